@@ -0,0 +1,154 @@
+//! Log records emitted when the samples of the process cross thresholds,
+//! see [`crate::ProcessObserverBuilder::with_alerts`].
+
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, Severity};
+
+use crate::ProcessMetricsSnapshot;
+
+const EVENT_NAME: &str = "process.alert";
+
+/// A threshold on a value of the samples of the process. The alert fires
+/// once the value is above the threshold for [`AlertRule::for_samples`]
+/// consecutive samples, and resolves at the first sample below it.
+/// # Example
+///
+/// ```
+/// use opentelemetry_system_metrics::AlertRule;
+///
+/// let rules = [
+///     AlertRule::memory_above(2 << 30),
+///     AlertRule::cpu_above(90.0).for_samples(3),
+///     AlertRule::above("disk.written", |snapshot| snapshot.disk_written as f64, 1e9),
+/// ];
+/// ```
+///
+pub struct AlertRule {
+    name: Cow<'static, str>,
+    value: Box<dyn Fn(&ProcessMetricsSnapshot) -> f64 + Send>,
+    threshold: f64,
+    samples: usize,
+}
+
+impl AlertRule {
+    /// Fire when `value` of the samples is above `threshold`. `name` is
+    /// recorded as the `alert.name` attribute.
+    pub fn above(
+        name: impl Into<Cow<'static, str>>,
+        value: impl Fn(&ProcessMetricsSnapshot) -> f64 + Send + 'static,
+        threshold: f64,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            value: Box::new(value),
+            threshold,
+            samples: 1,
+        }
+    }
+
+    /// Fire when the resident memory is above `bytes`.
+    pub fn memory_above(bytes: u64) -> Self {
+        Self::above("memory", |snapshot| snapshot.memory as f64, bytes as f64)
+    }
+
+    /// Fire when the CPU usage is above `percent` of a single CPU.
+    pub fn cpu_above(percent: f64) -> Self {
+        Self::above("cpu", |snapshot| snapshot.cpu_usage.into(), percent)
+    }
+
+    /// Only fire after `samples` consecutive samples above the threshold.
+    /// Defaults to 1.
+    pub fn for_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+}
+
+/// A change of state of an alert.
+struct AlertEvent<'a> {
+    rule: &'a AlertRule,
+    pid: u32,
+    value: f64,
+    firing: bool,
+}
+
+/// Creates and emits a log record for the event.
+type Emit = Box<dyn FnMut(&AlertEvent) + Send>;
+
+/// The rules of an observer and their state.
+pub(crate) struct Alerts {
+    emit: Emit,
+    rules: Vec<(AlertRule, RuleState)>,
+}
+
+#[derive(Default)]
+struct RuleState {
+    // Consecutive samples above the threshold.
+    above: usize,
+    firing: bool,
+}
+
+impl Alerts {
+    pub(crate) fn new<L>(logger: L, rules: impl IntoIterator<Item = AlertRule>) -> Self
+    where
+        L: Logger + Send + 'static,
+    {
+        Self {
+            emit: Box::new(move |event| logger.emit(record(&logger, event))),
+            rules: rules
+                .into_iter()
+                .map(|rule| (rule, RuleState::default()))
+                .collect(),
+        }
+    }
+
+    /// Update the state of every rule with a new sample of `pid`.
+    pub(crate) fn check(&mut self, pid: u32, snapshot: &ProcessMetricsSnapshot) {
+        for (rule, state) in &mut self.rules {
+            let value = (rule.value)(snapshot);
+            if value > rule.threshold {
+                state.above += 1;
+            } else {
+                state.above = 0;
+            }
+            let firing = state.above >= rule.samples;
+            if firing != state.firing {
+                state.firing = firing;
+                (self.emit)(&AlertEvent {
+                    rule,
+                    pid,
+                    value,
+                    firing,
+                });
+            }
+        }
+    }
+}
+
+fn record<L: Logger>(logger: &L, event: &AlertEvent) -> L::LogRecord {
+    let (severity, state) = if event.firing {
+        (Severity::Warn, "firing")
+    } else {
+        (Severity::Info, "resolved")
+    };
+    let name = &event.rule.name;
+    let mut record = logger.create_log_record();
+    record.set_event_name(EVENT_NAME);
+    record.set_timestamp(SystemTime::now());
+    record.set_severity_number(severity);
+    record.set_severity_text(severity.name().into());
+    record.set_body(AnyValue::from(format!(
+        "Alert {name} of process {} {state}: {} for a threshold of {}",
+        event.pid, event.value, event.rule.threshold,
+    )));
+    record.add_attributes([
+        ("alert.name", AnyValue::from(name.to_string())),
+        ("alert.state", AnyValue::from(state)),
+        ("alert.value", AnyValue::from(event.value)),
+        ("alert.threshold", AnyValue::from(event.rule.threshold)),
+        ("process.pid", AnyValue::from(i64::from(event.pid))),
+    ]);
+    record
+}
@@ -0,0 +1,77 @@
+use opentelemetry::metrics::Meter;
+use opentelemetry::metrics::Unit;
+use opentelemetry::Key;
+
+use starship_battery::units::power::watt;
+use starship_battery::units::ratio::ratio;
+use starship_battery::Manager;
+
+use crate::{prefixed, Result};
+
+const BATTERY_INDEX: Key = Key::from_static_str("battery.index");
+const BATTERY_STATE: Key = Key::from_static_str("battery.state");
+
+const SYSTEM_BATTERY_CHARGE: &str = "system.battery.charge";
+const SYSTEM_BATTERY_STATE: &str = "system.battery.state";
+const SYSTEM_BATTERY_ENERGY_RATE: &str = "system.battery.energy_rate";
+
+pub(crate) fn register_battery_metrics(meter: &Meter, prefix: &str) -> Result<()> {
+    let battery_charge = meter
+        .f64_observable_gauge(prefixed(prefix, SYSTEM_BATTERY_CHARGE))
+        .with_description("The battery state of charge, from 0 to 1.")
+        .with_unit(Unit::new("1"))
+        .init();
+    let battery_state = meter
+        .u64_observable_gauge(prefixed(prefix, SYSTEM_BATTERY_STATE))
+        .with_description("Set to 1 for the current battery state.")
+        .init();
+    let battery_energy_rate = meter
+        .f64_observable_gauge(prefixed(prefix, SYSTEM_BATTERY_ENERGY_RATE))
+        .with_description("The rate at which the battery is charging or discharging.")
+        .with_unit(Unit::new("W"))
+        .init();
+
+    meter.register_callback(
+        &[
+            battery_charge.as_any(),
+            battery_state.as_any(),
+            battery_energy_rate.as_any(),
+        ],
+        move |context| {
+            // The platform manager is not guaranteed to be `Send`, so it is
+            // opened on every collection instead of being kept around.
+            let batteries = match Manager::new().and_then(|manager| manager.batteries()) {
+                Ok(batteries) => batteries,
+                Err(err) => {
+                    tracing::debug!("Could not read battery information. Error: {:?}", err);
+                    return;
+                }
+            };
+
+            for (index, battery) in batteries.flatten().enumerate() {
+                let attributes = [BATTERY_INDEX.i64(index as i64)];
+
+                context.observe_f64(
+                    &battery_charge,
+                    battery.state_of_charge().get::<ratio>().into(),
+                    &attributes,
+                );
+                context.observe_u64(
+                    &battery_state,
+                    1,
+                    &[
+                        attributes.as_slice(),
+                        &[BATTERY_STATE.string(battery.state().to_string())],
+                    ]
+                    .concat(),
+                );
+                context.observe_f64(
+                    &battery_energy_rate,
+                    battery.energy_rate().get::<watt>().into(),
+                    &attributes,
+                );
+            }
+        },
+    )?;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Observe every process matched by a configuration file, picking up the
+//! processes started afterwards.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use opentelemetry::metrics::Meter;
+use opentelemetry::KeyValue;
+use opentelemetry_system_metrics::{ObserverHandle, ProcessMetricsConfig, ProcessObserverBuilder};
+use serde::Deserialize;
+use sysinfo::{PidExt, ProcessExt, System, SystemExt};
+
+/// The configuration file, for instance:
+///
+/// ```json
+/// {
+///   "otlp_endpoint": "http://localhost:4317",
+///   "observer": { "interval": "10s", "metric_groups": { "gpu": false } },
+///   "targets": [
+///     { "name": "postgres" },
+///     { "cgroup": "/system.slice/nginx.service" },
+///     { "systemd_unit": "redis.service" }
+///   ]
+/// }
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) otlp_endpoint: Option<String>,
+    /// The configuration of the observers of every target, the interval
+    /// included. Processes are matched again every interval.
+    #[serde(default)]
+    pub(crate) observer: ProcessMetricsConfig,
+    pub(crate) targets: Vec<Matcher>,
+}
+
+/// Processes to observe.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Matcher {
+    /// Every process with this executable name.
+    Name(String),
+    /// Every process of this cgroup v2 and of its descendants, by its path
+    /// under `/sys/fs/cgroup` (Linux).
+    Cgroup(String),
+    /// Every process of the cgroup of this systemd unit, with the
+    /// `systemd.unit` attribute (Linux).
+    SystemdUnit(String),
+}
+
+impl Config {
+    pub(crate) fn read(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        serde_json::from_str(&content).map_err(|err| format!("invalid {}: {err}", path.display()))
+    }
+}
+
+/// One observer per matcher, to which the matching processes are added.
+struct Target {
+    matcher: Matcher,
+    // `None` until a process matches.
+    handle: Option<ObserverHandle>,
+}
+
+/// Match the processes every interval, forever, and observe them on `meter`.
+pub(crate) async fn run(meter: Meter, config: Config) -> Result<(), String> {
+    let interval = config.observer.interval;
+    let mut targets: Vec<Target> = config
+        .targets
+        .into_iter()
+        .map(|matcher| Target {
+            matcher,
+            handle: None,
+        })
+        .collect();
+    let mut sys = System::new();
+    loop {
+        sys.refresh_processes();
+        for target in &mut targets {
+            let pids = match target.matcher.pids(&sys) {
+                Ok(pids) => pids,
+                Err(err) => {
+                    eprintln!("warning: {err}");
+                    continue;
+                }
+            };
+            target.observe(&meter, &config.observer, pids);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+impl Target {
+    /// Observe exactly `pids`, the processes which exited are already
+    /// removed by the observer.
+    fn observe(&mut self, meter: &Meter, config: &ProcessMetricsConfig, pids: BTreeSet<u32>) {
+        if let Some(handle) = &mut self.handle {
+            for pid in handle.pids() {
+                if !pids.contains(&pid) {
+                    handle.remove_pid(pid);
+                }
+            }
+            for pid in pids {
+                // The process may have exited since it was matched.
+                let _ = handle.add_pid(pid);
+            }
+            return;
+        }
+        // The first process which can be observed creates the observer.
+        let mut pids = pids.into_iter();
+        for pid in pids.by_ref() {
+            let builder = ProcessObserverBuilder::new(meter.clone())
+                .with_config(config.clone())
+                .with_attributes(self.matcher.attributes())
+                .with_pid(pid);
+            if let Ok(handle) = builder.spawn() {
+                self.handle = Some(handle);
+                break;
+            }
+        }
+        if let Some(handle) = &mut self.handle {
+            for pid in pids {
+                let _ = handle.add_pid(pid);
+            }
+        }
+    }
+}
+
+impl Matcher {
+    fn attributes(&self) -> Vec<KeyValue> {
+        match self {
+            Matcher::SystemdUnit(unit) => vec![KeyValue::new("systemd.unit", unit.clone())],
+            Matcher::Name(_) | Matcher::Cgroup(_) => Vec::new(),
+        }
+    }
+
+    fn pids(&self, sys: &System) -> Result<BTreeSet<u32>, String> {
+        match self {
+            Matcher::Name(name) => Ok(sys
+                .processes_by_exact_name(name)
+                .map(|process| process.pid().as_u32())
+                .collect()),
+            Matcher::Cgroup(path) => cgroup_pids(path),
+            Matcher::SystemdUnit(unit) => cgroup_pids(&systemd_unit_cgroup(unit)?),
+        }
+    }
+}
+
+/// The processes of the cgroup `path` and of its descendants.
+#[cfg(target_os = "linux")]
+fn cgroup_pids(path: &str) -> Result<BTreeSet<u32>, String> {
+    let pids = opentelemetry_system_metrics::cgroup_pids(path).map_err(|err| err.to_string())?;
+    Ok(pids.into_iter().collect())
+}
+
+#[cfg(target_os = "linux")]
+fn systemd_unit_cgroup(unit: &str) -> Result<String, String> {
+    opentelemetry_system_metrics::systemd_unit_cgroup(unit).map_err(|err| err.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_pids(path: &str) -> Result<BTreeSet<u32>, String> {
+    Err(format!(
+        "could not read the cgroup {path}: only available on Linux"
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn systemd_unit_cgroup(unit: &str) -> Result<String, String> {
+    Err(format!(
+        "could not resolve the systemd unit {unit}: only available on Linux"
+    ))
+}
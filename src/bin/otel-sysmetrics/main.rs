@@ -0,0 +1,211 @@
+//! Observe a process from the outside and export its metrics over OTLP, as
+//! a sidecar of programs which are not instrumented, or print them as JSON
+//! for scripts. With a configuration file, observe every process it
+//! matches, as a small process metrics agent.
+
+mod daemon;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use opentelemetry::metrics::MeterProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_system_metrics::{process_metrics_stream, ProcessObserverBuilder};
+use sysinfo::{PidExt, ProcessExt, System, SystemExt};
+
+const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4317";
+
+const USAGE: &str = "\
+Usage: otel-sysmetrics (--pid <PID> | --name <NAME>) [OPTIONS]
+       otel-sysmetrics --config <FILE> [--otlp-endpoint <URL>]
+
+Observe a process until it exits and export its metrics over OTLP/gRPC, or
+print them to stdout. With --config, observe every process matched by the
+JSON configuration file and export their metrics until interrupted.
+
+Options:
+      --pid <PID>               PID of the process
+      --name <NAME>             Name of the process, the oldest one if several match
+      --config <FILE>           Configuration file of the processes to observe
+      --output <OUTPUT>         `otlp`, or `json` for a JSON snapshot per line [default: otlp]
+      --otlp-endpoint <URL>     OTLP/gRPC endpoint [default: http://localhost:4317]
+      --interval <SECONDS>      Time between two samples [default: 30]
+  -h, --help                    Print this help";
+
+/// The process to observe.
+enum Target {
+    Pid(u32),
+    Name(String),
+    /// The processes matched by a configuration file.
+    Config(PathBuf),
+}
+
+/// Where the metrics go.
+enum Output {
+    Otlp,
+    Json,
+}
+
+struct Args {
+    target: Target,
+    output: Output,
+    // The one of the configuration file, or the default.
+    otlp_endpoint: Option<String>,
+    interval: Duration,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut target = None;
+    let mut output = Output::Otlp;
+    let mut otlp_endpoint = None;
+    let mut interval = Duration::from_secs(30);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value of {arg}"));
+        match arg.as_str() {
+            "--pid" => {
+                let pid = value()?;
+                let pid = pid.parse().map_err(|_| format!("invalid PID {pid:?}"))?;
+                target = Some(Target::Pid(pid));
+            }
+            "--name" => target = Some(Target::Name(value()?)),
+            "--config" => target = Some(Target::Config(value()?.into())),
+            "--output" => {
+                output = match value()?.as_str() {
+                    "otlp" => Output::Otlp,
+                    "json" => Output::Json,
+                    other => return Err(format!("invalid output {other:?}")),
+                };
+            }
+            "--otlp-endpoint" => otlp_endpoint = Some(value()?),
+            "--interval" => {
+                let seconds = value()?;
+                interval = seconds
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .filter(|interval| !interval.is_zero())
+                    .ok_or(format!("invalid interval {seconds:?}"))?;
+            }
+            _ => return Err(format!("unexpected argument {arg:?}")),
+        }
+    }
+    let target = target.ok_or("--pid, --name or --config is required")?;
+    if matches!((&target, &output), (Target::Config(_), Output::Json)) {
+        return Err("--output json cannot be used with --config".to_string());
+    }
+    Ok(Args {
+        target,
+        output,
+        otlp_endpoint,
+        interval,
+    })
+}
+
+/// The PID of the oldest process named `name`.
+fn find_pid(name: &str) -> Option<u32> {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    sys.processes_by_exact_name(name)
+        .min_by_key(|process| (process.start_time(), process.pid()))
+        .map(|process| process.pid().as_u32())
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let pid = match &args.target {
+        Target::Pid(pid) => *pid,
+        Target::Name(name) => find_pid(name).ok_or(format!("no process named {name:?}"))?,
+        Target::Config(path) => {
+            let config = daemon::Config::read(path)?;
+            let runtime = tokio::runtime::Runtime::new().map_err(|err| err.to_string())?;
+            return runtime.block_on(watch(args.otlp_endpoint, config));
+        }
+    };
+    let runtime = tokio::runtime::Runtime::new().map_err(|err| err.to_string())?;
+    match args.output {
+        Output::Otlp => runtime.block_on(export(args, pid)),
+        Output::Json => runtime.block_on(print(args, pid)),
+    }
+}
+
+/// Print a snapshot per line, until the process exits.
+async fn print(args: Args, pid: u32) -> Result<(), String> {
+    let snapshots = process_metrics_stream(pid, args.interval).map_err(|err| err.to_string())?;
+    let mut snapshots = std::pin::pin!(snapshots);
+    while let Some(snapshot) = snapshots.next().await {
+        let line = serde_json::to_string(&snapshot).map_err(|err| err.to_string())?;
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn meter_provider(
+    endpoint: Option<String>,
+    period: Duration,
+) -> Result<opentelemetry_sdk::metrics::SdkMeterProvider, String> {
+    let endpoint = endpoint.unwrap_or_else(|| DEFAULT_OTLP_ENDPOINT.to_string());
+    opentelemetry_otlp::new_pipeline()
+        .metrics(opentelemetry_sdk::runtime::Tokio)
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_period(period)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// Export the metrics of the processes matched by `config` over OTLP, until
+/// interrupted.
+async fn watch(otlp_endpoint: Option<String>, config: daemon::Config) -> Result<(), String> {
+    // The command line takes precedence over the file.
+    let endpoint = otlp_endpoint.or(config.otlp_endpoint.clone());
+    let provider = meter_provider(endpoint, config.observer.interval)?;
+    let meter = provider.meter("otel-sysmetrics");
+    let watched = tokio::select! {
+        watched = daemon::run(meter, config) => watched,
+        interrupted = tokio::signal::ctrl_c() => interrupted.map_err(|err| err.to_string()),
+    };
+    // Exports the last samples.
+    let shutdown = provider.shutdown();
+    watched?;
+    shutdown.map_err(|err| err.to_string())
+}
+
+/// Export the metrics over OTLP, until the process exits.
+async fn export(args: Args, pid: u32) -> Result<(), String> {
+    let provider = meter_provider(args.otlp_endpoint, args.interval)?;
+    let observed = ProcessObserverBuilder::new(provider.meter("otel-sysmetrics"))
+        .with_pid(pid)
+        .with_interval(args.interval)
+        .run()
+        .await;
+    // Exports the last samples.
+    let shutdown = provider.shutdown();
+    observed.map_err(|err| err.to_string())?;
+    shutdown.map_err(|err| err.to_string())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
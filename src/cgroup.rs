@@ -0,0 +1,242 @@
+//! Aggregation of the processes of a cgroup v2 (Linux), see
+//! [`crate::init_process_observer_for_systemd_unit`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
+
+use crate::sampler::ProcessSampler;
+use crate::{CpuTimes, Error, MetricGroups, ProcessInfo, ProcessMetricsSnapshot, Result};
+
+/// Replaces the CPU, memory and disk usage of the wrapped sampler with the
+/// totals of a cgroup and its descendants.
+pub(crate) struct CgroupSampler {
+    sampler: Box<dyn ProcessSampler>,
+    dir: PathBuf,
+    sys: Arc<Mutex<System>>,
+    last_cpu_time: Option<(f64, Instant)>,
+}
+
+impl CgroupSampler {
+    pub(crate) fn new(
+        sampler: Box<dyn ProcessSampler>,
+        dir: PathBuf,
+        sys: Arc<Mutex<System>>,
+    ) -> Self {
+        let mut sampler = Self {
+            sampler,
+            dir,
+            sys,
+            last_cpu_time: None,
+        };
+        // As the warm up of the other samplers, for the first sample to
+        // report the usage since the registration.
+        sampler.last_cpu_time = sampler
+            .cpu_times()
+            .map(|cpu_times| (cpu_times.user + cpu_times.system, Instant::now()));
+        sampler
+    }
+
+    /// Times of the processes of the cgroup, including the ones which
+    /// already exited, from `cpu.stat`.
+    fn cpu_times(&self) -> Option<CpuTimes> {
+        let stat = fs::read_to_string(self.dir.join("cpu.stat")).ok()?;
+        let value = |key: &str| {
+            stat.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(' '))
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        // In microseconds.
+        Some(CpuTimes {
+            user: value("user_usec")? as f64 / 1e6,
+            system: value("system_usec")? as f64 / 1e6,
+        })
+    }
+
+    /// Bytes read and written by the processes of the cgroup, including
+    /// the ones which already exited, from `io.stat`. `None` without the io
+    /// controller.
+    fn disk_io(&self) -> Option<(u64, u64)> {
+        let stat = fs::read_to_string(self.dir.join("io.stat")).ok()?;
+        // Lines look like `8:0 rbytes=1459200 wbytes=314773504 rios=192
+        // wios=353 dbytes=0 dios=0`, one per device.
+        let (mut read, mut written) = (0, 0);
+        for field in stat.split_whitespace() {
+            if let Some(bytes) = field.strip_prefix("rbytes=") {
+                read += bytes.parse::<u64>().unwrap_or_default();
+            } else if let Some(bytes) = field.strip_prefix("wbytes=") {
+                written += bytes.parse::<u64>().unwrap_or_default();
+            }
+        }
+        Some((read, written))
+    }
+}
+
+/// The PIDs of the processes of a cgroup v2 and of its descendants, by its
+/// path under `/sys/fs/cgroup`, such as `/system.slice/nginx.service`.
+/// Only available on Linux.
+///
+/// Fails with [`Error::Cgroup`] if the cgroup does not exist.
+/// # Example
+///
+/// ```no_run
+/// use opentelemetry_system_metrics::{cgroup_pids, systemd_unit_cgroup};
+///
+/// let cgroup = systemd_unit_cgroup("nginx.service").unwrap();
+/// println!("{:?}", cgroup_pids(&cgroup).unwrap());
+/// ```
+///
+pub fn cgroup_pids(cgroup: &str) -> Result<Vec<u32>> {
+    let dir = cgroup_dir(cgroup);
+    if !dir.join("cgroup.procs").is_file() {
+        return Err(Error::Cgroup(cgroup.to_string()));
+    }
+    Ok(pids(&dir))
+}
+
+/// The path of the cgroup of a running systemd unit under
+/// `/sys/fs/cgroup`, from `systemctl`, see [`cgroup_pids`]. Only available
+/// on Linux.
+///
+/// Fails with [`Error::SystemdUnit`] if the unit is not running.
+pub fn systemd_unit_cgroup(unit: &str) -> Result<String> {
+    show(unit).map(|(cgroup, _)| cgroup)
+}
+
+fn cgroup_dir(cgroup: &str) -> PathBuf {
+    Path::new("/sys/fs/cgroup").join(cgroup.trim_start_matches('/'))
+}
+
+/// The processes of the cgroup `dir` and of its descendants.
+pub(crate) fn pids(dir: &Path) -> Vec<u32> {
+    let mut pids = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    // Cgroups may be removed while they are read.
+    while let Some(dir) = dirs.pop() {
+        if let Ok(procs) = fs::read_to_string(dir.join("cgroup.procs")) {
+            pids.extend(
+                procs
+                    .lines()
+                    .filter_map(|pid| pid.trim().parse::<u32>().ok()),
+            );
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                dirs.push(entry.path());
+            }
+        }
+    }
+    pids
+}
+
+/// The cgroup and the main process of a systemd unit, from `systemctl`. The
+/// main process is the first one of the cgroup for units without one, such
+/// as scopes.
+pub(crate) fn systemd_unit(unit: &str) -> Result<(PathBuf, u32)> {
+    let (cgroup, main_pid) = show(unit)?;
+    let dir = cgroup_dir(&cgroup);
+    let main_pid = main_pid
+        .or_else(|| pids(&dir).into_iter().min())
+        .ok_or_else(|| Error::SystemdUnit(unit.to_string()))?;
+    Ok((dir, main_pid))
+}
+
+/// The cgroup of a systemd unit, and its main process if it has one.
+fn show(unit: &str) -> Result<(String, Option<u32>)> {
+    let output = std::process::Command::new("systemctl")
+        .args(["show", "--property=ControlGroup,MainPID", unit])
+        .output()
+        .map_err(|err| {
+            tracing::debug!("Could not run systemctl: {err}");
+            Error::SystemdUnit(unit.to_string())
+        })?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let property = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .filter(|value| !value.is_empty())
+    };
+    // Empty for units which are not loaded or not running.
+    let cgroup = property("ControlGroup").ok_or_else(|| Error::SystemdUnit(unit.to_string()))?;
+    let main_pid = property("MainPID")
+        .and_then(|pid| pid.parse().ok())
+        .filter(|&pid| pid != 0);
+    Ok((cgroup.to_string(), main_pid))
+}
+
+impl ProcessSampler for CgroupSampler {
+    fn pid(&self) -> u32 {
+        self.sampler.pid()
+    }
+
+    fn info(&mut self) -> Option<ProcessInfo> {
+        self.sampler.info()
+    }
+
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+        let mut snapshot = self.sampler.sample()?;
+        let Some(cpu_times) = self.cpu_times() else {
+            return Some(snapshot);
+        };
+
+        let now = Instant::now();
+        let total = cpu_times.user + cpu_times.system;
+        snapshot.cpu_usage = match self.last_cpu_time.replace((total, now)) {
+            Some((last_total, last_time)) => {
+                let elapsed = now.duration_since(last_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    ((total - last_total).max(0.0) / elapsed * 100.0) as f32
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        snapshot.cpu_times = Some(cpu_times);
+        if let Some((read, written)) = self.disk_io() {
+            snapshot.disk_read = read;
+            snapshot.disk_written = written;
+        }
+
+        let mut sys = self.sys.lock().unwrap();
+        let (mut memory, mut virtual_memory) = (0, 0);
+        for pid in pids(&self.dir) {
+            let pid = Pid::from_u32(pid);
+            if sys.refresh_process_specifics(pid, ProcessRefreshKind::new()) {
+                if let Some(process) = sys.process(pid) {
+                    memory += process.memory();
+                    virtual_memory += process.virtual_memory();
+                }
+            }
+        }
+        snapshot.memory = memory;
+        snapshot.virtual_memory = virtual_memory;
+        Some(snapshot)
+    }
+
+    fn set_metric_groups(&mut self, metric_groups: MetricGroups) {
+        self.sampler.set_metric_groups(metric_groups);
+    }
+
+    fn sampled_groups(&self) -> MetricGroups {
+        self.sampler.sampled_groups()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_cgroups_fail() {
+        let cgroup = "/otel-sysmetrics-missing.slice";
+        assert!(matches!(cgroup_pids(cgroup), Err(Error::Cgroup(path)) if path == cgroup));
+    }
+}
@@ -0,0 +1,192 @@
+//! Child processes observed for as long as they run, see
+//! [`ObservedCommand`].
+
+use std::ffi::OsStr;
+use std::ops::{Deref, DerefMut};
+use std::process::ExitStatus;
+
+use tokio::process::{Child, Command};
+
+use crate::{ObserverHandle, ProcessObserverBuilder, PROCESS_ROLE};
+
+/// A [`tokio::process::Command`] whose children are observed from the time
+/// they are spawned until they exit, with the
+/// [`ProcessObserverBuilder::spawn`] of the given builder. Requires the
+/// `tokio-process` feature.
+///
+/// The command is configured through `Deref`, as a `Command` would be.
+/// # Example
+///
+/// ```no_run
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{ObservedCommand, ProcessObserverBuilder};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let observer = ProcessObserverBuilder::new(global::meter("process-meter"));
+/// let mut command = ObservedCommand::new("python", observer).with_role("trainer");
+/// command.arg("train.py");
+/// let status = command.spawn()?.wait().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+pub struct ObservedCommand {
+    command: Command,
+    observer: ProcessObserverBuilder,
+    #[cfg(feature = "exit-events")]
+    exit_events: Option<crate::exit::ExitEvents>,
+}
+
+impl ObservedCommand {
+    /// Observe the children of `Command::new(program)` with `observer`.
+    pub fn new(program: impl AsRef<OsStr>, observer: ProcessObserverBuilder) -> Self {
+        Self::from_command(Command::new(program), observer)
+    }
+
+    /// Observe the children of `command` with `observer`.
+    pub fn from_command(command: Command, observer: ProcessObserverBuilder) -> Self {
+        Self {
+            command,
+            observer,
+            #[cfg(feature = "exit-events")]
+            exit_events: None,
+        }
+    }
+
+    /// Record the `process.role` attribute on every metric of the children,
+    /// to tell them apart from the parent and from each other.
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.observer = self
+            .observer
+            .with_attributes([PROCESS_ROLE.string(role.into())]);
+        self
+    }
+
+    /// Emit a `process.exit` log record with `logger` once the child exited
+    /// and was awaited with [`ObservedChild::wait`]: an info record if it
+    /// succeeded, a warning otherwise. The record has the `process.pid`,
+    /// `process.exit.code` and, on Unix, `process.exit.signal` attributes,
+    /// and the values of the last sample of the child, taken at most an
+    /// interval before it exited.
+    ///
+    /// Requires the `exit-events` feature.
+    /// # Example
+    ///
+    /// ```no_run
+    /// use opentelemetry::global;
+    /// use opentelemetry::logs::{LoggerProvider as _, NoopLoggerProvider};
+    /// use opentelemetry_system_metrics::{ObservedCommand, ProcessObserverBuilder};
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let observer = ProcessObserverBuilder::new(global::meter("process-meter"));
+    /// let logger = NoopLoggerProvider::new().logger("workers");
+    /// let status = ObservedCommand::new("./worker", observer)
+    ///     .with_exit_events(logger)
+    ///     .spawn()?
+    ///     .wait()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    #[cfg(feature = "exit-events")]
+    pub fn with_exit_events<L>(mut self, logger: L) -> Self
+    where
+        L: opentelemetry::logs::Logger + Send + 'static,
+    {
+        let exit_events = crate::exit::ExitEvents::new(logger);
+        let latest = exit_events.latest.clone();
+        self.observer = self
+            .observer
+            .on_sample(move |snapshot| *latest.lock().unwrap() = Some(snapshot.clone()));
+        self.exit_events = Some(exit_events);
+        self
+    }
+
+    /// Spawn the command and start observing the child.
+    ///
+    /// Fails only if the command could not be spawned: when the observer
+    /// cannot be registered, the child runs unobserved, with a warning.
+    pub fn spawn(self) -> std::io::Result<ObservedChild> {
+        let Self {
+            mut command,
+            observer,
+            #[cfg(feature = "exit-events")]
+            exit_events,
+        } = self;
+        let child = command.spawn()?;
+        #[cfg(feature = "exit-events")]
+        let exit_events = child.id().zip(exit_events);
+        // `None` once the child was awaited, which cannot be the case yet.
+        let observer = child.id().and_then(|pid| {
+            observer
+                .with_pid(pid)
+                .spawn()
+                .inspect_err(|err| tracing::warn!("Could not observe the child {pid}: {err}"))
+                .ok()
+        });
+        Ok(ObservedChild {
+            child,
+            observer,
+            #[cfg(feature = "exit-events")]
+            exit_events,
+        })
+    }
+}
+
+impl Deref for ObservedCommand {
+    type Target = Command;
+
+    fn deref(&self) -> &Command {
+        &self.command
+    }
+}
+
+impl DerefMut for ObservedCommand {
+    fn deref_mut(&mut self) -> &mut Command {
+        &mut self.command
+    }
+}
+
+/// A child spawned by [`ObservedCommand::spawn`]. The observer stops once
+/// the child exited, or when this is dropped.
+pub struct ObservedChild {
+    child: Child,
+    // `None` if the observer could not be registered.
+    observer: Option<ObserverHandle>,
+    // With the PID, which the child forgets once awaited.
+    #[cfg(feature = "exit-events")]
+    exit_events: Option<(u32, crate::exit::ExitEvents)>,
+}
+
+impl ObservedChild {
+    /// The OS-assigned process identifier of the child, `None` once it was
+    /// awaited.
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// The child, for instance to take its standard streams or kill it.
+    pub fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// The handle of the observer, `None` if it could not be registered or
+    /// the child was awaited.
+    pub fn observer(&self) -> Option<&ObserverHandle> {
+        self.observer.as_ref()
+    }
+
+    /// Wait for the child to exit, and stop the observer.
+    pub async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        let status = self.child.wait().await;
+        if let Some(observer) = self.observer.take() {
+            observer.stop();
+        }
+        #[cfg(feature = "exit-events")]
+        if let (Ok(status), Some((pid, mut exit_events))) = (&status, self.exit_events.take()) {
+            exit_events.emit(pid, *status);
+        }
+        status
+    }
+}
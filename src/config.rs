@@ -0,0 +1,844 @@
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+
+/// Settings of a process observer, see [`crate::ProcessObserverBuilder`].
+///
+/// With the `serde` feature, the configuration can be deserialized, for
+/// instance from the configuration file of the application. Missing fields
+/// keep their default value and durations are numbers of seconds or strings
+/// such as `"500ms"`, `"30s"`, `"5m"` or `"1h"`.
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use std::time::Duration;
+///
+/// use opentelemetry_system_metrics::{CommandAttribute, ProcessMetricsConfig};
+///
+/// let config: ProcessMetricsConfig = serde_json::from_str(
+///     r#"{
+///         "interval": "10s",
+///         "gpu_devices": { "indices": [0, 1] },
+///         "command": { "truncate": 64 },
+///         "metric_groups": { "disk": false },
+///         "attributes": { "service.name": "trainer" }
+///     }"#,
+/// )
+/// .unwrap();
+/// assert_eq!(config.interval, Duration::from_secs(10));
+/// assert_eq!(config.command, CommandAttribute::Truncate(64));
+/// assert!(!config.metric_groups.disk && config.metric_groups.cpu);
+/// # }
+/// ```
+///
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(default))]
+pub struct ProcessMetricsConfig {
+    /// GPU devices polled for the observed process.
+    pub gpu_devices: GpuDevices,
+    /// Record the CPU, memory and disk usage with the instruments of the
+    /// OpenTelemetry semantic conventions instead of the historical ones:
+    /// a `process.cpu.utilization` ratio, `process.memory.usage` and
+    /// `process.memory.virtual` up-down counters, and the `disk.io.direction`
+    /// attribute on the `process.disk.io` counter. GPU metrics and
+    /// `process.cpu.time`, recorded in both modes, are not affected.
+    pub semconv: bool,
+    /// What the CPU utilization of the process is divided by.
+    pub cpu_normalization: CpuNormalization,
+    /// How long to wait for the process to exist when registering the
+    /// observer. `None` fails right away.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub wait_for_process: Option<Duration>,
+    /// What the observer does once the process exited.
+    pub on_exit: OnExit,
+    /// Time between two samples when the process is sampled on its own
+    /// schedule, with [`crate::ProcessObserverBuilder::spawn`], rather than
+    /// on every collection. Zero is rejected when deserialized.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::interval"))]
+    pub interval: Duration,
+    /// How the command line is recorded as the `process.command` attribute.
+    pub command: CommandAttribute,
+    /// Groups of metrics sampled and recorded.
+    pub metric_groups: MetricGroups,
+    /// Attributes recorded on every metric, in addition to the ones
+    /// identifying the process. Deserialized from a map of names to
+    /// strings, numbers or booleans.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::attributes"))]
+    pub attributes: Vec<KeyValue>,
+    /// Window of the samples `process.memory.growth_rate` is computed over.
+    /// Longer windows are less sensitive to allocation bursts.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::duration"))]
+    pub memory_growth_window: Duration,
+    /// Also sample the CPU usage alone every `cpu_sampling_interval`
+    /// between two samples, so that short spikes are not missed with long
+    /// intervals. The minimum, average and maximum usage since the previous
+    /// collection are recorded as `process.cpu.usage.aggregate`, with the
+    /// `aggregation` attribute set to `min`, `avg` or `max`, in the unit of
+    /// `process.cpu.usage`. Only used with
+    /// [`crate::ProcessObserverBuilder::spawn`] and `run`, which sample on
+    /// their own schedule. `None` by default.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub cpu_sampling_interval: Option<Duration>,
+    /// Record the CPU utilization, `process.cpu.usage` or
+    /// `process.cpu.utilization` with [`ProcessMetricsConfig::semconv`],
+    /// into a histogram of every sample instead of a gauge of the latest
+    /// one, so that backends can compute its percentiles over time. The CPU
+    /// usages sampled with [`ProcessMetricsConfig::cpu_sampling_interval`]
+    /// are recorded too.
+    pub cpu_histogram: bool,
+    /// Minimum time between two samples of every group of metrics, for
+    /// instance to poll the GPU drivers less often. In between, the previous
+    /// values of the group are recorded.
+    pub group_intervals: GroupIntervals,
+    /// Delay the first sample of [`crate::ProcessObserverBuilder::spawn`]
+    /// and `run` by a random duration up to `start_jitter`. The following
+    /// samples keep the random phase, so that many processes started at the
+    /// same time do not all sample at the same instant. Zero by default.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::duration"))]
+    pub start_jitter: Duration,
+    /// Record `process.thread.cpu.time`, the CPU time of the threads which
+    /// used the most CPU since the previous sample, up to
+    /// `thread_cpu_top`, with the `thread.id` and `thread.name` attributes.
+    /// Part of the `cpu` group, only available on Linux. Every thread is
+    /// read, which is slower for processes with many threads. `None` by
+    /// default.
+    pub thread_cpu_top: Option<usize>,
+    /// Record the physical footprint of the process as
+    /// `process.memory.usage` on macOS, the memory shown by Activity
+    /// Monitor, instead of its resident memory. It includes the compressed
+    /// memory and leaves out the shared pages. Other processes than the
+    /// current one need `task_for_pid` rights, usually only granted to root;
+    /// the resident memory is recorded when they are denied. `false` by
+    /// default.
+    pub memory_footprint: bool,
+    /// Turn the observers into no-ops, which neither read the process nor
+    /// load the GPU drivers, nor register any instrument. Observers are
+    /// disabled whatever the configuration when `OTEL_SDK_DISABLED` is
+    /// `true`, so that operators can disable the telemetry without code
+    /// changes. `false` by default.
+    pub disabled: bool,
+    /// Prepended to the name of every instrument, such as `myapp.` for
+    /// `myapp.process.memory.usage`, for collectors routing metrics by name.
+    /// Empty by default.
+    pub metric_prefix: String,
+    /// Where the attributes identifying the process are recorded, on every
+    /// data point by default.
+    pub identity: IdentityAttributes,
+    /// Skip the data points which changed by at most `suppress_unchanged`
+    /// since they were last recorded, to cut the export volume of idle
+    /// processes. The difference is in the unit of every metric, so values
+    /// below 1 only tolerate changes of fractional values such as the CPU
+    /// usage, the others having to be equal. `process.alive` and
+    /// `process.info` are always recorded. Backends may consider the
+    /// skipped series stale. `None` by default.
+    pub suppress_unchanged: Option<f64>,
+    /// What to do with the values which do not fit in their instrument.
+    pub on_overflow: OnOverflow,
+}
+
+impl Default for ProcessMetricsConfig {
+    fn default() -> Self {
+        Self {
+            gpu_devices: GpuDevices::default(),
+            semconv: false,
+            cpu_normalization: CpuNormalization::default(),
+            wait_for_process: None,
+            on_exit: OnExit::default(),
+            interval: Duration::from_secs(30),
+            command: CommandAttribute::default(),
+            metric_groups: MetricGroups::default(),
+            attributes: Vec::new(),
+            memory_growth_window: Duration::from_secs(10 * 60),
+            cpu_sampling_interval: None,
+            cpu_histogram: false,
+            group_intervals: GroupIntervals::default(),
+            start_jitter: Duration::ZERO,
+            thread_cpu_top: None,
+            memory_footprint: false,
+            disabled: false,
+            metric_prefix: String::new(),
+            identity: IdentityAttributes::default(),
+            suppress_unchanged: None,
+            on_overflow: OnOverflow::default(),
+        }
+    }
+}
+
+impl ProcessMetricsConfig {
+    /// The default configuration, overridden by the environment variables:
+    /// - `OTEL_PROCESS_METRICS_INTERVAL`: [`ProcessMetricsConfig::interval`],
+    ///   in milliseconds
+    /// - `OTEL_PROCESS_METRICS_DISABLED`: comma separated metric groups to
+    ///   disable among `cpu`, `memory`, `disk` and `gpu`
+    /// - `OTEL_PROCESS_METRICS_ATTRIBUTES`: comma separated `key=value`
+    ///   attributes, added to [`ProcessMetricsConfig::attributes`]
+    /// - `OTEL_SDK_DISABLED`: `true` sets [`ProcessMetricsConfig::disabled`]
+    /// - `OTEL_PROCESS_METRICS_PREFIX`:
+    ///   [`ProcessMetricsConfig::metric_prefix`], also used by
+    ///   [`crate::init_host_observer`]
+    ///
+    /// Invalid values are ignored with a warning.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        crate::env::apply(&mut config);
+        config
+    }
+
+    /// Whether the CPU usage is a percentage of the physical cores, see
+    /// [`CpuNormalization::PhysicalCoresPercent`].
+    pub(crate) fn cpu_percent(&self) -> bool {
+        !self.semconv && self.cpu_normalization == CpuNormalization::PhysicalCoresPercent
+    }
+}
+
+/// Groups of metrics recorded by the observer, every group but `pressure`
+/// and `network` by default.
+/// Disabled groups are neither sampled nor registered on the meter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(default))]
+pub struct MetricGroups {
+    /// `process.cpu.usage`, `process.cpu.utilization`, `process.cpu.time`,
+    /// `process.cpu.usage.aggregate`, `process.cpu.allowed`, `process.nice`,
+    /// `process.priority_class`,
+    /// `process.thread.cpu.time`, `process.group.cpu.usage`, and
+    /// `process.delay.time` with the `taskstats` feature.
+    pub cpu: bool,
+    /// `process.memory.usage`, `process.memory.virtual`,
+    /// `process.memory.swap`, `process.memory.growth_rate`,
+    /// `process.memory.breakdown`, `process.memory.peak`,
+    /// `process.memory.virtual.peak`, `process.memory.hugepages`,
+    /// `process.memory.locked`, `process.oom.score`,
+    /// `process.oom.score_adj`, `process.cgroup.oom_kills`,
+    /// `process.handle.count`, `process.gui_objects`,
+    /// `process.group.memory.usage`, `process.group.memory.virtual`,
+    /// `process.memory.pss` and
+    /// `process.memory.uss` with the `smaps` feature, and
+    /// `process.memory.numa` with the `numa` feature.
+    pub memory: bool,
+    /// `process.disk.io` and `process.disk.io.rate`.
+    pub disk: bool,
+    /// `process.gpu.memory.usage` and `process.gpu.utilization`. When
+    /// disabled, the GPU drivers are not loaded.
+    pub gpu: bool,
+    /// `process.cgroup.pressure.stall` and
+    /// `process.cgroup.pressure.stall.time`, the pressure stall information
+    /// of the cgroup v2 of the process. Only available on Linux, disabled by
+    /// default.
+    pub pressure: bool,
+    /// `process.network.connections`, the sockets of the process by
+    /// protocol and TCP state. Only available on Linux and Windows, disabled
+    /// by default as it reads the socket tables of the host.
+    pub network: bool,
+}
+
+impl Default for MetricGroups {
+    fn default() -> Self {
+        Self {
+            cpu: true,
+            memory: true,
+            disk: true,
+            gpu: true,
+            pressure: false,
+            network: false,
+        }
+    }
+}
+
+/// Minimum time between two samples of every group of [`MetricGroups`].
+/// `None`, the default, samples the group with every sample of the process.
+/// Groups are only sampled along with the process, so their interval is in
+/// effect rounded up to a multiple of the interval of the observer.
+///
+/// Only used by the default sampler, not by custom
+/// [`crate::ProcessSampler`]s.
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{GroupIntervals, ProcessObserverBuilder};
+///
+/// let meter = global::meter("process-meter");
+/// ProcessObserverBuilder::new(meter)
+///     .with_interval(Duration::from_secs(10))
+///     .with_group_intervals(GroupIntervals {
+///         gpu: Some(Duration::from_secs(60)),
+///         ..Default::default()
+///     })
+///     .spawn();
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(default))]
+pub struct GroupIntervals {
+    /// CPU usage and times.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub cpu: Option<Duration>,
+    /// Memory usage.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub memory: Option<Duration>,
+    /// Disk usage.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub disk: Option<Duration>,
+    /// GPU usage, the most expensive to sample.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub gpu: Option<Duration>,
+    /// Pressure stall information.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub pressure: Option<Duration>,
+    /// Sockets.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::optional_duration"))]
+    pub network: Option<Duration>,
+}
+
+/// Behavior of the observer once the observed process exited. In every
+/// case the process is not sampled anymore. While it runs, the
+/// `process.alive` gauge is 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OnExit {
+    /// Stop recording, the instruments report nothing anymore, for alerts on
+    /// the absence of `process.alive`.
+    #[default]
+    Stop,
+    /// Record `process.alive` as 0 once, then stop.
+    ReportExit,
+}
+
+/// Behavior of the observer when a value does not fit in its instrument,
+/// such as a memory usage above `i64::MAX` bytes for the `i64` gauges. The
+/// other data points are recorded in every case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OnOverflow {
+    /// Skip the data point, with a warning counting the overflows.
+    #[default]
+    Skip,
+    /// Record the largest value of the instrument instead, with a warning
+    /// counting the overflows.
+    Saturate,
+    /// Skip the data point and report the overflow to the OpenTelemetry error
+    /// handler, see `opentelemetry::global::set_error_handler`, for tests and
+    /// strict deployments to catch the overflows.
+    Strict,
+}
+
+/// Where the attributes identifying the process are recorded: `process.pid`,
+/// `process.executable.name`, `process.executable.path`, `process.command`,
+/// `process.owner` and the other attributes read from the process. The
+/// attributes of [`ProcessMetricsConfig::attributes`] are always on every
+/// data point.
+///
+/// Recording them on every data point inflates the payloads at short
+/// intervals, the other modes keep them out of the data points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum IdentityAttributes {
+    /// On every data point.
+    #[default]
+    DataPoints,
+    /// On the data points of a `process.info` gauge of value 1, to join
+    /// the other metrics on. They keep `process.pid`, to tell the processes
+    /// apart.
+    InfoMetric,
+    /// Nowhere, for the `Resource` of the meter provider to describe the
+    /// process, for instance with `process_resource` of the `sdk` feature.
+    /// Only suited to meter providers observing a single process.
+    Resource,
+}
+
+/// Recording of the command line of the process as the `process.command`
+/// attribute, attached to every data point. Command lines may contain
+/// secrets, and are unique per process in some deployments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum CommandAttribute {
+    /// The whole command line, arguments joined with spaces.
+    #[default]
+    Full,
+    /// Do not record the attribute.
+    Omit,
+    /// The first characters of the command line.
+    Truncate(usize),
+    /// A hash of the command line, in hexadecimal, to tell processes apart
+    /// without recording the command line. The hash is stable across
+    /// restarts and versions of the crate.
+    Hash,
+    /// Replace the value of arguments containing one of the patterns with
+    /// `[REDACTED]`: the part after the first `=` for `--token=value`, the
+    /// next argument when the argument is exactly a pattern, as in
+    /// `--token value`, and the whole argument otherwise.
+    Redact(Vec<String>),
+}
+
+const REDACTED: &str = "[REDACTED]";
+
+impl CommandAttribute {
+    /// The value of the attribute, `None` when it is not recorded.
+    pub(crate) fn apply(&self, command: &[String]) -> Option<String> {
+        match self {
+            CommandAttribute::Full => Some(command.join(" ")),
+            CommandAttribute::Omit => None,
+            CommandAttribute::Truncate(length) => {
+                Some(command.join(" ").chars().take(*length).collect())
+            }
+            CommandAttribute::Hash => Some(format!("{:016x}", fnv1a(&command.join(" ")))),
+            CommandAttribute::Redact(patterns) => {
+                let mut redact_next = false;
+                let arguments: Vec<String> = command
+                    .iter()
+                    .map(|argument| {
+                        if std::mem::take(&mut redact_next) {
+                            return REDACTED.to_string();
+                        }
+                        let Some(pattern) = patterns
+                            .iter()
+                            .find(|pattern| argument.contains(pattern.as_str()))
+                        else {
+                            return argument.clone();
+                        };
+                        if argument == pattern {
+                            redact_next = true;
+                            argument.clone()
+                        } else if let Some((name, _)) = argument.split_once('=') {
+                            format!("{name}={REDACTED}")
+                        } else {
+                            REDACTED.to_string()
+                        }
+                    })
+                    .collect();
+                Some(arguments.join(" "))
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a, unlike the hasher of the standard library its output is
+/// specified.
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Normalization of the CPU utilization of the process, recorded as
+/// `process.cpu.usage`, or `process.cpu.utilization` with
+/// [`ProcessMetricsConfig::semconv`].
+///
+/// Values normalized explicitly are ratios between 0 and 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum CpuNormalization {
+    /// The percentage of CPU in use divided by the number of physical
+    /// cores, without unit, as recorded before the normalization was
+    /// configurable. With SMT, a process using every logical CPU reports
+    /// more than 100. The ratio over the physical cores with
+    /// [`ProcessMetricsConfig::semconv`].
+    #[default]
+    PhysicalCoresPercent,
+    /// Divide by the number of physical cores. With SMT, a process using
+    /// every logical CPU reports more than 1.
+    PhysicalCores,
+    /// Divide by the number of logical CPUs, so that a process using every
+    /// CPU reports 1.
+    LogicalCores,
+    /// Divide by the number of CPUs in the affinity mask of the process
+    /// when the observer is registered, so that a process pinned to 2 CPUs
+    /// and using both reports 1. Only available on Linux, other platforms
+    /// divide by the number of logical CPUs.
+    AllowedCpus,
+    /// Report the usage relative to a single CPU, as a percentage, or a
+    /// ratio with [`ProcessMetricsConfig::semconv`]. A process using several
+    /// CPUs reports more than 100%.
+    None,
+}
+
+/// Selection of the GPU devices polled for the observed process.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum GpuDevices {
+    /// Devices listed in the `CUDA_VISIBLE_DEVICES` environment variable of
+    /// the observed process, or every device if it is not set. Without
+    /// `CUDA_DEVICE_ORDER=PCI_BUS_ID`, indices of different GPU models cannot
+    /// be mapped to the driver and every device is polled.
+    #[default]
+    Visible,
+    /// Every device reported by the driver.
+    All,
+    /// Devices with the given driver indices.
+    Indices(Vec<u32>),
+}
+
+/// A single entry of a device list, an index or a UUID (prefix).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DeviceId {
+    /// An NVML index, in the order of the PCI bus IDs.
+    Index(u32),
+    /// A CUDA index in the default `FASTEST_FIRST` order, which is the order
+    /// of NVML only when the devices are identical.
+    CudaIndex(u32),
+    Uuid(String),
+}
+
+impl DeviceId {
+    /// `index` is `None` for devices without a CUDA index, such as MIG
+    /// instances which can only be selected by UUID.
+    pub(crate) fn matches(&self, index: Option<u32>, uuid: &str) -> bool {
+        match self {
+            DeviceId::Index(id) | DeviceId::CudaIndex(id) => Some(*id) == index,
+            DeviceId::Uuid(prefix) => uuid.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+impl GpuDevices {
+    /// Resolve the selection against `CUDA_VISIBLE_DEVICES` and
+    /// `CUDA_DEVICE_ORDER` of the observed process. `None` means every device
+    /// is polled.
+    pub(crate) fn resolve(
+        &self,
+        cuda_visible_devices: Option<&str>,
+        cuda_device_order: Option<&str>,
+    ) -> Option<Vec<DeviceId>> {
+        let pci_bus_order = cuda_device_order.map(str::trim) == Some("PCI_BUS_ID");
+        match self {
+            GpuDevices::Visible => cuda_visible_devices
+                .map(|devices| parse_cuda_visible_devices(devices, pci_bus_order)),
+            GpuDevices::All => None,
+            GpuDevices::Indices(indices) => {
+                Some(indices.iter().copied().map(DeviceId::Index).collect())
+            }
+        }
+    }
+}
+
+/// Parse `CUDA_VISIBLE_DEVICES` the way the CUDA runtime does: entries are
+/// indices or UUIDs, and everything after the first invalid entry is ignored.
+/// The indices are NVML indices only in the `PCI_BUS_ID` order.
+fn parse_cuda_visible_devices(value: &str, pci_bus_order: bool) -> Vec<DeviceId> {
+    value
+        .split(',')
+        .map(str::trim)
+        .map_while(|entry| {
+            if let Ok(index) = entry.parse::<u32>() {
+                Some(if pci_bus_order {
+                    DeviceId::Index(index)
+                } else {
+                    DeviceId::CudaIndex(index)
+                })
+            } else if entry.starts_with("GPU-") || entry.starts_with("MIG-") {
+                Some(DeviceId::Uuid(entry.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Deserialization of the fields without a convenient serde representation.
+#[cfg(feature = "serde")]
+mod de {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use opentelemetry::KeyValue;
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDuration {
+        Seconds(f64),
+        Text(String),
+    }
+
+    impl RawDuration {
+        fn parse<E: serde::de::Error>(self) -> Result<Duration, E> {
+            let (value, unit) = match self {
+                RawDuration::Seconds(seconds) => (seconds, 1.0),
+                RawDuration::Text(text) => {
+                    let text = text.trim();
+                    let split = text
+                        .find(|c: char| !c.is_ascii_digit() && c != '.')
+                        .unwrap_or(text.len());
+                    let unit = match text[split..].trim() {
+                        "ms" => 0.001,
+                        "" | "s" => 1.0,
+                        "m" => 60.0,
+                        "h" => 3600.0,
+                        unit => return Err(E::custom(format!("unknown duration unit {unit:?}"))),
+                    };
+                    let value = text[..split]
+                        .parse()
+                        .map_err(|_| E::custom(format!("invalid duration {text:?}")))?;
+                    (value, unit)
+                }
+            };
+            Duration::try_from_secs_f64(value * unit).map_err(E::custom)
+        }
+    }
+
+    pub(super) fn duration<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        RawDuration::deserialize(deserializer)?.parse()
+    }
+
+    /// A duration between two samples, which would busy loop if zero.
+    pub(super) fn interval<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        let interval = duration(deserializer)?;
+        if interval.is_zero() {
+            return Err(serde::de::Error::custom("the interval cannot be zero"));
+        }
+        Ok(interval)
+    }
+
+    pub(super) fn optional_duration<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<RawDuration>::deserialize(deserializer)?
+            .map(RawDuration::parse)
+            .transpose()
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawValue {
+        Bool(bool),
+        I64(i64),
+        F64(f64),
+        String(String),
+    }
+
+    pub(super) fn attributes<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<KeyValue>, D::Error> {
+        let attributes = BTreeMap::<String, RawValue>::deserialize(deserializer)?;
+        Ok(attributes
+            .into_iter()
+            .map(|(key, value)| match value {
+                RawValue::Bool(value) => KeyValue::new(key, value),
+                RawValue::I64(value) => KeyValue::new(key, value),
+                RawValue::F64(value) => KeyValue::new(key, value),
+                RawValue::String(value) => KeyValue::new(key, value),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn command_attribute() {
+        let redact = |patterns: &[&str]| CommandAttribute::Redact(command(patterns));
+        let cases = [
+            (
+                CommandAttribute::Full,
+                &["app", "--port", "80"][..],
+                Some("app --port 80"),
+            ),
+            (CommandAttribute::Omit, &["app"], None),
+            (
+                CommandAttribute::Truncate(5),
+                &["héllo", "world"],
+                Some("héllo"),
+            ),
+            (CommandAttribute::Truncate(100), &["app"], Some("app")),
+            // Test vectors of FNV-1a.
+            (CommandAttribute::Hash, &[], Some("cbf29ce484222325")),
+            (CommandAttribute::Hash, &["a"], Some("af63dc4c8601ec8c")),
+            (
+                CommandAttribute::Hash,
+                &["foobar"],
+                Some("85944171f73967e8"),
+            ),
+            (
+                redact(&["token"]),
+                &["app", "--token=abc", "-v"],
+                Some("app --token=[REDACTED] -v"),
+            ),
+            (
+                redact(&["--token"]),
+                &["app", "--token", "abc", "-v"],
+                Some("app --token [REDACTED] -v"),
+            ),
+            (
+                redact(&["token"]),
+                &["app", "mytoken"],
+                Some("app [REDACTED]"),
+            ),
+            (
+                redact(&["password", "--secret"]),
+                &["app", "--secret", "s", "--password=p"],
+                Some("app --secret [REDACTED] --password=[REDACTED]"),
+            ),
+            // A pattern as the last argument has no value to redact.
+            (
+                redact(&["--token"]),
+                &["app", "--token"],
+                Some("app --token"),
+            ),
+            (
+                redact(&[]),
+                &["app", "--token=abc"],
+                Some("app --token=abc"),
+            ),
+        ];
+        for (attribute, arguments, expected) in cases {
+            assert_eq!(
+                attribute.apply(&command(arguments)).as_deref(),
+                expected,
+                "{attribute:?} {arguments:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn gpu_devices() {
+        use DeviceId::{CudaIndex, Index, Uuid};
+
+        let uuid = |uuid: &str| Uuid(uuid.to_string());
+        let cases = [
+            (GpuDevices::Visible, None, None, None),
+            (
+                GpuDevices::Visible,
+                Some("0,2"),
+                None,
+                Some(vec![CudaIndex(0), CudaIndex(2)]),
+            ),
+            (
+                GpuDevices::Visible,
+                Some("0, 2"),
+                Some("PCI_BUS_ID"),
+                Some(vec![Index(0), Index(2)]),
+            ),
+            (
+                GpuDevices::Visible,
+                Some("1"),
+                Some("FASTEST_FIRST"),
+                Some(vec![CudaIndex(1)]),
+            ),
+            (
+                GpuDevices::Visible,
+                Some("GPU-8f2a,MIG-1c3d,1"),
+                None,
+                Some(vec![uuid("GPU-8f2a"), uuid("MIG-1c3d"), CudaIndex(1)]),
+            ),
+            // Everything after the first invalid entry is ignored.
+            (
+                GpuDevices::Visible,
+                Some("1,x,2"),
+                None,
+                Some(vec![CudaIndex(1)]),
+            ),
+            (GpuDevices::Visible, Some(""), None, Some(vec![])),
+            (GpuDevices::Visible, Some("-1"), None, Some(vec![])),
+            (GpuDevices::All, Some("0"), None, None),
+            (
+                GpuDevices::Indices(vec![3]),
+                Some("0"),
+                None,
+                Some(vec![Index(3)]),
+            ),
+        ];
+        for (devices, visible, order, expected) in cases {
+            assert_eq!(
+                devices.resolve(visible, order),
+                expected,
+                "{devices:?} {visible:?} {order:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn device_id() {
+        let uuid = "GPU-8f2a6b1e-0000-0000-0000-000000000000";
+        let cases = [
+            (DeviceId::Index(1), Some(1), true),
+            (DeviceId::Index(1), Some(0), false),
+            (DeviceId::CudaIndex(1), Some(1), true),
+            (DeviceId::Index(0), None, false),
+            (DeviceId::Uuid("GPU-8f2a".to_string()), None, true),
+            (DeviceId::Uuid(uuid.to_string()), Some(0), true),
+            (DeviceId::Uuid("GPU-9".to_string()), Some(0), false),
+        ];
+        for (id, index, expected) in cases {
+            assert_eq!(id.matches(index, uuid), expected, "{id:?} {index:?}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn durations() {
+        let cases = [
+            (serde_json::json!(2), Some(Duration::from_secs(2))),
+            (serde_json::json!(0.25), Some(Duration::from_millis(250))),
+            (serde_json::json!("500ms"), Some(Duration::from_millis(500))),
+            (serde_json::json!("10"), Some(Duration::from_secs(10))),
+            (serde_json::json!("10s"), Some(Duration::from_secs(10))),
+            (serde_json::json!("1.5m"), Some(Duration::from_secs(90))),
+            (serde_json::json!(" 2 h "), Some(Duration::from_secs(7200))),
+            (serde_json::json!("5d"), None),
+            (serde_json::json!("ms"), None),
+            (serde_json::json!("1.2.3s"), None),
+            (serde_json::json!(-1), None),
+            (serde_json::json!(0), None),
+            (serde_json::json!("0s"), None),
+            (serde_json::json!("0ms"), None),
+            (serde_json::json!(true), None),
+        ];
+        for (interval, expected) in cases {
+            let config = serde_json::from_value::<ProcessMetricsConfig>(
+                serde_json::json!({ "interval": interval.clone() }),
+            );
+            assert_eq!(
+                config.ok().map(|config| config.interval),
+                expected,
+                "{interval}"
+            );
+        }
+        let config: ProcessMetricsConfig =
+            serde_json::from_value(serde_json::json!({ "wait_for_process": null })).unwrap();
+        assert_eq!(config.wait_for_process, None);
+        let config: ProcessMetricsConfig =
+            serde_json::from_value(serde_json::json!({ "wait_for_process": "1m" })).unwrap();
+        assert_eq!(config.wait_for_process, Some(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,120 @@
+//! CPU time spent by a process, which sysinfo only exposes as a percentage.
+
+/// Time spent by a process on the CPU since it started, in seconds.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CpuTimes {
+    /// Recorded with the `user` state of `process.cpu.time`.
+    pub user: f64,
+    /// Recorded with the `system` state of `process.cpu.time`.
+    pub system: f64,
+}
+
+impl CpuTimes {
+    /// `None` when the platform is not supported or the process is gone.
+    pub(crate) fn read(pid: u32) -> Option<Self> {
+        read(pid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read(pid: u32) -> Option<CpuTimes> {
+    let stat = crate::linux::ProcStat::read(pid)?;
+    let ticks = crate::linux::clock_ticks_per_second() as f64;
+    Some(CpuTimes {
+        user: stat.utime as f64 / ticks,
+        system: stat.stime as f64 / ticks,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn read(pid: u32) -> Option<CpuTimes> {
+    use std::mem;
+
+    let mut info: libc::proc_taskinfo = unsafe { mem::zeroed() };
+    let size = mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    // SAFETY: `info` is a valid buffer of `size` bytes. Unlike `task_info`,
+    // this does not need the task port of other processes.
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            libc::PROC_PIDTASKINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    if written != size {
+        return None;
+    }
+
+    // Times are in Mach absolute time units, which are not nanoseconds on
+    // Apple Silicon.
+    let mut timebase = mach2::mach_time::mach_timebase_info::default();
+    // SAFETY: `timebase` is a valid pointer.
+    unsafe { mach2::mach_time::mach_timebase_info(&mut timebase) };
+    let nanoseconds = if timebase.denom > 0 {
+        f64::from(timebase.numer) / f64::from(timebase.denom)
+    } else {
+        1.0
+    };
+    Some(CpuTimes {
+        user: info.pti_total_user as f64 * nanoseconds / 1e9,
+        system: info.pti_total_system as f64 * nanoseconds / 1e9,
+    })
+}
+
+#[cfg(windows)]
+fn read(pid: u32) -> Option<CpuTimes> {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME};
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    // SAFETY: the handle is checked and closed below.
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle == 0 {
+        return None;
+    }
+    let empty = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    let (mut creation, mut exit, mut kernel, mut user) = (empty, empty, empty, empty);
+    // SAFETY: every pointer is valid for the duration of the call.
+    let ok = unsafe { GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) };
+    // SAFETY: `handle` was opened above and is not used afterwards.
+    unsafe { CloseHandle(handle) };
+    if ok == 0 {
+        return None;
+    }
+
+    // FILETIME counts 100 nanosecond intervals.
+    let seconds = |time: FILETIME| {
+        ((u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime)) as f64 / 1e7
+    };
+    Some(CpuTimes {
+        user: seconds(user),
+        system: seconds(kernel),
+    })
+}
+
+#[cfg(target_os = "freebsd")]
+fn read(pid: u32) -> Option<CpuTimes> {
+    let usage = crate::freebsd::kinfo_proc(pid)?.ki_rusage;
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1e6;
+    Some(CpuTimes {
+        user: seconds(usage.ru_utime),
+        system: seconds(usage.ru_stime),
+    })
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    windows
+)))]
+fn read(_pid: u32) -> Option<CpuTimes> {
+    None
+}
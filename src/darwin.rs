@@ -0,0 +1,100 @@
+//! macOS specific readers for information sysinfo does not expose.
+
+use std::mem;
+
+use mach2::kern_return::KERN_SUCCESS;
+use mach2::mach_port::mach_port_deallocate;
+use mach2::message::mach_msg_type_number_t;
+use mach2::port::{mach_port_t, MACH_PORT_NULL};
+use mach2::task::task_info;
+use mach2::task_info::TASK_VM_INFO;
+use mach2::traps::{mach_task_self, task_for_pid};
+
+/// `task_vm_info_data_t` up to revision 3, from `<mach/task_info.h>`.
+#[repr(C, packed(4))]
+#[derive(Default, Clone, Copy)]
+pub(crate) struct TaskVmInfo {
+    pub(crate) virtual_size: u64,
+    pub(crate) region_count: i32,
+    pub(crate) page_size: i32,
+    pub(crate) resident_size: u64,
+    pub(crate) resident_size_peak: u64,
+    pub(crate) device: u64,
+    pub(crate) device_peak: u64,
+    pub(crate) internal: u64,
+    pub(crate) internal_peak: u64,
+    pub(crate) external: u64,
+    pub(crate) external_peak: u64,
+    pub(crate) reusable: u64,
+    pub(crate) reusable_peak: u64,
+    pub(crate) purgeable_volatile_pmap: u64,
+    pub(crate) purgeable_volatile_resident: u64,
+    pub(crate) purgeable_volatile_virtual: u64,
+    pub(crate) compressed: u64,
+    pub(crate) compressed_peak: u64,
+    pub(crate) compressed_lifetime: u64,
+    // rev1
+    pub(crate) phys_footprint: u64,
+    // rev2
+    pub(crate) min_address: u64,
+    pub(crate) max_address: u64,
+    // rev3
+    pub(crate) ledger_phys_footprint_peak: i64,
+    pub(crate) ledger_purgeable_nonvolatile: i64,
+    pub(crate) ledger_purgeable_novolatile_compressed: i64,
+    pub(crate) ledger_purgeable_volatile: i64,
+    pub(crate) ledger_purgeable_volatile_compressed: i64,
+    pub(crate) ledger_tag_network_nonvolatile: i64,
+    pub(crate) ledger_tag_network_nonvolatile_compressed: i64,
+    pub(crate) ledger_tag_network_volatile: i64,
+    pub(crate) ledger_tag_network_volatile_compressed: i64,
+    pub(crate) ledger_tag_media_footprint: i64,
+    pub(crate) ledger_tag_media_footprint_compressed: i64,
+    pub(crate) ledger_tag_media_nofootprint: i64,
+    pub(crate) ledger_tag_media_nofootprint_compressed: i64,
+    pub(crate) ledger_tag_graphics_footprint: i64,
+    pub(crate) ledger_tag_graphics_footprint_compressed: i64,
+    pub(crate) ledger_tag_graphics_nofootprint: i64,
+    pub(crate) ledger_tag_graphics_nofootprint_compressed: i64,
+    pub(crate) ledger_tag_neural_footprint: i64,
+    pub(crate) ledger_tag_neural_footprint_compressed: i64,
+    pub(crate) ledger_tag_neural_nofootprint: i64,
+    pub(crate) ledger_tag_neural_nofootprint_compressed: i64,
+}
+
+impl TaskVmInfo {
+    /// Read `TASK_VM_INFO` of a process.
+    ///
+    /// Other processes than the current one need `task_for_pid` rights,
+    /// usually only granted to root.
+    pub(crate) fn read(pid: u32) -> Option<Self> {
+        // SAFETY: the task port is only used for `task_info` and released
+        // before returning; `info` is large enough for `count` integers.
+        unsafe {
+            let own_task = mach_task_self();
+            let task = if pid == std::process::id() {
+                own_task
+            } else {
+                let mut task: mach_port_t = MACH_PORT_NULL;
+                if task_for_pid(own_task, pid as i32, &mut task) != KERN_SUCCESS {
+                    return None;
+                }
+                task
+            };
+
+            let mut info = TaskVmInfo::default();
+            let mut count =
+                (mem::size_of::<TaskVmInfo>() / mem::size_of::<u32>()) as mach_msg_type_number_t;
+            let result = task_info(
+                task,
+                TASK_VM_INFO,
+                &mut info as *mut TaskVmInfo as *mut i32,
+                &mut count,
+            );
+            if task != own_task {
+                mach_port_deallocate(own_task, task);
+            }
+            (result == KERN_SUCCESS).then_some(info)
+        }
+    }
+}
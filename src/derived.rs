@@ -0,0 +1,199 @@
+//! Values derived from several samples of the process.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::sampler::ProcessSampler;
+use crate::{MetricGroups, ProcessInfo, ProcessMetricsSnapshot};
+
+/// Fills the derived values of the snapshots of the wrapped sampler, unless
+/// it provides them.
+pub(crate) struct Derived {
+    sampler: Box<dyn ProcessSampler>,
+    memory_growth_window: Duration,
+    // Resident memory of the samples in the window, oldest first.
+    memory: VecDeque<(Instant, u64)>,
+    // Bytes read and written at the previous sample of the disk group, and
+    // the rates since the one before.
+    last_disk: Option<(Instant, u64, u64)>,
+    last_disk_rates: Option<(f64, f64)>,
+}
+
+impl Derived {
+    pub(crate) fn new(sampler: Box<dyn ProcessSampler>, memory_growth_window: Duration) -> Self {
+        Self {
+            sampler,
+            memory_growth_window,
+            memory: VecDeque::new(),
+            last_disk: None,
+            last_disk_rates: None,
+        }
+    }
+
+    /// Slope of the resident memory over the window, in bytes per minute,
+    /// by least squares so that a single outlier does not dominate.
+    fn memory_growth_rate(&mut self, now: Instant, memory: u64) -> Option<f64> {
+        self.memory.push_back((now, memory));
+        while self
+            .memory
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > self.memory_growth_window)
+        {
+            self.memory.pop_front();
+        }
+        if self.memory.len() < 2 {
+            return None;
+        }
+
+        let start = self.memory[0].0;
+        let points: Vec<(f64, f64)> = self
+            .memory
+            .iter()
+            .map(|(time, memory)| {
+                let minutes = time.duration_since(start).as_secs_f64() / 60.0;
+                (minutes, *memory as f64)
+            })
+            .collect();
+        let count = points.len() as f64;
+        let mean_time = points.iter().map(|(time, _)| time).sum::<f64>() / count;
+        let mean_memory = points.iter().map(|(_, memory)| memory).sum::<f64>() / count;
+        let (covariance, variance) =
+            points
+                .iter()
+                .fold((0.0, 0.0), |(covariance, variance), (time, memory)| {
+                    let time = time - mean_time;
+                    (
+                        covariance + time * (memory - mean_memory),
+                        variance + time * time,
+                    )
+                });
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
+    /// Bytes read and written per second since the previous sample.
+    fn disk_rates(&mut self, now: Instant, read: u64, written: u64) -> Option<(f64, f64)> {
+        let (last_time, last_read, last_written) = self.last_disk.replace((now, read, written))?;
+        let elapsed = now.duration_since(last_time).as_secs_f64();
+        // Counters of custom samplers may be reset.
+        (elapsed > 0.0).then(|| {
+            (
+                read.saturating_sub(last_read) as f64 / elapsed,
+                written.saturating_sub(last_written) as f64 / elapsed,
+            )
+        })
+    }
+}
+
+impl ProcessSampler for Derived {
+    fn pid(&self) -> u32 {
+        self.sampler.pid()
+    }
+
+    fn info(&mut self) -> Option<ProcessInfo> {
+        self.sampler.info()
+    }
+
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+        let mut snapshot = self.sampler.sample()?;
+        if snapshot.zombie {
+            return Some(snapshot);
+        }
+        let now = Instant::now();
+        if snapshot.memory_growth_rate.is_none() {
+            snapshot.memory_growth_rate = self.memory_growth_rate(now, snapshot.memory);
+        }
+        // The counters of a group that is not due are those of its previous
+        // sample, the rates are kept until it is sampled again.
+        if self.sampler.sampled_groups().disk {
+            self.last_disk_rates = self.disk_rates(now, snapshot.disk_read, snapshot.disk_written);
+        }
+        if let (None, None, Some((read_rate, write_rate))) = (
+            snapshot.disk_read_rate,
+            snapshot.disk_write_rate,
+            self.last_disk_rates,
+        ) {
+            snapshot.disk_read_rate = Some(read_rate);
+            snapshot.disk_write_rate = Some(write_rate);
+        }
+        Some(snapshot)
+    }
+
+    fn sample_cpu(&mut self) -> Option<f32> {
+        self.sampler.sample_cpu()
+    }
+
+    fn set_metric_groups(&mut self, metric_groups: MetricGroups) {
+        self.sampler.set_metric_groups(metric_groups);
+    }
+
+    fn sampled_groups(&self) -> MetricGroups {
+        self.sampler.sampled_groups()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads the disk every `disk_every` samples, `read` bytes more every
+    /// time, and records the previous counters in between.
+    struct SlowDisk {
+        disk_every: usize,
+        samples: usize,
+        read: u64,
+    }
+
+    impl ProcessSampler for SlowDisk {
+        fn pid(&self) -> u32 {
+            42
+        }
+
+        fn info(&mut self) -> Option<ProcessInfo> {
+            None
+        }
+
+        fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+            self.samples += 1;
+            if self.sampled_groups().disk {
+                self.read += 1000;
+            }
+            Some(ProcessMetricsSnapshot {
+                disk_read: self.read,
+                ..Default::default()
+            })
+        }
+
+        fn sampled_groups(&self) -> MetricGroups {
+            MetricGroups {
+                disk: (self.samples - 1).is_multiple_of(self.disk_every),
+                ..MetricGroups::default()
+            }
+        }
+    }
+
+    #[test]
+    fn disk_rates_with_a_slower_disk_group() {
+        let interval = Duration::from_millis(20);
+        let sampler = SlowDisk {
+            disk_every: 3,
+            samples: 0,
+            read: 0,
+        };
+        let mut derived = Derived::new(Box::new(sampler), Duration::from_secs(60));
+        let mut rates = Vec::new();
+        for _ in 0..7 {
+            let start = Instant::now();
+            rates.push(derived.sample().unwrap().disk_read_rate);
+            std::thread::sleep(interval.saturating_sub(start.elapsed()));
+        }
+
+        assert_eq!(rates[..3], [None, None, None]);
+        // 1000 bytes over the 3 intervals since the previous disk sample,
+        // reported until the next one.
+        let rate = rates[3].unwrap();
+        let max = 1000.0 / (3 * interval).as_secs_f64();
+        assert!(rate > 0.0 && rate <= max, "{rate} > {max}");
+        assert_eq!(rates[4..6], [Some(rate), Some(rate)]);
+        assert!(rates[6].is_some_and(|rate| rate > 0.0 && rate <= max));
+    }
+}
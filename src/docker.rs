@@ -0,0 +1,82 @@
+//! Resolution of Docker containers from the Engine API, see
+//! [`crate::init_process_observer_for_container`].
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use crate::{Error, Result};
+
+const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// A running container.
+pub(crate) struct Container {
+    /// Without the leading `/` of the API.
+    pub(crate) name: String,
+    pub(crate) id: String,
+    /// The first process of the container, as seen from the host.
+    pub(crate) pid: u32,
+    /// The cgroup v2 of the container.
+    pub(crate) cgroup: PathBuf,
+}
+
+/// Inspect the container with the ID or name `container`, on the socket of
+/// `DOCKER_HOST` if it is a `unix://` address.
+pub(crate) fn inspect(container: &str) -> Result<Container> {
+    let error = || Error::Container(container.to_string());
+    // Names and IDs are made of these, which need no escaping in the path.
+    if container.is_empty()
+        || !container
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    {
+        return Err(error());
+    }
+    let body = get(&format!("/containers/{container}/json")).map_err(|err| {
+        tracing::debug!("Could not inspect the container {container}: {err}");
+        error()
+    })?;
+    let inspected: serde_json::Value = serde_json::from_str(&body).map_err(|err| {
+        tracing::debug!("Invalid inspection of the container {container}: {err}");
+        error()
+    })?;
+    // 0 when the container is not running.
+    let pid = inspected["State"]["Pid"]
+        .as_u64()
+        .and_then(|pid| u32::try_from(pid).ok())
+        .filter(|&pid| pid != 0)
+        .ok_or_else(error)?;
+    let cgroup = crate::linux::cgroup_dir(pid).ok_or_else(error)?;
+    Ok(Container {
+        name: inspected["Name"]
+            .as_str()
+            .unwrap_or(container)
+            .trim_start_matches('/')
+            .to_string(),
+        id: inspected["Id"].as_str().unwrap_or_default().to_string(),
+        pid,
+        cgroup: cgroup.into(),
+    })
+}
+
+/// The body of a successful `GET` of `path` on the Docker socket.
+fn get(path: &str) -> std::io::Result<String> {
+    let socket = std::env::var("DOCKER_HOST")
+        .ok()
+        .and_then(|host| host.strip_prefix("unix://").map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_SOCKET.to_string());
+    let mut stream = UnixStream::connect(socket)?;
+    // HTTP/1.0 for the daemon to close the connection after a body which
+    // is not chunked.
+    write!(stream, "GET {path} HTTP/1.0\r\nHost: docker\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| std::io::Error::other("invalid response"))?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(std::io::Error::other(status.to_string()));
+    }
+    Ok(body.to_string())
+}
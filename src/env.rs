@@ -0,0 +1,222 @@
+//! Configuration from `OTEL_PROCESS_METRICS_*` environment variables, so
+//! deployments can configure the observer without code changes.
+//!
+//! Invalid values are ignored with a warning, as the OpenTelemetry SDKs do.
+
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+
+use crate::{MetricGroups, ProcessMetricsConfig};
+
+/// Time between two samples, in milliseconds.
+const INTERVAL: &str = "OTEL_PROCESS_METRICS_INTERVAL";
+/// Comma separated metric groups to disable: `cpu`, `memory`, `disk`, `gpu`.
+const DISABLED: &str = "OTEL_PROCESS_METRICS_DISABLED";
+/// PID of the observed process.
+const PID: &str = "OTEL_PROCESS_METRICS_PID";
+/// Comma separated `key=value` attributes recorded on every metric.
+const ATTRIBUTES: &str = "OTEL_PROCESS_METRICS_ATTRIBUTES";
+/// Prepended to the names of the instruments, see
+/// `ProcessMetricsConfig::metric_prefix`.
+const PREFIX: &str = "OTEL_PROCESS_METRICS_PREFIX";
+/// `true` to disable the telemetry, shared with the OpenTelemetry SDKs.
+const SDK_DISABLED: &str = "OTEL_SDK_DISABLED";
+
+/// Override the settings of `config` set in the environment.
+pub(crate) fn apply(config: &mut ProcessMetricsConfig) {
+    if let Some(interval) = var(INTERVAL) {
+        match parse_interval(&interval) {
+            Some(interval) => config.interval = interval,
+            None => invalid(INTERVAL, &interval),
+        }
+    }
+    if let Some(disabled) = var(DISABLED) {
+        config.metric_groups = parse_disabled(&disabled);
+    }
+    if let Some(attributes) = var(ATTRIBUTES) {
+        config.attributes.extend(parse_attributes(&attributes));
+    }
+    if sdk_disabled() {
+        config.disabled = true;
+    }
+    if let Some(prefix) = var(PREFIX) {
+        config.metric_prefix = prefix.trim().to_string();
+    }
+}
+
+/// The prefix of the instrument names set in the environment, empty if
+/// none.
+pub(crate) fn metric_prefix() -> String {
+    var(PREFIX)
+        .map(|prefix| prefix.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether the telemetry is disabled in the environment.
+pub(crate) fn sdk_disabled() -> bool {
+    let Some(disabled) = var(SDK_DISABLED) else {
+        return false;
+    };
+    parse_bool(&disabled).unwrap_or_else(|| {
+        invalid(SDK_DISABLED, &disabled);
+        false
+    })
+}
+
+/// The PID set in the environment, if any.
+pub(crate) fn pid() -> Option<u32> {
+    let pid = var(PID)?;
+    let parsed = pid.trim().parse().ok();
+    if parsed.is_none() {
+        invalid(PID, &pid);
+    }
+    parsed
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn invalid(name: &str, value: &str) {
+    tracing::warn!("Ignoring invalid value {value:?} of {name}");
+}
+
+/// A positive number of milliseconds.
+fn parse_interval(value: &str) -> Option<Duration> {
+    match value.trim().parse::<u64>() {
+        Ok(millis) if millis > 0 => Some(Duration::from_millis(millis)),
+        _ => None,
+    }
+}
+
+/// `true` or `false`, in any case.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_disabled(value: &str) -> MetricGroups {
+    let mut groups = MetricGroups::default();
+    for group in value
+        .split(',')
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+    {
+        match group.to_ascii_lowercase().as_str() {
+            "cpu" => groups.cpu = false,
+            "memory" => groups.memory = false,
+            "disk" => groups.disk = false,
+            "gpu" => groups.gpu = false,
+            "pressure" => groups.pressure = false,
+            "network" => groups.network = false,
+            _ => invalid(DISABLED, group),
+        }
+    }
+    groups
+}
+
+/// Same format as `OTEL_RESOURCE_ATTRIBUTES`, without percent encoding.
+fn parse_attributes(value: &str) -> Vec<KeyValue> {
+    value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| match entry.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Some(KeyValue::new(
+                key.trim().to_string(),
+                value.trim().to_string(),
+            )),
+            _ => {
+                invalid(ATTRIBUTES, entry);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval() {
+        let cases = [
+            ("1000", Some(Duration::from_secs(1))),
+            (" 250 ", Some(Duration::from_millis(250))),
+            ("0", None),
+            ("-5", None),
+            ("1s", None),
+            ("1.5", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_interval(value), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn bool() {
+        let cases = [
+            ("true", Some(true)),
+            (" TRUE ", Some(true)),
+            ("False", Some(false)),
+            ("1", None),
+            ("yes", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_bool(value), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn disabled() {
+        let groups = |change: fn(&mut MetricGroups)| {
+            let mut groups = MetricGroups::default();
+            change(&mut groups);
+            groups
+        };
+        let cases = [
+            ("", MetricGroups::default()),
+            ("gpu", groups(|groups| groups.gpu = false)),
+            (
+                " CPU , disk,,",
+                groups(|groups| {
+                    groups.cpu = false;
+                    groups.disk = false;
+                }),
+            ),
+            // Unknown groups are ignored.
+            ("memory,swap", groups(|groups| groups.memory = false)),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_disabled(value), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn attributes() {
+        let cases = [
+            (
+                "service.name=api, team = core",
+                vec![
+                    KeyValue::new("service.name", "api"),
+                    KeyValue::new("team", "core"),
+                ],
+            ),
+            // Values may contain `=`, and may be empty.
+            (
+                "query=a=b,empty=",
+                vec![KeyValue::new("query", "a=b"), KeyValue::new("empty", "")],
+            ),
+            ("=value,novalue,,", vec![]),
+            ("", vec![]),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_attributes(value), expected, "{value:?}");
+        }
+    }
+}
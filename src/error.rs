@@ -0,0 +1,73 @@
+use std::fmt;
+
+use opentelemetry::metrics::MetricsError;
+
+/// Errors returned when registering an observer.
+///
+/// GPU samplers never fail the registration: a missing driver only disables
+/// the GPU metrics, and NVML initialization is retried until it succeeds.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The observed process does not exist.
+    PidNotFound(u32),
+    /// sysinfo could not provide the given information on this platform.
+    SysinfoUnavailable(&'static str),
+    /// The meter rejected the instruments or their callback.
+    InstrumentCreation(MetricsError),
+    /// The sampling thread could not be spawned.
+    Thread(std::io::Error),
+    /// The process could not be assigned to a Job Object, see
+    /// `ProcessObserverBuilder::with_job_object`.
+    JobObject(std::io::Error),
+    /// The exporter of a quick-start helper such as `init_with_otlp` could
+    /// not be built.
+    Exporter(MetricsError),
+    /// The command of `init_process_observer_for_command` could not be
+    /// spawned or awaited.
+    Command(std::io::Error),
+    /// The systemd unit of `init_process_observer_for_systemd_unit` is not
+    /// running, or its cgroup could not be resolved.
+    SystemdUnit(String),
+    /// The cgroup of `cgroup_pids` does not exist.
+    Cgroup(String),
+    /// The Docker container of `init_process_observer_for_container` is not
+    /// running, or the Docker socket could not be queried.
+    Container(String),
+}
+
+/// Result type of the fallible functions of this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PidNotFound(pid) => write!(f, "process {pid} not found"),
+            Error::SysinfoUnavailable(what) => write!(f, "could not get the {what}"),
+            Error::InstrumentCreation(err) => write!(f, "could not register instruments: {err}"),
+            Error::Thread(err) => write!(f, "could not spawn the sampling thread: {err}"),
+            Error::JobObject(err) => write!(f, "could not assign the process to a job: {err}"),
+            Error::Exporter(err) => write!(f, "could not build the exporter: {err}"),
+            Error::Command(err) => write!(f, "could not run the command: {err}"),
+            Error::SystemdUnit(unit) => write!(f, "could not resolve the systemd unit {unit}"),
+            Error::Cgroup(cgroup) => write!(f, "could not read the cgroup {cgroup}"),
+            Error::Container(container) => write!(f, "could not resolve the container {container}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InstrumentCreation(err) | Error::Exporter(err) => Some(err),
+            Error::Thread(err) | Error::JobObject(err) | Error::Command(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MetricsError> for Error {
+    fn from(err: MetricsError) -> Self {
+        Error::InstrumentCreation(err)
+    }
+}
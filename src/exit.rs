@@ -0,0 +1,97 @@
+//! Log records emitted when an observed child exits, see
+//! [`crate::ObservedCommand::with_exit_events`].
+
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use opentelemetry::logs::{AnyValue, LogRecord, Logger, Severity};
+
+use crate::ProcessMetricsSnapshot;
+
+const EVENT_NAME: &str = "process.exit";
+
+/// Creates and emits a log record for the exit of a process.
+type Emit = Box<dyn FnMut(u32, ExitStatus, Option<&ProcessMetricsSnapshot>) + Send>;
+
+/// Emits the exit event of a child, with its last sample.
+pub(crate) struct ExitEvents {
+    emit: Emit,
+    // Updated on every sample of the child.
+    pub(crate) latest: Arc<Mutex<Option<ProcessMetricsSnapshot>>>,
+}
+
+impl ExitEvents {
+    pub(crate) fn new<L>(logger: L) -> Self
+    where
+        L: Logger + Send + 'static,
+    {
+        Self {
+            emit: Box::new(move |pid, status, snapshot| {
+                logger.emit(record(&logger, pid, status, snapshot))
+            }),
+            latest: Arc::default(),
+        }
+    }
+
+    pub(crate) fn emit(&mut self, pid: u32, status: ExitStatus) {
+        let snapshot = self.latest.lock().unwrap().take();
+        (self.emit)(pid, status, snapshot.as_ref());
+    }
+}
+
+fn record<L: Logger>(
+    logger: &L,
+    pid: u32,
+    status: ExitStatus,
+    snapshot: Option<&ProcessMetricsSnapshot>,
+) -> L::LogRecord {
+    let severity = if status.success() {
+        Severity::Info
+    } else {
+        Severity::Warn
+    };
+    let mut record = logger.create_log_record();
+    record.set_event_name(EVENT_NAME);
+    record.set_timestamp(SystemTime::now());
+    record.set_severity_number(severity);
+    record.set_severity_text(severity.name().into());
+    record.set_body(AnyValue::from(format!("Process {pid} exited: {status}")));
+    record.add_attribute("process.pid", i64::from(pid));
+    if let Some(code) = status.code() {
+        record.add_attribute("process.exit.code", i64::from(code));
+    }
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        record.add_attribute("process.exit.signal", i64::from(signal));
+    }
+    // Taken at most an interval before the exit.
+    if let Some(snapshot) = snapshot {
+        record.add_attributes([
+            (
+                "process.cpu.usage",
+                AnyValue::from(f64::from(snapshot.cpu_usage)),
+            ),
+            (
+                "process.memory.usage",
+                AnyValue::from(snapshot.memory as i64),
+            ),
+            (
+                "process.memory.virtual",
+                AnyValue::from(snapshot.virtual_memory as i64),
+            ),
+            (
+                "process.disk.read",
+                AnyValue::from(snapshot.disk_read as i64),
+            ),
+            (
+                "process.disk.written",
+                AnyValue::from(snapshot.disk_written as i64),
+            ),
+        ]);
+        if let Some(cpu_times) = &snapshot.cpu_times {
+            record.add_attribute("process.cpu.time", cpu_times.user + cpu_times.system);
+        }
+    }
+    record
+}
@@ -0,0 +1,210 @@
+//! Helpers setting up the export of the process metrics in one call, for
+//! programs which do not configure OpenTelemetry otherwise.
+
+use opentelemetry::metrics::MeterProvider as _;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+
+use crate::{Error, ProcessObserverBuilder, Result};
+
+/// Keeps the meter provider of a quick-start helper such as
+/// `init_with_otlp` alive, and shuts it down when dropped, which
+/// exports the metrics not exported yet.
+#[cfg(any(feature = "otlp", feature = "stdout"))]
+#[must_use = "the meter provider is shut down when the guard is dropped"]
+pub struct ExporterGuard {
+    provider: SdkMeterProvider,
+    // The runtime exporting the metrics, when the helper started one.
+    #[cfg(feature = "stdout")]
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+#[cfg(any(feature = "otlp", feature = "stdout"))]
+impl ExporterGuard {
+    /// The meter provider, to record other metrics with the same exporter.
+    pub fn meter_provider(&self) -> &SdkMeterProvider {
+        &self.provider
+    }
+}
+
+#[cfg(any(feature = "otlp", feature = "stdout"))]
+impl Drop for ExporterGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.provider.shutdown() {
+            tracing::warn!("Could not shut down the meter provider: {err}");
+        }
+        // Without blocking, the guard may be dropped from another runtime.
+        #[cfg(feature = "stdout")]
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// A guard of a meter provider without any reader, when the telemetry is
+/// disabled in the environment.
+#[cfg(any(feature = "otlp", feature = "stdout"))]
+fn disabled() -> Option<ExporterGuard> {
+    crate::env::sdk_disabled().then(|| {
+        tracing::debug!("Telemetry disabled, not building the exporter");
+        ExporterGuard {
+            provider: SdkMeterProvider::default(),
+            #[cfg(feature = "stdout")]
+            runtime: None,
+        }
+    })
+}
+
+/// Register a process observer on a meter of the provider of `guard`.
+#[cfg(any(feature = "otlp", feature = "stdout"))]
+fn observe(guard: ExporterGuard) -> Result<ExporterGuard> {
+    ProcessObserverBuilder::new(guard.provider.meter(crate::METER_NAME)).init()?;
+    Ok(guard)
+}
+
+/// Export the metrics of the current process over OTLP/gRPC to `endpoint`,
+/// such as `http://localhost:4317`, until the returned guard is dropped.
+/// Requires the `otlp` feature.
+///
+/// The observer is configured from the environment, see
+/// [`ProcessObserverBuilder::new`], and the metrics are exported every
+/// `OTEL_METRIC_EXPORT_INTERVAL` milliseconds, 60 seconds by default. The
+/// meter provider also becomes the global one. When `OTEL_SDK_DISABLED` is
+/// `true`, nothing is exported and the observer is a no-op.
+///
+/// Must be called from a multi-threaded tokio runtime, which exports the
+/// metrics in the background: dropping the guard waits for the last export.
+///
+/// Fails with [`Error::Exporter`] if the exporter could not be built, for
+/// instance because `endpoint` is not a valid URI.
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> opentelemetry_system_metrics::Result<()> {
+/// let _guard = opentelemetry_system_metrics::init_with_otlp("http://localhost:4317")?;
+///
+/// // Do some work
+/// # Ok(())
+/// # }
+/// ```
+///
+#[cfg(feature = "otlp")]
+pub fn init_with_otlp(endpoint: impl Into<String>) -> Result<ExporterGuard> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    if let Some(guard) = disabled() {
+        return Ok(guard);
+    }
+    let provider = opentelemetry_otlp::new_pipeline()
+        .metrics(opentelemetry_sdk::runtime::Tokio)
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .build()
+        .map_err(Error::Exporter)?;
+    observe(ExporterGuard {
+        provider,
+        #[cfg(feature = "stdout")]
+        runtime: None,
+    })
+}
+
+/// Print the metrics of the current process to stdout every 5 seconds, or
+/// every `OTEL_METRIC_EXPORT_INTERVAL` milliseconds, as JSON lines, until
+/// the returned guard is dropped. Meant for local debugging. Requires the
+/// `stdout` feature.
+///
+/// The observer is configured from the environment, see
+/// [`ProcessObserverBuilder::new`], and the meter provider also becomes the
+/// global one, and nothing is printed when `OTEL_SDK_DISABLED` is `true`.
+/// The metrics are exported from a tokio runtime with a single
+/// worker thread, started for this purpose, so no async runtime is needed.
+///
+/// Fails with [`Error::Thread`] if the runtime could not be started.
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> opentelemetry_system_metrics::Result<()> {
+/// let _guard = opentelemetry_system_metrics::init_with_stdout()?;
+///
+/// // Do some work
+/// # Ok(())
+/// # }
+/// ```
+///
+#[cfg(feature = "stdout")]
+pub fn init_with_stdout() -> Result<ExporterGuard> {
+    use opentelemetry_sdk::metrics::PeriodicReader;
+
+    if let Some(guard) = disabled() {
+        return Ok(guard);
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("otel-stdout-exporter")
+        .enable_time()
+        .build()
+        .map_err(Error::Thread)?;
+    // The reader creates its interval on the runtime it is built in.
+    let provider = {
+        let _enter = runtime.enter();
+        let exporter = opentelemetry_stdout::MetricsExporter::default();
+        let mut reader = PeriodicReader::builder(exporter, opentelemetry_sdk::runtime::Tokio);
+        if std::env::var_os("OTEL_METRIC_EXPORT_INTERVAL").is_none() {
+            reader = reader.with_interval(std::time::Duration::from_secs(5));
+        }
+        SdkMeterProvider::builder()
+            .with_reader(reader.build())
+            .build()
+    };
+    opentelemetry::global::set_meter_provider(provider.clone());
+    observe(ExporterGuard {
+        provider,
+        runtime: Some(runtime),
+    })
+}
+
+/// Expose the metrics of the current process in the returned
+/// `prometheus::Registry`, to serve its `gather()` on the endpoint scraped by
+/// Prometheus. Requires the `prometheus` feature.
+///
+/// The observer is configured from the environment, see
+/// [`ProcessObserverBuilder::new`], and samples the process on a thread of
+/// its own, see [`ProcessObserverBuilder::spawn`], until the returned handle
+/// is dropped. The meter provider becomes the global one, which keeps it
+/// alive. When `OTEL_SDK_DISABLED` is `true`, the registry stays empty and
+/// the observer is a no-op.
+///
+/// Fails with [`Error::Exporter`] if the exporter could not be built.
+/// # Example
+///
+/// ```
+/// # fn main() -> opentelemetry_system_metrics::Result<()> {
+/// let (registry, _handle) = opentelemetry_system_metrics::init_with_prometheus()?;
+///
+/// // On every scrape.
+/// let families = registry.gather();
+/// # Ok(())
+/// # }
+/// ```
+///
+#[cfg(feature = "prometheus")]
+pub fn init_with_prometheus() -> Result<(prometheus::Registry, crate::ObserverHandle)> {
+    let registry = prometheus::Registry::new();
+    let provider = if crate::env::sdk_disabled() {
+        tracing::debug!("Telemetry disabled, not building the exporter");
+        SdkMeterProvider::default()
+    } else {
+        let reader = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()
+            .map_err(Error::Exporter)?;
+        let provider = SdkMeterProvider::builder().with_reader(reader).build();
+        opentelemetry::global::set_meter_provider(provider.clone());
+        provider
+    };
+    let handle = ProcessObserverBuilder::new(provider.meter(crate::METER_NAME)).spawn()?;
+    Ok((registry, handle))
+}
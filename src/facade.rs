@@ -0,0 +1,76 @@
+//! Publishing of the samples through the macros of the `metrics` crate, see
+//! [`crate::ProcessObserverBuilder::with_metrics_facade`].
+
+use std::borrow::Cow;
+
+use metrics::Label;
+
+use crate::{prefixed, ProcessMetricsSnapshot};
+use crate::{
+    PROCESS_CPU_USAGE, PROCESS_DISK_IO, PROCESS_GPU_MEMORY_USAGE, PROCESS_MEMORY_SWAP,
+    PROCESS_MEMORY_USAGE, PROCESS_MEMORY_VIRTUAL,
+};
+
+/// Publishes every sample to the recorder installed in the `metrics` crate,
+/// which is looked up on every sample as it may be installed later.
+pub(crate) struct Facade {
+    cpu_usage: Cow<'static, str>,
+    memory_usage: Cow<'static, str>,
+    memory_virtual: Cow<'static, str>,
+    memory_swap: Cow<'static, str>,
+    disk_io: Cow<'static, str>,
+    gpu_memory_usage: Cow<'static, str>,
+    labels: Vec<Label>,
+}
+
+impl Facade {
+    pub(crate) fn new(prefix: &str, pid: u32, attributes: &[opentelemetry::KeyValue]) -> Self {
+        let labels = [Label::new("process.pid", pid.to_string())]
+            .into_iter()
+            .chain(attributes.iter().map(|attribute| {
+                Label::new(attribute.key.to_string(), attribute.value.to_string())
+            }))
+            .collect();
+        Self {
+            cpu_usage: prefixed(prefix, PROCESS_CPU_USAGE),
+            memory_usage: prefixed(prefix, PROCESS_MEMORY_USAGE),
+            memory_virtual: prefixed(prefix, PROCESS_MEMORY_VIRTUAL),
+            memory_swap: prefixed(prefix, PROCESS_MEMORY_SWAP),
+            disk_io: prefixed(prefix, PROCESS_DISK_IO),
+            gpu_memory_usage: prefixed(prefix, PROCESS_GPU_MEMORY_USAGE),
+            labels,
+        }
+    }
+
+    pub(crate) fn publish(&self, snapshot: &ProcessMetricsSnapshot) {
+        let labels = || self.labels.iter();
+        metrics::gauge!(self.cpu_usage.clone(), labels()).set(snapshot.cpu_usage);
+        metrics::gauge!(self.memory_usage.clone(), labels()).set(snapshot.memory as f64);
+        metrics::gauge!(self.memory_virtual.clone(), labels()).set(snapshot.virtual_memory as f64);
+        if let Some(swap) = snapshot.swap {
+            metrics::gauge!(self.memory_swap.clone(), labels()).set(swap as f64);
+        }
+        for (direction, bytes) in [
+            ("read", snapshot.disk_read),
+            ("write", snapshot.disk_written),
+        ] {
+            let labels = with_label(&self.labels, "direction", direction.to_string());
+            metrics::counter!(self.disk_io.clone(), labels).absolute(bytes);
+        }
+        for gpu in &snapshot.gpus {
+            let Some(memory) = gpu.memory_used else {
+                continue;
+            };
+            let labels = with_label(&self.labels, "gpu.uuid", gpu.uuid.clone());
+            metrics::gauge!(self.gpu_memory_usage.clone(), labels).set(memory as f64);
+        }
+    }
+}
+
+fn with_label(labels: &[Label], key: &'static str, value: String) -> Vec<Label> {
+    labels
+        .iter()
+        .cloned()
+        .chain([Label::new(key, value)])
+        .collect()
+}
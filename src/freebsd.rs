@@ -0,0 +1,30 @@
+//! FreeBSD specific readers for information sysinfo does not expose.
+
+use std::mem;
+
+/// Information of the kernel about the process. `None` if it is gone.
+pub(crate) fn kinfo_proc(pid: u32) -> Option<libc::kinfo_proc> {
+    let mut mib = [
+        libc::CTL_KERN,
+        libc::KERN_PROC,
+        libc::KERN_PROC_PID,
+        pid as libc::c_int,
+    ];
+    // SAFETY: `kinfo_proc` is plain data, valid when zeroed.
+    let mut info: libc::kinfo_proc = unsafe { mem::zeroed() };
+    let mut size = mem::size_of::<libc::kinfo_proc>();
+    // SAFETY: `mib` has `mib.len()` entries and `info` is a valid buffer of
+    // `size` bytes.
+    let ok = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as libc::c_uint,
+            &mut info as *mut _ as *mut libc::c_void,
+            &mut size,
+            std::ptr::null(),
+            0,
+        )
+    };
+    // Nothing is written for processes which do not exist.
+    (ok == 0 && size == mem::size_of::<libc::kinfo_proc>()).then_some(info)
+}
@@ -0,0 +1,49 @@
+use super::drm::{self, EngineUtilization};
+use super::{GpuProcessSample, GpuSampler};
+
+const AMD_VENDOR_ID: &str = "0x1002";
+
+/// AMD GPUs through the `amdgpu` DRM usage statistics.
+#[derive(Default)]
+pub(crate) struct AmdSampler {
+    utilization: EngineUtilization,
+}
+
+impl GpuSampler for AmdSampler {
+    fn sample(&self, pid: u32) -> Vec<GpuProcessSample> {
+        let usage = drm::read_usage(pid, "amdgpu", "vram");
+
+        drm::devices(AMD_VENDOR_ID)
+            .into_iter()
+            .map(|(index, pci_address)| {
+                let mut sample = GpuProcessSample {
+                    vendor: "amd",
+                    index,
+                    uuid: pci_address.clone(),
+                    mig_instance: None,
+                    memory_used: Some(0),
+                    sm_utilization: Some(0),
+                    encoder_utilization: Some(0),
+                    decoder_utilization: Some(0),
+                };
+                if let Some(device) = usage.get(&pci_address) {
+                    sample.memory_used = Some(device.memory);
+                    for (engine, percent) in self.utilization.update(&pci_address, &device.engines)
+                    {
+                        let total = if engine == "gfx" || engine == "compute" {
+                            &mut sample.sm_utilization
+                        } else if engine.starts_with("enc") {
+                            &mut sample.encoder_utilization
+                        } else if engine.starts_with("dec") {
+                            &mut sample.decoder_utilization
+                        } else {
+                            continue;
+                        };
+                        *total = total.map(|total| (total + percent).min(100));
+                    }
+                }
+                sample
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,26 @@
+use super::{GpuProcessSample, GpuSampler};
+use crate::darwin::TaskVmInfo;
+
+/// Apple Silicon GPUs, through the graphics memory the kernel attributes
+/// to the process in its unified memory footprint.
+///
+/// Per-process utilization is not exposed by the OS.
+#[derive(Default)]
+pub(crate) struct AppleSampler;
+
+impl GpuSampler for AppleSampler {
+    fn sample(&self, pid: u32) -> Vec<GpuProcessSample> {
+        let Some(info) = TaskVmInfo::read(pid) else {
+            tracing::debug!("Could not read the VM info of process {pid} for GPU memory usage");
+            return Vec::new();
+        };
+        let graphics_footprint = info.ledger_tag_graphics_footprint;
+
+        vec![GpuProcessSample {
+            vendor: "apple",
+            index: 0,
+            memory_used: Some(graphics_footprint.max(0) as u64),
+            ..Default::default()
+        }]
+    }
+}
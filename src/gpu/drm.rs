@@ -0,0 +1,185 @@
+//! Per client usage exposed by DRM drivers in `/proc/<pid>/fdinfo`, see
+//! <https://docs.kernel.org/gpu/drm-usage-stats.html>.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Usage of a single DRM device, summed over all clients of the process.
+#[derive(Debug, Default)]
+pub(crate) struct DrmDeviceUsage {
+    /// Bytes resident in the requested memory region.
+    pub(crate) memory: u64,
+    /// Busy time per engine, in nanoseconds.
+    pub(crate) engines: HashMap<String, u64>,
+    /// Busy and total GPU cycles per engine, for drivers reporting cycles
+    /// instead of time.
+    pub(crate) cycles: HashMap<String, (u64, u64)>,
+}
+
+/// DRM devices of the given PCI vendor, as `(card index, PCI address)`.
+pub(crate) fn devices(vendor_id: &str) -> Vec<(u32, String)> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    let mut devices: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Skip connectors such as `card0-DP-1`.
+            let index = name.strip_prefix("card")?.parse::<u32>().ok()?;
+            let vendor = fs::read_to_string(entry.path().join("device/vendor")).ok()?;
+            if vendor.trim() != vendor_id {
+                return None;
+            }
+            let device = fs::canonicalize(entry.path().join("device")).ok()?;
+            let pci_address = device.file_name()?.to_str()?.to_string();
+            Some((index, pci_address))
+        })
+        .collect();
+    devices.sort();
+    devices
+}
+
+/// Usage of every device of `driver` opened by the process, keyed by PCI
+/// address.
+pub(crate) fn read_usage(
+    pid: u32,
+    driver: &str,
+    memory_region: &str,
+) -> HashMap<String, DrmDeviceUsage> {
+    let mut usage: HashMap<String, DrmDeviceUsage> = HashMap::new();
+    let Ok(entries) = fs::read_dir(format!("/proc/{pid}/fdinfo")) else {
+        return usage;
+    };
+
+    // Duplicated file descriptors share the same client, only count it once.
+    let mut seen_clients = HashSet::new();
+    for entry in entries.flatten() {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let fields: HashMap<&str, &str> = content
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        if fields.get("drm-driver") != Some(&driver) {
+            continue;
+        }
+        let (Some(pdev), Some(client_id)) = (fields.get("drm-pdev"), fields.get("drm-client-id"))
+        else {
+            continue;
+        };
+        if !seen_clients.insert((pdev.to_string(), client_id.to_string())) {
+            continue;
+        }
+
+        let device = usage.entry(pdev.to_string()).or_default();
+        let memory = fields
+            .get(format!("drm-resident-{memory_region}").as_str())
+            .or_else(|| fields.get(format!("drm-memory-{memory_region}").as_str()));
+        device.memory += memory.and_then(|value| parse_bytes(value)).unwrap_or(0);
+        for (key, value) in &fields {
+            let counter = value
+                .trim_end_matches("ns")
+                .trim()
+                .parse::<u64>()
+                .unwrap_or(0);
+            if let Some(engine) = key.strip_prefix("drm-engine-") {
+                *device.engines.entry(engine.to_string()).or_default() += counter;
+            } else if let Some(engine) = key.strip_prefix("drm-total-cycles-") {
+                device.cycles.entry(engine.to_string()).or_default().1 += counter;
+            } else if let Some(engine) = key.strip_prefix("drm-cycles-") {
+                device.cycles.entry(engine.to_string()).or_default().0 += counter;
+            }
+        }
+    }
+    usage
+}
+
+/// Parse a memory value such as `1024 KiB`.
+fn parse_bytes(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let amount = parts.next()?.parse::<u64>().ok()?;
+    let multiplier = match parts.next() {
+        None => 1,
+        Some("KiB") => 1024,
+        Some("MiB") => 1024 * 1024,
+        Some("GiB") => 1024 * 1024 * 1024,
+        Some(_) => return None,
+    };
+    Some(amount * multiplier)
+}
+
+/// Busy and total counters per engine at the previous sample.
+type EngineCounters = HashMap<String, (u64, u64)>;
+
+/// Turns cumulative engine counters into utilization between two samples.
+pub(crate) struct EngineUtilization {
+    start: Instant,
+    previous: Mutex<HashMap<String, EngineCounters>>,
+}
+
+impl Default for EngineUtilization {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            previous: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl EngineUtilization {
+    /// Utilization percentage per engine from busy time in nanoseconds,
+    /// since the previous call for the same device.
+    pub(crate) fn update(
+        &self,
+        device: &str,
+        engines: &HashMap<String, u64>,
+    ) -> HashMap<String, u32> {
+        // Wall clock time plays the role of the total counter.
+        let now = self.start.elapsed().as_nanos() as u64;
+        let counters = engines
+            .iter()
+            .map(|(engine, busy)| (engine.clone(), (*busy, now)))
+            .collect();
+        self.update_counters(device, counters)
+    }
+
+    /// Utilization percentage per engine from busy and total cycles, since
+    /// the previous call for the same device.
+    pub(crate) fn update_cycles(
+        &self,
+        device: &str,
+        cycles: &HashMap<String, (u64, u64)>,
+    ) -> HashMap<String, u32> {
+        self.update_counters(device, cycles.clone())
+    }
+
+    /// The first call for a device reports every engine as idle.
+    fn update_counters(&self, device: &str, counters: EngineCounters) -> HashMap<String, u32> {
+        let mut previous = self.previous.lock().unwrap();
+        let last_counters = previous.get(device);
+        let utilization = counters
+            .iter()
+            .map(|(engine, (busy, total))| {
+                let (last_busy, last_total) = last_counters
+                    .and_then(|last| last.get(engine))
+                    .copied()
+                    .unwrap_or((*busy, *total));
+                let delta_busy = busy.saturating_sub(last_busy) as f64;
+                let delta_total = total.saturating_sub(last_total) as f64;
+                let percent = if delta_total > 0.0 {
+                    (delta_busy / delta_total * 100.0).min(100.0)
+                } else {
+                    0.0
+                };
+                (engine.clone(), percent as u32)
+            })
+            .collect();
+        previous.insert(device.to_string(), counters);
+        utilization
+    }
+}
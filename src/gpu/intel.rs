@@ -0,0 +1,60 @@
+use super::drm::{self, EngineUtilization};
+use super::{GpuProcessSample, GpuSampler};
+
+const INTEL_VENDOR_ID: &str = "0x8086";
+
+/// Intel GPUs through the `i915` and `xe` DRM usage statistics.
+///
+/// Memory is not reported as integrated GPUs share system memory with the
+/// process, which is already accounted for in `process.memory.usage`.
+#[derive(Default)]
+pub(crate) struct IntelSampler {
+    utilization: EngineUtilization,
+}
+
+impl GpuSampler for IntelSampler {
+    fn sample(&self, pid: u32) -> Vec<GpuProcessSample> {
+        let mut usage = drm::read_usage(pid, "i915", "system0");
+        usage.extend(drm::read_usage(pid, "xe", "gtt"));
+
+        drm::devices(INTEL_VENDOR_ID)
+            .into_iter()
+            .map(|(index, pci_address)| {
+                let mut sample = GpuProcessSample {
+                    vendor: "intel",
+                    index,
+                    uuid: pci_address.clone(),
+                    sm_utilization: Some(0),
+                    encoder_utilization: Some(0),
+                    decoder_utilization: Some(0),
+                    ..Default::default()
+                };
+                let Some(device) = usage.get(&pci_address) else {
+                    return sample;
+                };
+
+                // i915 reports busy time, xe reports busy cycles.
+                let utilization = if device.cycles.is_empty() {
+                    self.utilization.update(&pci_address, &device.engines)
+                } else {
+                    self.utilization.update_cycles(&pci_address, &device.cycles)
+                };
+                for (engine, percent) in utilization {
+                    let totals = match engine.as_str() {
+                        "render" | "compute" | "rcs" | "ccs" => vec![&mut sample.sm_utilization],
+                        // The video engines both encode and decode.
+                        "video" | "vcs" => vec![
+                            &mut sample.encoder_utilization,
+                            &mut sample.decoder_utilization,
+                        ],
+                        _ => continue,
+                    };
+                    for total in totals {
+                        *total = total.map(|total| (total + percent).min(100));
+                    }
+                }
+                sample
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{GpuDeviceSample, GpuProcessSample, GpuSampler};
+
+/// GPU load in per mille, depending on the Tegra generation.
+const LOAD_PATHS: [&str; 3] = [
+    "/sys/devices/gpu.0/load",
+    "/sys/devices/platform/gpu.0/load",
+    "/sys/devices/platform/17000000.ga10b/load",
+];
+/// Memory mapped by every nvmap client, only readable by root.
+const NVMAP_CLIENTS_PATH: &str = "/sys/kernel/debug/nvmap/iovmm/clients";
+
+/// Jetson boards, where NVML is not available, through the Tegra sysfs.
+pub(crate) struct JetsonSampler {
+    load_path: PathBuf,
+}
+
+impl JetsonSampler {
+    /// `None` when not running on a Tegra device.
+    pub(crate) fn detect() -> Option<Self> {
+        let load_path = LOAD_PATHS
+            .iter()
+            .map(Path::new)
+            .find(|path| path.exists())?;
+        Some(Self {
+            load_path: load_path.to_path_buf(),
+        })
+    }
+}
+
+impl GpuSampler for JetsonSampler {
+    fn sample(&self, pid: u32) -> Vec<GpuProcessSample> {
+        let Ok(clients) = fs::read_to_string(NVMAP_CLIENTS_PATH) else {
+            return Vec::new();
+        };
+        // CLIENT PROCESS PID SIZE, with sizes such as `1234K`.
+        let memory_used = clients
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace().rev();
+                let size = fields.next()?.strip_suffix('K')?.parse::<u64>().ok()?;
+                let client_pid = fields.next()?.parse::<u32>().ok()?;
+                (client_pid == pid).then_some(size * 1024)
+            })
+            .sum();
+
+        vec![GpuProcessSample {
+            vendor: "nvidia",
+            memory_used: Some(memory_used),
+            ..Default::default()
+        }]
+    }
+
+    fn sample_devices(&self) -> Vec<GpuDeviceSample> {
+        let load = fs::read_to_string(&self.load_path)
+            .ok()
+            .and_then(|load| load.trim().parse::<u32>().ok());
+
+        vec![GpuDeviceSample {
+            vendor: "nvidia",
+            utilization: load.map(|per_mille| per_mille / 10),
+            ..Default::default()
+        }]
+    }
+}
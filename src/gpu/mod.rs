@@ -0,0 +1,127 @@
+//! GPU sampling, kept separate from the instruments so that new vendors only
+//! have to implement [`GpuSampler`].
+
+#[cfg(all(feature = "amd-gpu", target_os = "linux"))]
+mod amd;
+#[cfg(all(feature = "apple-gpu", target_os = "macos"))]
+mod apple;
+#[cfg(all(any(feature = "amd-gpu", feature = "intel-gpu"), target_os = "linux"))]
+mod drm;
+#[cfg(all(feature = "intel-gpu", target_os = "linux"))]
+mod intel;
+#[cfg(target_os = "linux")]
+mod jetson;
+mod nvml;
+
+#[cfg(all(feature = "amd-gpu", target_os = "linux"))]
+pub(crate) use self::amd::AmdSampler;
+#[cfg(all(feature = "apple-gpu", target_os = "macos"))]
+pub(crate) use self::apple::AppleSampler;
+#[cfg(all(feature = "intel-gpu", target_os = "linux"))]
+pub(crate) use self::intel::IntelSampler;
+#[cfg(target_os = "linux")]
+pub(crate) use self::jetson::JetsonSampler;
+pub(crate) use self::nvml::NvmlSampler;
+
+/// A source of per-process GPU usage, usually one per GPU vendor.
+///
+/// The built-in samplers are selected by cargo features. Custom samplers
+/// can be plugged in with [`crate::ProcessObserverBuilder::with_gpu_sampler`].
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{GpuProcessSample, GpuSampler, ProcessObserverBuilder};
+///
+/// struct FixedSampler;
+///
+/// impl GpuSampler for FixedSampler {
+///     fn sample(&self, _pid: u32) -> Vec<GpuProcessSample> {
+///         vec![GpuProcessSample {
+///             vendor: "mock",
+///             memory_used: Some(1024),
+///             ..Default::default()
+///         }]
+///     }
+/// }
+///
+/// let meter = global::meter("process-meter");
+/// ProcessObserverBuilder::new(meter)
+///     .with_gpu_sampler(FixedSampler)
+///     .init();
+/// ```
+///
+pub trait GpuSampler: Send + Sync {
+    /// Usage of every device handled by the sampler by the process `pid`.
+    ///
+    /// Devices the process does not use should be reported with zero usage.
+    fn sample(&self, pid: u32) -> Vec<GpuProcessSample>;
+
+    /// State of every device handled by the sampler, recorded by
+    /// [`crate::init_host_observer`]. Samplers without device level
+    /// information report nothing.
+    fn sample_devices(&self) -> Vec<GpuDeviceSample> {
+        Vec::new()
+    }
+}
+
+/// Usage of a single GPU device by the observed process.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GpuProcessSample {
+    /// Recorded as the `gpu.vendor` attribute.
+    pub vendor: &'static str,
+    /// Recorded as the `gpu.index` attribute.
+    pub index: u32,
+    /// Recorded as the `gpu.uuid` attribute.
+    pub uuid: String,
+    /// Recorded as the `gpu.mig.instance` attribute, for usage of a MIG
+    /// instance of the device `index`.
+    pub mig_instance: Option<u32>,
+    /// Device memory used by the process, in bytes. `None` when the device
+    /// shares system memory.
+    pub memory_used: Option<u64>,
+    /// Utilization of the graphics and compute engines, in percent.
+    /// `None` when the sampler cannot attribute utilization to a process.
+    pub sm_utilization: Option<u32>,
+    /// Utilization of the video encoder, in percent.
+    pub encoder_utilization: Option<u32>,
+    /// Utilization of the video decoder, in percent.
+    pub decoder_utilization: Option<u32>,
+}
+
+/// State of a single GPU device, independent of any process.
+#[derive(Debug, Clone, Default)]
+pub struct GpuDeviceSample {
+    /// Recorded as the `gpu.vendor` attribute.
+    pub vendor: &'static str,
+    /// Recorded as the `gpu.index` attribute.
+    pub index: u32,
+    /// Recorded as the `gpu.uuid` attribute.
+    pub uuid: String,
+    /// Utilization of the whole device, in percent.
+    pub utilization: Option<u32>,
+    /// Power draw, in watts.
+    pub power_usage: Option<f64>,
+    /// Die temperature, in degrees Celsius.
+    pub temperature: Option<f64>,
+    /// SM clock frequency, in MHz.
+    pub sm_clock: Option<u32>,
+    /// Memory clock frequency, in MHz.
+    pub memory_clock: Option<u32>,
+    /// Every clock throttle reason known to the sampler and whether it is
+    /// currently active.
+    pub throttle_reasons: Vec<(&'static str, bool)>,
+}
+
+/// The built-in samplers reporting device level information, polling
+/// every device.
+pub(crate) fn device_samplers() -> Vec<Box<dyn GpuSampler>> {
+    #[allow(unused_mut)]
+    let mut samplers: Vec<Box<dyn GpuSampler>> = vec![Box::new(NvmlSampler::new(None))];
+    #[cfg(target_os = "linux")]
+    if let Some(jetson) = JetsonSampler::detect() {
+        samplers.push(Box::new(jetson));
+    }
+    samplers
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use nvml_wrapper::bitmasks::device::ThrottleReasons;
+use nvml_wrapper::enum_wrappers::device::{Clock, TemperatureSensor};
+use nvml_wrapper::enums::device::UsedGpuMemory;
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::{Device, Nvml};
+
+use super::{GpuDeviceSample, GpuProcessSample, GpuSampler};
+use crate::config::DeviceId;
+
+/// `NVML_DEVICE_MIG_ENABLE`, not re-exported by nvml-wrapper.
+const NVML_DEVICE_MIG_ENABLE: u32 = 1;
+
+const THROTTLE_REASONS: [(&str, ThrottleReasons); 9] = [
+    ("gpu_idle", ThrottleReasons::GPU_IDLE),
+    (
+        "applications_clocks_setting",
+        ThrottleReasons::APPLICATIONS_CLOCKS_SETTING,
+    ),
+    ("sw_power_cap", ThrottleReasons::SW_POWER_CAP),
+    ("hw_slowdown", ThrottleReasons::HW_SLOWDOWN),
+    ("sync_boost", ThrottleReasons::SYNC_BOOST),
+    ("sw_thermal_slowdown", ThrottleReasons::SW_THERMAL_SLOWDOWN),
+    ("hw_thermal_slowdown", ThrottleReasons::HW_THERMAL_SLOWDOWN),
+    (
+        "hw_power_brake_slowdown",
+        ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN,
+    ),
+    (
+        "display_clock_setting",
+        ThrottleReasons::DISPLAY_CLOCK_SETTING,
+    ),
+];
+
+/// Delay before retrying to initialize NVML, doubled on every failure.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(10);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+pub(crate) struct NvmlSampler {
+    // The driver may be loaded, or the container granted access to it, after
+    // the observer started, so initialization is retried until it succeeds.
+    nvml: OnceLock<Nvml>,
+    retry: Mutex<Retry>,
+    // `None` polls every device.
+    requested_devices: Option<Vec<DeviceId>>,
+    // The requested devices, with the CUDA indices resolved once NVML lists
+    // the devices.
+    devices: OnceLock<Option<Vec<DeviceId>>>,
+    // NVML only returns utilization samples newer than the last seen
+    // timestamp, which is tracked per device index.
+    last_sample_timestamps: Mutex<HashMap<u32, u64>>,
+}
+
+impl NvmlSampler {
+    pub(crate) fn new(devices: Option<Vec<DeviceId>>) -> Self {
+        let sampler = Self {
+            nvml: OnceLock::new(),
+            retry: Mutex::new(Retry {
+                next_attempt: Instant::now(),
+                delay: INITIAL_RETRY_DELAY,
+                attempts: 0,
+            }),
+            requested_devices: devices,
+            devices: OnceLock::new(),
+            last_sample_timestamps: Mutex::new(HashMap::new()),
+        };
+        sampler.nvml();
+        sampler
+    }
+
+    /// The NVML handle, initializing it if the retry delay has elapsed.
+    fn nvml(&self) -> Option<&Nvml> {
+        if let Some(nvml) = self.nvml.get() {
+            return Some(nvml);
+        }
+
+        let mut retry = self.retry.lock().unwrap();
+        let now = Instant::now();
+        if now < retry.next_attempt {
+            return None;
+        }
+        match Nvml::init() {
+            Ok(nvml) => {
+                if retry.attempts > 0 {
+                    tracing::info!("NVML initialized after {} attempts", retry.attempts + 1);
+                }
+                Some(self.nvml.get_or_init(|| nvml))
+            }
+            Err(err) => {
+                // Only the first failure is worth the attention of the user.
+                if retry.attempts == 0 {
+                    tracing::info!(
+                        "Could not initiate NVML for observing GPU memory usage. Error: {:?}",
+                        err
+                    );
+                } else {
+                    tracing::debug!("Could not initiate NVML. Error: {:?}", err);
+                }
+                retry.attempts += 1;
+                retry.next_attempt = now + retry.delay;
+                retry.delay = (retry.delay * 2).min(MAX_RETRY_DELAY);
+                None
+            }
+        }
+    }
+
+    /// Every physical device with its index.
+    fn devices<'a>(&'a self, nvml: &'a Nvml) -> impl Iterator<Item = (u32, Device<'a>)> + 'a {
+        let device_count = nvml.device_count().unwrap_or(0);
+        (0..device_count).filter_map(move |index| Some((index, nvml.device_by_index(index).ok()?)))
+    }
+
+    /// The selected devices. The CUDA indices only match the NVML indices
+    /// when the devices are identical, otherwise every device is polled.
+    fn selected_devices(&self) -> Option<&[DeviceId]> {
+        // Only called once NVML is initialized.
+        let nvml = self.nvml.get()?;
+        self.devices
+            .get_or_init(|| {
+                let devices = self.requested_devices.clone()?;
+                if !devices
+                    .iter()
+                    .any(|id| matches!(id, DeviceId::CudaIndex(_)))
+                {
+                    return Some(devices);
+                }
+                let mut names = self.devices(nvml).map(|(_, device)| device.name().ok());
+                let first = names.next().flatten();
+                if names.all(|name| name.is_some() && name == first) {
+                    return Some(devices);
+                }
+                tracing::warn!(
+                    "CUDA_VISIBLE_DEVICES lists indices of different GPUs without \
+                     CUDA_DEVICE_ORDER=PCI_BUS_ID, polling every device"
+                );
+                None
+            })
+            .as_deref()
+    }
+
+    fn is_selected(&self, index: Option<u32>, uuid: &str) -> bool {
+        match self.selected_devices() {
+            Some(devices) => devices.iter().any(|id| id.matches(index, uuid)),
+            None => true,
+        }
+    }
+
+    /// With MIG enabled, processes run on the MIG instances and are not
+    /// visible on the physical device.
+    fn sample_mig_devices(
+        &self,
+        device: &Device,
+        index: u32,
+        uuid: &str,
+        pid: u32,
+    ) -> Vec<GpuProcessSample> {
+        let instance_count = device.mig_device_count().unwrap_or(0);
+        (0..instance_count)
+            .filter_map(|instance| {
+                // Slots without a configured instance return an error.
+                let mig_device = device.mig_device_by_index(instance).ok()?;
+                let mig_uuid = mig_device.uuid().unwrap_or_default();
+                if !self.is_selected(Some(index), uuid) && !self.is_selected(None, &mig_uuid) {
+                    return None;
+                }
+                // NVML does not report process utilization on MIG instances.
+                Some(GpuProcessSample {
+                    vendor: "nvidia",
+                    index,
+                    uuid: mig_uuid,
+                    mig_instance: Some(instance),
+                    memory_used: Some(process_memory(&mig_device, pid)),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn sample_device(
+        &self,
+        device: &Device,
+        index: u32,
+        uuid: String,
+        pid: u32,
+    ) -> GpuProcessSample {
+        let mut sample = GpuProcessSample {
+            vendor: "nvidia",
+            index,
+            uuid,
+            mig_instance: None,
+            memory_used: Some(process_memory(device, pid)),
+            sm_utilization: Some(0),
+            encoder_utilization: Some(0),
+            decoder_utilization: Some(0),
+        };
+
+        let mut last_timestamps = self.last_sample_timestamps.lock().unwrap();
+        let last_timestamp = last_timestamps.entry(index).or_default();
+        // `NotFound` when no process used the device since the last sample.
+        let utilization_samples = match device.process_utilization_stats(*last_timestamp) {
+            Ok(samples) => samples,
+            Err(NvmlError::NotFound) => Vec::new(),
+            Err(err) => {
+                tracing::debug!(
+                    "Could not read the process utilization of GPU {}. Error: {:?}",
+                    index,
+                    err
+                );
+                Vec::new()
+            }
+        };
+        if let Some(latest) = utilization_samples.iter().map(|s| s.timestamp).max() {
+            *last_timestamp = latest;
+        }
+        // Several samples may be returned for the same process, keep the latest.
+        if let Some(utilization) = utilization_samples
+            .iter()
+            .filter(|utilization| utilization.pid == pid)
+            .max_by_key(|utilization| utilization.timestamp)
+        {
+            sample.sm_utilization = Some(utilization.sm_util);
+            sample.encoder_utilization = Some(utilization.enc_util);
+            sample.decoder_utilization = Some(utilization.dec_util);
+        }
+
+        sample
+    }
+}
+
+struct Retry {
+    next_attempt: Instant,
+    delay: Duration,
+    attempts: u32,
+}
+
+/// Memory used by the process on the device, in bytes.
+fn process_memory(device: &Device, pid: u32) -> u64 {
+    // OpenGL/Vulkan workloads only show up as graphics processes. A process
+    // using both reports the same memory in each list.
+    let processes = [
+        device.running_compute_processes(),
+        device.running_graphics_processes(),
+    ];
+    for err in processes
+        .iter()
+        .filter_map(|processes| processes.as_ref().err())
+    {
+        tracing::debug!("Could not list the processes of a GPU. Error: {:?}", err);
+    }
+    processes
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|process| process.pid == pid)
+        .map(|process| match process.used_gpu_memory {
+            UsedGpuMemory::Used(bytes) => bytes,
+            UsedGpuMemory::Unavailable => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+impl GpuSampler for NvmlSampler {
+    /// Sample every selected device. Devices the process is not running on
+    /// are reported with zero usage.
+    fn sample(&self, pid: u32) -> Vec<GpuProcessSample> {
+        let Some(nvml) = self.nvml() else {
+            return Vec::new();
+        };
+
+        let mut samples = Vec::new();
+        for (index, device) in self.devices(nvml) {
+            let uuid = device.uuid().unwrap_or_default();
+            let mig_enabled = device
+                .mig_mode()
+                .is_ok_and(|mode| mode.current == NVML_DEVICE_MIG_ENABLE);
+            if mig_enabled {
+                samples.extend(self.sample_mig_devices(&device, index, &uuid, pid));
+            } else if self.is_selected(Some(index), &uuid) {
+                samples.push(self.sample_device(&device, index, uuid, pid));
+            }
+        }
+        samples
+    }
+
+    fn sample_devices(&self) -> Vec<GpuDeviceSample> {
+        let Some(nvml) = self.nvml() else {
+            return Vec::new();
+        };
+
+        self.devices(nvml)
+            .map(|(index, device)| GpuDeviceSample {
+                vendor: "nvidia",
+                index,
+                uuid: device.uuid().unwrap_or_default(),
+                utilization: device.utilization_rates().ok().map(|rates| rates.gpu),
+                // NVML reports milliwatts.
+                power_usage: device
+                    .power_usage()
+                    .ok()
+                    .map(|power| f64::from(power) / 1000.0),
+                temperature: device
+                    .temperature(TemperatureSensor::Gpu)
+                    .ok()
+                    .map(f64::from),
+                sm_clock: device.clock_info(Clock::SM).ok(),
+                memory_clock: device.clock_info(Clock::Memory).ok(),
+                throttle_reasons: device
+                    .current_throttle_reasons()
+                    .map(|active| {
+                        THROTTLE_REASONS
+                            .iter()
+                            .map(|(name, reason)| (*name, active.contains(*reason)))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,103 @@
+//! Totals of the processes of an observer, see
+//! [`crate::ProcessObserverBuilder::with_group`].
+
+use std::sync::{Arc, Mutex};
+
+use opentelemetry::metrics::{Meter, Unit};
+
+use crate::instruments::Overflow;
+use crate::periodic::{Latest, Registration};
+use crate::{
+    prefixed, ProcessMetricsConfig, Result, PROCESS_GROUP_COUNT, PROCESS_GROUP_CPU_USAGE,
+    PROCESS_GROUP_MEMORY_USAGE, PROCESS_GROUP_MEMORY_VIRTUAL,
+};
+
+/// The group instruments of an observer, recording the sum of the latest
+/// samples of its processes.
+pub(crate) struct Group {
+    // The latest samples of the processes of the observer. Kept apart from
+    // the entries of the registry, as the callback cannot lock its
+    // scheduler: the scheduler is locked when unregistering callbacks.
+    members: Arc<Mutex<Vec<Arc<Mutex<Latest>>>>>,
+    _registration: Registration,
+}
+
+impl Group {
+    pub(crate) fn register(meter: &Meter, config: &ProcessMetricsConfig) -> Result<Self> {
+        let groups = config.metric_groups;
+        let prefix = config.metric_prefix.as_str();
+        let cpu_usage = groups.cpu.then(|| {
+            meter
+                .f64_observable_gauge(prefixed(prefix, PROCESS_GROUP_CPU_USAGE))
+                .with_description("The percentage of a single CPU in use by the processes.")
+                .with_unit(Unit::new("%"))
+                .init()
+        });
+        let memory_usage = groups.memory.then(|| {
+            meter
+                .i64_observable_gauge(prefixed(prefix, PROCESS_GROUP_MEMORY_USAGE))
+                .with_description("The amount of physical memory in use by the processes.")
+                .with_unit(Unit::new("byte"))
+                .init()
+        });
+        let memory_virtual = groups.memory.then(|| {
+            meter
+                .i64_observable_gauge(prefixed(prefix, PROCESS_GROUP_MEMORY_VIRTUAL))
+                .with_description("The amount of committed virtual memory of the processes.")
+                .with_unit(Unit::new("byte"))
+                .init()
+        });
+        let count = meter
+            .u64_observable_gauge(prefixed(prefix, PROCESS_GROUP_COUNT))
+            .with_description("The number of processes running.")
+            .init();
+
+        let mut instruments = vec![count.as_any()];
+        instruments.extend(cpu_usage.as_ref().map(|i| i.as_any()));
+        instruments.extend(memory_usage.as_ref().map(|i| i.as_any()));
+        instruments.extend(memory_virtual.as_ref().map(|i| i.as_any()));
+
+        let members: Arc<Mutex<Vec<Arc<Mutex<Latest>>>>> = Arc::default();
+        let callback_members = members.clone();
+        let attributes = config.attributes.clone();
+        let overflow = Overflow::new(config.on_overflow);
+        let registration = meter.register_callback(&instruments, move |observer| {
+            let (mut processes, mut cpu, mut memory, mut virtual_memory) = (0, 0.0, 0, 0);
+            for latest in callback_members.lock().unwrap().iter() {
+                match &*latest.lock().unwrap() {
+                    Latest::Sample { snapshot, .. } if !snapshot.zombie => {
+                        processes += 1;
+                        cpu += f64::from(snapshot.cpu_usage);
+                        memory = snapshot.memory.saturating_add(memory);
+                        virtual_memory = snapshot.virtual_memory.saturating_add(virtual_memory);
+                    }
+                    _ => {}
+                }
+            }
+            observer.observe_u64(&count, processes, &attributes);
+            if let Some(cpu_usage) = &cpu_usage {
+                observer.observe_f64(cpu_usage, cpu, &attributes);
+            }
+            if let Some(memory_usage) = &memory_usage {
+                if let Some(memory) = overflow.to_i64(PROCESS_GROUP_MEMORY_USAGE, memory) {
+                    observer.observe_i64(memory_usage, memory, &attributes);
+                }
+            }
+            if let Some(memory_virtual) = &memory_virtual {
+                let virtual_memory = overflow.to_i64(PROCESS_GROUP_MEMORY_VIRTUAL, virtual_memory);
+                if let Some(virtual_memory) = virtual_memory {
+                    observer.observe_i64(memory_virtual, virtual_memory, &attributes);
+                }
+            }
+        })?;
+        Ok(Self {
+            members,
+            _registration: Registration::new(registration),
+        })
+    }
+
+    /// Replace the processes summed.
+    pub(crate) fn set_members(&self, members: impl IntoIterator<Item = Arc<Mutex<Latest>>>) {
+        *self.members.lock().unwrap() = members.into_iter().collect();
+    }
+}
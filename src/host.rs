@@ -0,0 +1,230 @@
+use std::sync::Mutex;
+
+use sysinfo::{System, SystemExt};
+
+use opentelemetry::metrics::Meter;
+use opentelemetry::metrics::Unit;
+use opentelemetry::Key;
+
+use crate::gpu;
+use crate::{prefixed, Result};
+use crate::{GPU_INDEX, GPU_UUID, GPU_VENDOR};
+#[cfg(target_os = "linux")]
+use crate::{PRESSURE_KIND, PRESSURE_RESOURCE};
+
+#[cfg(unix)]
+const SYSTEM_CPU_LOAD_AVERAGE_1M: &str = "system.cpu.load_average.1m";
+#[cfg(unix)]
+const SYSTEM_CPU_LOAD_AVERAGE_5M: &str = "system.cpu.load_average.5m";
+#[cfg(unix)]
+const SYSTEM_CPU_LOAD_AVERAGE_15M: &str = "system.cpu.load_average.15m";
+const SYSTEM_MEMORY_SWAP_USAGE: &str = "system.memory.swap.usage";
+#[cfg(target_os = "linux")]
+const SYSTEM_PRESSURE_STALL: &str = "system.pressure.stall";
+#[cfg(target_os = "linux")]
+const SYSTEM_PRESSURE_STALL_TIME: &str = "system.pressure.stall.time";
+const GPU_UTILIZATION: &str = "gpu.utilization";
+const GPU_POWER_USAGE: &str = "gpu.power.usage";
+const GPU_TEMPERATURE: &str = "gpu.temperature";
+const GPU_CLOCK_FREQUENCY: &str = "gpu.clock.frequency";
+const GPU_THROTTLE: &str = "gpu.throttle";
+const GPU_CLOCK: Key = Key::from_static_str("gpu.clock");
+const GPU_THROTTLE_REASON: Key = Key::from_static_str("gpu.throttle.reason");
+
+/// Record asynchronously information about the host the process is running on.
+///
+/// Unlike [`crate::init_process_observer`], these metrics are not attached to
+/// a process and should be registered once per host.
+///
+/// The load average is only recorded on Unix, Windows having none.
+///
+/// GPU utilization, power draw, temperature, clocks and throttle reasons are
+/// recorded for every device, with the
+/// `gpu.vendor`, `gpu.index` and `gpu.uuid` attributes. With the `battery`
+/// feature enabled, battery charge, state and energy rate are recorded as
+/// well. On Linux, the pressure stall information of the CPU, memory and IO
+/// is recorded with the `pressure.resource` and `pressure.kind` attributes.
+///
+/// Does nothing when `OTEL_SDK_DISABLED` is `true`.
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::init_host_observer;
+///
+/// let meter = global::meter("host-meter");
+/// init_host_observer(meter);
+/// ```
+///
+pub fn init_host_observer(meter: Meter) -> Result<()> {
+    if crate::env::sdk_disabled() {
+        tracing::debug!("Host metrics disabled, not registering the observer");
+        return Ok(());
+    }
+    let sys = Mutex::new(System::new());
+    let prefix = &crate::env::metric_prefix();
+
+    let swap_usage = meter
+        .u64_observable_gauge(prefixed(prefix, SYSTEM_MEMORY_SWAP_USAGE))
+        .with_description("The amount of swap space in use.")
+        .with_unit(Unit::new("byte"))
+        .init();
+
+    meter.register_callback(&[swap_usage.as_any()], move |context| {
+        let mut sys = sys.lock().unwrap();
+        sys.refresh_memory();
+        context.observe_u64(&swap_usage, sys.used_swap(), &[]);
+    })?;
+
+    #[cfg(unix)]
+    register_load_average_metrics(&meter, prefix)?;
+
+    register_gpu_metrics(&meter, prefix)?;
+
+    #[cfg(target_os = "linux")]
+    register_pressure_metrics(&meter, prefix)?;
+
+    #[cfg(feature = "battery")]
+    crate::battery::register_battery_metrics(&meter, prefix)?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn register_load_average_metrics(meter: &Meter, prefix: &str) -> Result<()> {
+    let sys = System::new();
+    let load_average_1m = meter
+        .f64_observable_gauge(prefixed(prefix, SYSTEM_CPU_LOAD_AVERAGE_1M))
+        .with_description("The average number of runnable processes over the last minute.")
+        .init();
+    let load_average_5m = meter
+        .f64_observable_gauge(prefixed(prefix, SYSTEM_CPU_LOAD_AVERAGE_5M))
+        .with_description("The average number of runnable processes over the last 5 minutes.")
+        .init();
+    let load_average_15m = meter
+        .f64_observable_gauge(prefixed(prefix, SYSTEM_CPU_LOAD_AVERAGE_15M))
+        .with_description("The average number of runnable processes over the last 15 minutes.")
+        .init();
+
+    meter.register_callback(
+        &[
+            load_average_1m.as_any(),
+            load_average_5m.as_any(),
+            load_average_15m.as_any(),
+        ],
+        move |context| {
+            // Load average is read fresh from the OS on every call, no refresh needed.
+            let load_average = sys.load_average();
+
+            context.observe_f64(&load_average_1m, load_average.one, &[]);
+            context.observe_f64(&load_average_5m, load_average.five, &[]);
+            context.observe_f64(&load_average_15m, load_average.fifteen, &[]);
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn register_pressure_metrics(meter: &Meter, prefix: &str) -> Result<()> {
+    let stall = meter
+        .f64_observable_gauge(prefixed(prefix, SYSTEM_PRESSURE_STALL))
+        .with_description("The percentage of the last 10 seconds tasks were stalled on a resource.")
+        .with_unit(Unit::new("%"))
+        .init();
+    let stall_time = meter
+        .f64_observable_counter(prefixed(prefix, SYSTEM_PRESSURE_STALL_TIME))
+        .with_description("The time tasks were stalled on a resource.")
+        .with_unit(Unit::new("s"))
+        .init();
+
+    meter.register_callback(&[stall.as_any(), stall_time.as_any()], move |context| {
+        for pressure in crate::linux::host_pressure() {
+            let attributes = [
+                PRESSURE_RESOURCE.string(pressure.resource),
+                PRESSURE_KIND.string(pressure.kind),
+            ];
+            context.observe_f64(&stall, pressure.avg10, &attributes);
+            context.observe_f64(&stall_time, pressure.total.as_secs_f64(), &attributes);
+        }
+    })?;
+    Ok(())
+}
+
+fn register_gpu_metrics(meter: &Meter, prefix: &str) -> Result<()> {
+    let gpu_samplers = gpu::device_samplers();
+
+    let gpu_utilization = meter
+        .u64_observable_gauge(prefixed(prefix, GPU_UTILIZATION))
+        .with_description("The percentage of time the GPU was busy.")
+        .with_unit(Unit::new("%"))
+        .init();
+    let gpu_power_usage = meter
+        .f64_observable_gauge(prefixed(prefix, GPU_POWER_USAGE))
+        .with_description("The power drawn by the GPU.")
+        .with_unit(Unit::new("W"))
+        .init();
+    let gpu_temperature = meter
+        .f64_observable_gauge(prefixed(prefix, GPU_TEMPERATURE))
+        .with_description("The temperature of the GPU die.")
+        .with_unit(Unit::new("Cel"))
+        .init();
+    let gpu_clock_frequency = meter
+        .u64_observable_gauge(prefixed(prefix, GPU_CLOCK_FREQUENCY))
+        .with_description("The current clock frequency of the GPU.")
+        .with_unit(Unit::new("MHz"))
+        .init();
+    let gpu_throttle = meter
+        .u64_observable_gauge(prefixed(prefix, GPU_THROTTLE))
+        .with_description("Set to 1 while the GPU clocks are throttled for the given reason.")
+        .init();
+
+    meter.register_callback(
+        &[
+            gpu_utilization.as_any(),
+            gpu_power_usage.as_any(),
+            gpu_temperature.as_any(),
+            gpu_clock_frequency.as_any(),
+            gpu_throttle.as_any(),
+        ],
+        move |context| {
+            let devices = gpu_samplers
+                .iter()
+                .flat_map(|sampler| sampler.sample_devices());
+            for device in devices {
+                let attributes = [
+                    GPU_VENDOR.string(device.vendor),
+                    GPU_INDEX.i64(device.index.into()),
+                    GPU_UUID.string(device.uuid),
+                ];
+
+                if let Some(utilization) = device.utilization {
+                    context.observe_u64(&gpu_utilization, utilization.into(), &attributes);
+                }
+                if let Some(power_usage) = device.power_usage {
+                    context.observe_f64(&gpu_power_usage, power_usage, &attributes);
+                }
+                if let Some(temperature) = device.temperature {
+                    context.observe_f64(&gpu_temperature, temperature, &attributes);
+                }
+                for (clock, frequency) in [("sm", device.sm_clock), ("memory", device.memory_clock)]
+                {
+                    if let Some(frequency) = frequency {
+                        context.observe_u64(
+                            &gpu_clock_frequency,
+                            frequency.into(),
+                            &[attributes.as_slice(), &[GPU_CLOCK.string(clock)]].concat(),
+                        );
+                    }
+                }
+                for (reason, active) in device.throttle_reasons {
+                    context.observe_u64(
+                        &gpu_throttle,
+                        active.into(),
+                        &[attributes.as_slice(), &[GPU_THROTTLE_REASON.string(reason)]].concat(),
+                    );
+                }
+            }
+        },
+    )?;
+    Ok(())
+}
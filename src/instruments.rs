@@ -0,0 +1,964 @@
+//! Instruments recording the CPU, memory and disk usage of the process.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use opentelemetry::metrics::{
+    AsyncInstrument, Histogram, Meter, MetricsError, ObservableCounter, ObservableGauge, Observer,
+    Unit,
+};
+use opentelemetry::KeyValue;
+
+use crate::periodic::CpuStats;
+use crate::sampler::ProcessMetricsSnapshot;
+use crate::semconv::SemconvInstruments;
+use crate::unchanged::LastValues;
+use crate::{
+    prefixed, MetricGroups, OnOverflow, ProcessMetricsConfig, AGGREGATION, CPU_STATE,
+    DELAY_RESOURCE, DIRECTION, GPU_ENGINE, GPU_INDEX, GPU_MIG_INSTANCE, GPU_UUID, GPU_VENDOR,
+    GUI_OBJECT_TYPE, MEMORY_STATE, NETWORK_CONNECTION_STATE, NETWORK_TRANSPORT, NUMA_NODE,
+    PRESSURE_KIND, PRESSURE_RESOURCE, PRIORITY_CLASS, PROCESS_ALIVE, PROCESS_CGROUP_OOM_KILLS,
+    PROCESS_CGROUP_PRESSURE_STALL, PROCESS_CGROUP_PRESSURE_STALL_TIME, PROCESS_CPU_ALLOWED,
+    PROCESS_CPU_TIME, PROCESS_CPU_USAGE, PROCESS_CPU_USAGE_AGGREGATE, PROCESS_CPU_UTILIZATION,
+    PROCESS_DELAY_TIME, PROCESS_DISK_IO, PROCESS_DISK_IO_RATE, PROCESS_GPU_MEMORY_USAGE,
+    PROCESS_GPU_UTILIZATION, PROCESS_GUI_OBJECTS, PROCESS_HANDLE_COUNT, PROCESS_INFO,
+    PROCESS_MEMORY_BREAKDOWN, PROCESS_MEMORY_GROWTH_RATE, PROCESS_MEMORY_HUGEPAGES,
+    PROCESS_MEMORY_LOCKED, PROCESS_MEMORY_NUMA, PROCESS_MEMORY_PEAK, PROCESS_MEMORY_PSS,
+    PROCESS_MEMORY_SWAP, PROCESS_MEMORY_USAGE, PROCESS_MEMORY_USS, PROCESS_MEMORY_VIRTUAL,
+    PROCESS_MEMORY_VIRTUAL_PEAK, PROCESS_NETWORK_CONNECTIONS, PROCESS_NICE, PROCESS_OOM_SCORE,
+    PROCESS_OOM_SCORE_ADJ, PROCESS_PRIORITY_CLASS, PROCESS_STATE, PROCESS_THREAD_CPU_TIME,
+    THREAD_ID, THREAD_NAME,
+};
+
+/// A naming and typing scheme for the process instruments, see
+/// [`crate::ProcessMetricsConfig::semconv`].
+pub(crate) trait ProcessInstruments: Send + Sync {
+    /// Every instrument observed by [`ProcessInstruments::observe`].
+    fn instruments(&self) -> Vec<Arc<dyn Any>>;
+
+    fn observe(
+        &self,
+        observer: &dyn Observer,
+        snapshot: &ProcessMetricsSnapshot,
+        attributes: &[KeyValue],
+    );
+
+    /// Record the CPU usage of a sample into the histogram, with
+    /// [`crate::ProcessMetricsConfig::cpu_histogram`].
+    fn record_cpu(&self, cpu_usage: f32, attributes: &[KeyValue]);
+}
+
+/// Counts the data points recorded on an observer, for the spans of the
+/// observers.
+pub(crate) struct CountingObserver<'a> {
+    observer: &'a dyn Observer,
+    count: Cell<usize>,
+}
+
+impl<'a> CountingObserver<'a> {
+    pub(crate) fn new(observer: &'a dyn Observer) -> Self {
+        Self {
+            observer,
+            count: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count.get()
+    }
+}
+
+impl Observer for CountingObserver<'_> {
+    fn observe_f64(&self, inst: &dyn AsyncInstrument<f64>, measurement: f64, attrs: &[KeyValue]) {
+        self.count.set(self.count.get() + 1);
+        self.observer.observe_f64(inst, measurement, attrs);
+    }
+
+    fn observe_u64(&self, inst: &dyn AsyncInstrument<u64>, measurement: u64, attrs: &[KeyValue]) {
+        self.count.set(self.count.get() + 1);
+        self.observer.observe_u64(inst, measurement, attrs);
+    }
+
+    fn observe_i64(&self, inst: &dyn AsyncInstrument<i64>, measurement: i64, attrs: &[KeyValue]) {
+        self.count.set(self.count.get() + 1);
+        self.observer.observe_i64(inst, measurement, attrs);
+    }
+}
+
+/// Conversion of the values to the type of their instrument, see
+/// [`OnOverflow`].
+pub(crate) struct Overflow {
+    on_overflow: OnOverflow,
+    count: AtomicU64,
+}
+
+impl Overflow {
+    pub(crate) fn new(on_overflow: OnOverflow) -> Self {
+        Self {
+            on_overflow,
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// `value` as an `i64`, `None` to skip the data point of `name`.
+    pub(crate) fn to_i64(&self, name: &str, value: u64) -> Option<i64> {
+        if let Ok(value) = value.try_into() {
+            return Some(value);
+        }
+        if self.on_overflow == OnOverflow::Strict {
+            // Panicking here would unwind through the callback of the SDK.
+            opentelemetry::global::handle_error(MetricsError::Other(format!(
+                "{value} does not fit in the i64 instrument {name}"
+            )));
+            return None;
+        }
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        tracing::warn!(
+            "{} does not fit in the i64 instrument {}, {} overflows so far",
+            value,
+            name,
+            count
+        );
+        (self.on_overflow == OnOverflow::Saturate).then_some(i64::MAX)
+    }
+}
+
+/// The instruments historically recorded by the crate.
+pub(crate) struct DefaultInstruments {
+    // `None` reports the percentage of a single CPU.
+    cpu_count: Option<usize>,
+    // A percentage of `cpu_count` rather than a ratio, see
+    // `CpuNormalization::PhysicalCoresPercent`.
+    cpu_percent: bool,
+    // `None` for disabled groups.
+    cpu_utilization: Option<ObservableGauge<f64>>,
+    // Instead of `cpu_utilization`, with `ProcessMetricsConfig::cpu_histogram`.
+    cpu_utilization_histogram: Option<Histogram<f64>>,
+    cpu_usage: Option<ObservableGauge<f64>>,
+    memory_usage: Option<ObservableGauge<i64>>,
+    memory_virtual: Option<ObservableGauge<i64>>,
+    disk_io: Option<ObservableCounter<u64>>,
+    overflow: Overflow,
+}
+
+impl DefaultInstruments {
+    pub(crate) fn new(
+        meter: &Meter,
+        cpu_count: Option<usize>,
+        groups: MetricGroups,
+        cpu_histogram: bool,
+        prefix: &str,
+        on_overflow: OnOverflow,
+        cpu_percent: bool,
+    ) -> Self {
+        let (description, unit) = match (cpu_count, cpu_percent) {
+            (Some(_), true) => ("The percentage of CPU in use.", ""),
+            (Some(_), false) => ("The ratio of CPU in use, over the number of CPUs.", "1"),
+            (None, _) => ("The percentage of a single CPU in use.", "%"),
+        };
+        Self {
+            cpu_count,
+            cpu_percent,
+            cpu_utilization: (groups.cpu && !cpu_histogram).then(|| {
+                meter
+                    .f64_observable_gauge(prefixed(prefix, PROCESS_CPU_USAGE))
+                    .with_description(description)
+                    .with_unit(Unit::new(unit))
+                    .init()
+            }),
+            cpu_utilization_histogram: (groups.cpu && cpu_histogram).then(|| {
+                meter
+                    .f64_histogram(prefixed(prefix, PROCESS_CPU_USAGE))
+                    .with_description(description)
+                    .with_unit(Unit::new(unit))
+                    .init()
+            }),
+            cpu_usage: groups.cpu.then(|| {
+                meter
+                    .f64_observable_gauge(prefixed(prefix, PROCESS_CPU_UTILIZATION))
+                    .with_description("The amount of CPU in use.")
+                    .init()
+            }),
+            memory_usage: groups.memory.then(|| {
+                meter
+                    .i64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_USAGE))
+                    .with_description("The amount of physical memory in use.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_virtual: groups.memory.then(|| {
+                meter
+                    .i64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_VIRTUAL))
+                    .with_description("The amount of committed virtual memory.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            disk_io: groups.disk.then(|| {
+                meter
+                    .u64_observable_counter(prefixed(prefix, PROCESS_DISK_IO))
+                    .with_description("Disk bytes transferred since the process started.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            overflow: Overflow::new(on_overflow),
+        }
+    }
+
+    fn cpu_utilization(&self, cpu_usage: f32) -> f64 {
+        // sysinfo reports 100 per fully used CPU.
+        match self.cpu_count {
+            Some(cpu_count) if self.cpu_percent => f64::from(cpu_usage) / cpu_count as f64,
+            Some(cpu_count) => f64::from(cpu_usage) / 100.0 / cpu_count as f64,
+            None => cpu_usage.into(),
+        }
+    }
+}
+
+impl ProcessInstruments for DefaultInstruments {
+    fn instruments(&self) -> Vec<Arc<dyn Any>> {
+        [
+            self.cpu_utilization.as_ref().map(|i| i.as_any()),
+            self.cpu_usage.as_ref().map(|i| i.as_any()),
+            self.memory_usage.as_ref().map(|i| i.as_any()),
+            self.memory_virtual.as_ref().map(|i| i.as_any()),
+            self.disk_io.as_ref().map(|i| i.as_any()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn observe(
+        &self,
+        observer: &dyn Observer,
+        snapshot: &ProcessMetricsSnapshot,
+        attributes: &[KeyValue],
+    ) {
+        let cpu_usage = snapshot.cpu_usage;
+
+        if let Some(instrument) = &self.cpu_usage {
+            observer.observe_f64(instrument, cpu_usage.into(), attributes);
+        }
+        if let Some(instrument) = &self.cpu_utilization {
+            observer.observe_f64(instrument, self.cpu_utilization(cpu_usage), attributes);
+        }
+        if let Some(instrument) = &self.memory_usage {
+            if let Some(memory) = self.overflow.to_i64(PROCESS_MEMORY_USAGE, snapshot.memory) {
+                observer.observe_i64(instrument, memory, attributes);
+            }
+        }
+        if let Some(instrument) = &self.memory_virtual {
+            let virtual_memory = self
+                .overflow
+                .to_i64(PROCESS_MEMORY_VIRTUAL, snapshot.virtual_memory);
+            if let Some(virtual_memory) = virtual_memory {
+                observer.observe_i64(instrument, virtual_memory, attributes);
+            }
+        }
+        if let Some(instrument) = &self.disk_io {
+            observer.observe_u64(
+                instrument,
+                snapshot.disk_read,
+                &[attributes, &[DIRECTION.string("read")]].concat(),
+            );
+            observer.observe_u64(
+                instrument,
+                snapshot.disk_written,
+                &[attributes, &[DIRECTION.string("write")]].concat(),
+            );
+        }
+    }
+
+    fn record_cpu(&self, cpu_usage: f32, attributes: &[KeyValue]) {
+        if let Some(instrument) = &self.cpu_utilization_histogram {
+            instrument.record(self.cpu_utilization(cpu_usage), attributes);
+        }
+    }
+}
+
+/// Every instrument of a process observer, recording snapshots of the
+/// process.
+pub(crate) struct ProcessRecorder {
+    process_instruments: Box<dyn ProcessInstruments>,
+    // `None` for disabled groups.
+    cpu_time: Option<ObservableCounter<f64>>,
+    cpu_allowed: Option<ObservableGauge<u64>>,
+    nice: Option<ObservableGauge<i64>>,
+    priority_class: Option<ObservableGauge<u64>>,
+    // Only with `ProcessMetricsConfig::thread_cpu_top`.
+    thread_cpu_time: Option<ObservableCounter<f64>>,
+    // Only with the `taskstats` feature.
+    delay_time: Option<ObservableCounter<f64>>,
+    // Only with `ProcessMetricsConfig::cpu_sampling_interval`.
+    cpu_usage_aggregate: Option<ObservableGauge<f64>>,
+    memory_swap: Option<ObservableGauge<u64>>,
+    memory_growth_rate: Option<ObservableGauge<f64>>,
+    memory_breakdown: Option<ObservableGauge<u64>>,
+    // Only with the `smaps` feature.
+    memory_pss: Option<ObservableGauge<u64>>,
+    memory_uss: Option<ObservableGauge<u64>>,
+    memory_peak: Option<ObservableGauge<u64>>,
+    memory_virtual_peak: Option<ObservableGauge<u64>>,
+    memory_hugepages: Option<ObservableGauge<u64>>,
+    memory_locked: Option<ObservableGauge<u64>>,
+    // Only with the `numa` feature.
+    memory_numa: Option<ObservableGauge<u64>>,
+    oom_score: Option<ObservableGauge<u64>>,
+    handle_count: Option<ObservableGauge<u64>>,
+    gui_objects: Option<ObservableGauge<u64>>,
+    oom_score_adj: Option<ObservableGauge<i64>>,
+    cgroup_oom_kills: Option<ObservableCounter<u64>>,
+    disk_io_rate: Option<ObservableGauge<f64>>,
+    gpu_memory_usage: Option<ObservableGauge<u64>>,
+    gpu_utilization: Option<ObservableGauge<u64>>,
+    pressure_stall: Option<ObservableGauge<f64>>,
+    pressure_stall_time: Option<ObservableCounter<f64>>,
+    network_connections: Option<ObservableGauge<u64>>,
+    alive: ObservableGauge<u64>,
+    // Only with `IdentityAttributes::InfoMetric`, with its attributes.
+    info: Option<(ObservableGauge<u64>, Vec<KeyValue>)>,
+    // Only with `ProcessMetricsConfig::suppress_unchanged`.
+    last_values: Option<LastValues>,
+    attributes: Vec<KeyValue>,
+    // To create the instruments again with other metric groups.
+    config: ProcessMetricsConfig,
+    cpu_count: Option<usize>,
+}
+
+impl ProcessRecorder {
+    pub(crate) fn new(
+        meter: &Meter,
+        config: &ProcessMetricsConfig,
+        cpu_count: Option<usize>,
+        attributes: Vec<KeyValue>,
+        info_attributes: Option<Vec<KeyValue>>,
+    ) -> Self {
+        let groups = config.metric_groups;
+        let prefix = config.metric_prefix.as_str();
+        let process_instruments: Box<dyn ProcessInstruments> = if config.semconv {
+            Box::new(SemconvInstruments::new(
+                meter,
+                cpu_count,
+                groups,
+                config.cpu_histogram,
+                prefix,
+                config.on_overflow,
+            ))
+        } else {
+            Box::new(DefaultInstruments::new(
+                meter,
+                cpu_count,
+                groups,
+                config.cpu_histogram,
+                prefix,
+                config.on_overflow,
+                config.cpu_percent(),
+            ))
+        };
+        Self {
+            process_instruments,
+            cpu_time: groups.cpu.then(|| {
+                meter
+                    .f64_observable_counter(prefixed(prefix, PROCESS_CPU_TIME))
+                    .with_description("Total CPU seconds broken down by different CPU states.")
+                    .with_unit(Unit::new("s"))
+                    .init()
+            }),
+            cpu_allowed: groups.cpu.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_CPU_ALLOWED))
+                    .with_description(
+                        "The number of CPUs the process is allowed to run on. Only available on Linux.",
+                    )
+                    .with_unit(Unit::new("{cpu}"))
+                    .init()
+            }),
+            nice: groups.cpu.then(|| {
+                meter
+                    .i64_observable_gauge(prefixed(prefix, PROCESS_NICE))
+                    .with_description(
+                        "The nice value of the process, from -20, the highest priority, to 19. Only available on Linux, macOS and FreeBSD.",
+                    )
+                    .init()
+            }),
+            priority_class: groups.cpu.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_PRIORITY_CLASS))
+                    .with_description(
+                        "Set to 1 for the current priority class of the process. Only available on Windows.",
+                    )
+                    .init()
+            }),
+            thread_cpu_time: (groups.cpu && config.thread_cpu_top.is_some()).then(|| {
+                meter
+                    .f64_observable_counter(prefixed(prefix, PROCESS_THREAD_CPU_TIME))
+                    .with_description(
+                        "Total CPU seconds of the busiest threads broken down by different CPU states.",
+                    )
+                    .with_unit(Unit::new("s"))
+                    .init()
+            }),
+            delay_time: (groups.cpu && cfg!(feature = "taskstats")).then(|| {
+                meter
+                    .f64_observable_counter(prefixed(prefix, PROCESS_DELAY_TIME))
+                    .with_description(
+                        "The time the threads of the process waited for a resource. Only available on Linux.",
+                    )
+                    .with_unit(Unit::new("s"))
+                    .init()
+            }),
+            cpu_usage_aggregate: (groups.cpu && config.cpu_sampling_interval.is_some()).then(
+                || {
+                    let cpu_usage_aggregate = meter.f64_observable_gauge(prefixed(prefix, PROCESS_CPU_USAGE_AGGREGATE));
+                    match cpu_count {
+                        Some(_) if config.cpu_percent() => cpu_usage_aggregate
+                            .with_description(
+                                "The percentage of CPU in use since the last collection.",
+                            ),
+                        Some(_) => cpu_usage_aggregate
+                            .with_description(
+                                "The ratio of CPU in use since the last collection, over the number of CPUs.",
+                            )
+                            .with_unit(Unit::new("1")),
+                        None => cpu_usage_aggregate
+                            .with_description(
+                                "The percentage of a single CPU in use since the last collection.",
+                            )
+                            .with_unit(Unit::new("%")),
+                    }
+                    .init()
+                },
+            ),
+            memory_swap: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_SWAP))
+                    .with_description(
+                        "The amount of memory swapped out to disk. Only available on Linux.",
+                    )
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_growth_rate: groups.memory.then(|| {
+                meter
+                    .f64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_GROWTH_RATE))
+                    .with_description(
+                        "The growth of the physical memory in use, over a rolling window.",
+                    )
+                    .with_unit(Unit::new("By/min"))
+                    .init()
+            }),
+            memory_breakdown: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_BREAKDOWN))
+                    .with_description(
+                        "The amount of memory in use by state. Only available on Linux.",
+                    )
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_pss: (groups.memory && cfg!(feature = "smaps")).then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_PSS))
+                    .with_description(
+                        "The proportional set size, with shared pages divided among the processes sharing them.",
+                    )
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_uss: (groups.memory && cfg!(feature = "smaps")).then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_USS))
+                    .with_description("The unique set size, memory not shared with other processes.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_peak: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_PEAK))
+                    .with_description(
+                        "The highest amount of physical memory in use since the process started. Only available on Linux.",
+                    )
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_virtual_peak: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_VIRTUAL_PEAK))
+                    .with_description(
+                        "The highest amount of virtual memory since the process started. Only available on Linux.",
+                    )
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_hugepages: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_HUGEPAGES))
+                    .with_description("The amount of memory in huge pages. Only available on Linux.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_locked: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_LOCKED))
+                    .with_description("The amount of memory locked in RAM. Only available on Linux.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            memory_numa: (groups.memory && cfg!(feature = "numa")).then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_MEMORY_NUMA))
+                    .with_description("The amount of physical memory in use on every NUMA node.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            handle_count: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_HANDLE_COUNT))
+                    .with_description(
+                        "The number of kernel handles open by the process. Only available on Windows.",
+                    )
+                    .with_unit(Unit::new("{handle}"))
+                    .init()
+            }),
+            gui_objects: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_GUI_OBJECTS))
+                    .with_description(
+                        "The number of GDI and USER objects open by the process. Only available on Windows.",
+                    )
+                    .with_unit(Unit::new("{object}"))
+                    .init()
+            }),
+            oom_score: groups.memory.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_OOM_SCORE))
+                    .with_description(
+                        "The badness of the process for the OOM killer. Only available on Linux.",
+                    )
+                    .init()
+            }),
+            oom_score_adj: groups.memory.then(|| {
+                meter
+                    .i64_observable_gauge(prefixed(prefix, PROCESS_OOM_SCORE_ADJ))
+                    .with_description(
+                        "The adjustment of the OOM score of the process. Only available on Linux.",
+                    )
+                    .init()
+            }),
+            cgroup_oom_kills: groups.memory.then(|| {
+                meter
+                    .u64_observable_counter(prefixed(prefix, PROCESS_CGROUP_OOM_KILLS))
+                    .with_description(
+                        "Processes killed by the OOM killer in the cgroup of the process. Only available with cgroup v2.",
+                    )
+                    .init()
+            }),
+            disk_io_rate: groups.disk.then(|| {
+                meter
+                    .f64_observable_gauge(prefixed(prefix, PROCESS_DISK_IO_RATE))
+                    .with_description("Disk bytes transferred per second since the last sample.")
+                    .with_unit(Unit::new("By/s"))
+                    .init()
+            }),
+            gpu_memory_usage: groups.gpu.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_GPU_MEMORY_USAGE))
+                    .with_description("The amount of physical GPU memory in use.")
+                    .with_unit(Unit::new("byte"))
+                    .init()
+            }),
+            gpu_utilization: groups.gpu.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_GPU_UTILIZATION))
+                    .with_description(
+                        "The percentage of time the GPU engines were used by the process.",
+                    )
+                    .with_unit(Unit::new("%"))
+                    .init()
+            }),
+            pressure_stall: groups.pressure.then(|| {
+                meter
+                    .f64_observable_gauge(prefixed(prefix, PROCESS_CGROUP_PRESSURE_STALL))
+                    .with_description(
+                        "The percentage of the last 10 seconds tasks of the cgroup of the process were stalled on a resource.",
+                    )
+                    .with_unit(Unit::new("%"))
+                    .init()
+            }),
+            pressure_stall_time: groups.pressure.then(|| {
+                meter
+                    .f64_observable_counter(prefixed(prefix, PROCESS_CGROUP_PRESSURE_STALL_TIME))
+                    .with_description(
+                        "The time tasks of the cgroup of the process were stalled on a resource.",
+                    )
+                    .with_unit(Unit::new("s"))
+                    .init()
+            }),
+            network_connections: groups.network.then(|| {
+                meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_NETWORK_CONNECTIONS))
+                    .with_description(
+                        "The number of sockets of the process by protocol and TCP state. Only available on Linux and Windows.",
+                    )
+                    .with_unit(Unit::new("{connection}"))
+                    .init()
+            }),
+            alive: meter
+                .u64_observable_gauge(prefixed(prefix, PROCESS_ALIVE))
+                .with_description("1 while the process exists, 0 once it exited when reporting the exit or while it is a zombie.")
+                .init(),
+            info: info_attributes.map(|info_attributes| {
+                let info = meter
+                    .u64_observable_gauge(prefixed(prefix, PROCESS_INFO))
+                    .with_description("1 with the attributes identifying the process.")
+                    .init();
+                (info, info_attributes)
+            }),
+            last_values: config.suppress_unchanged.map(LastValues::new),
+            attributes,
+            config: config.clone(),
+            cpu_count,
+        }
+    }
+
+    /// The same recorder with other metric groups, to register instead of
+    /// this one.
+    pub(crate) fn with_metric_groups(&self, meter: &Meter, metric_groups: MetricGroups) -> Self {
+        let config = ProcessMetricsConfig {
+            metric_groups,
+            ..self.config.clone()
+        };
+        Self::new(
+            meter,
+            &config,
+            self.cpu_count,
+            self.attributes.clone(),
+            self.info.as_ref().map(|(_, attributes)| attributes.clone()),
+        )
+    }
+
+    /// Every instrument, to register the callback with.
+    pub(crate) fn instruments(&self) -> Vec<Arc<dyn Any>> {
+        let mut instruments = self.process_instruments.instruments();
+        instruments.extend(
+            [
+                self.cpu_time.as_ref().map(|i| i.as_any()),
+                self.cpu_usage_aggregate.as_ref().map(|i| i.as_any()),
+                self.cpu_allowed.as_ref().map(|i| i.as_any()),
+                self.nice.as_ref().map(|i| i.as_any()),
+                self.priority_class.as_ref().map(|i| i.as_any()),
+                self.thread_cpu_time.as_ref().map(|i| i.as_any()),
+                self.delay_time.as_ref().map(|i| i.as_any()),
+                self.memory_swap.as_ref().map(|i| i.as_any()),
+                self.memory_growth_rate.as_ref().map(|i| i.as_any()),
+                self.memory_breakdown.as_ref().map(|i| i.as_any()),
+                self.memory_pss.as_ref().map(|i| i.as_any()),
+                self.memory_uss.as_ref().map(|i| i.as_any()),
+                self.memory_peak.as_ref().map(|i| i.as_any()),
+                self.memory_virtual_peak.as_ref().map(|i| i.as_any()),
+                self.memory_hugepages.as_ref().map(|i| i.as_any()),
+                self.memory_locked.as_ref().map(|i| i.as_any()),
+                self.memory_numa.as_ref().map(|i| i.as_any()),
+                self.oom_score.as_ref().map(|i| i.as_any()),
+                self.handle_count.as_ref().map(|i| i.as_any()),
+                self.gui_objects.as_ref().map(|i| i.as_any()),
+                self.oom_score_adj.as_ref().map(|i| i.as_any()),
+                self.cgroup_oom_kills.as_ref().map(|i| i.as_any()),
+                self.disk_io_rate.as_ref().map(|i| i.as_any()),
+                self.gpu_memory_usage.as_ref().map(|i| i.as_any()),
+                self.gpu_utilization.as_ref().map(|i| i.as_any()),
+                self.pressure_stall.as_ref().map(|i| i.as_any()),
+                self.pressure_stall_time.as_ref().map(|i| i.as_any()),
+                self.network_connections.as_ref().map(|i| i.as_any()),
+                Some(self.alive.as_any()),
+                self.info.as_ref().map(|(i, _)| i.as_any()),
+            ]
+            .into_iter()
+            .flatten(),
+        );
+        instruments
+    }
+
+    pub(crate) fn record(&self, observer: &dyn Observer, snapshot: &ProcessMetricsSnapshot) {
+        let attributes = self.attributes.as_slice();
+        if snapshot.zombie {
+            let state = PROCESS_STATE.string("defunct");
+            observer.observe_u64(&self.alive, 0, &[attributes, &[state]].concat());
+            return;
+        }
+        // Always recorded, for alerts on their absence.
+        observer.observe_u64(&self.alive, 1, attributes);
+        if let Some((info, info_attributes)) = &self.info {
+            observer.observe_u64(info, 1, info_attributes);
+        }
+        let unchanged = self
+            .last_values
+            .as_ref()
+            .map(|last| last.observer(observer));
+        let observer: &dyn Observer = match &unchanged {
+            Some(unchanged) => unchanged,
+            None => observer,
+        };
+        self.process_instruments
+            .observe(observer, snapshot, attributes);
+        if let (Some(instrument), Some(cpu_times)) = (&self.cpu_time, snapshot.cpu_times) {
+            for (state, seconds) in [("user", cpu_times.user), ("system", cpu_times.system)] {
+                observer.observe_f64(
+                    instrument,
+                    seconds,
+                    &[attributes, &[CPU_STATE.string(state)]].concat(),
+                );
+            }
+        }
+        if let (Some(instrument), Some(allowed_cpus)) = (&self.cpu_allowed, snapshot.allowed_cpus) {
+            observer.observe_u64(instrument, allowed_cpus as u64, attributes);
+        }
+        if let (Some(instrument), Some(nice)) = (&self.nice, snapshot.nice) {
+            observer.observe_i64(instrument, nice.into(), attributes);
+        }
+        if let (Some(instrument), Some(class)) = (&self.priority_class, snapshot.priority_class) {
+            observer.observe_u64(
+                instrument,
+                1,
+                &[attributes, &[PRIORITY_CLASS.string(class)]].concat(),
+            );
+        }
+        if let Some(instrument) = &self.thread_cpu_time {
+            for thread in &snapshot.threads {
+                let cpu_times = thread.cpu_times;
+                for (state, seconds) in [("user", cpu_times.user), ("system", cpu_times.system)] {
+                    observer.observe_f64(
+                        instrument,
+                        seconds,
+                        &[
+                            attributes,
+                            &[
+                                THREAD_ID.i64(thread.id.into()),
+                                THREAD_NAME.string(thread.name.clone()),
+                                CPU_STATE.string(state),
+                            ],
+                        ]
+                        .concat(),
+                    );
+                }
+            }
+        }
+        if let (Some(instrument), Some(delays)) = (&self.delay_time, snapshot.delays) {
+            for (resource, delay) in [
+                ("cpu", delays.cpu),
+                ("block_io", delays.block_io),
+                ("swap_in", delays.swap_in),
+            ] {
+                observer.observe_f64(
+                    instrument,
+                    delay.as_secs_f64(),
+                    &[attributes, &[DELAY_RESOURCE.string(resource)]].concat(),
+                );
+            }
+        }
+        if let (Some(instrument), Some(swap)) = (&self.memory_swap, snapshot.swap) {
+            observer.observe_u64(instrument, swap, attributes);
+        }
+        if let (Some(instrument), Some(growth_rate)) =
+            (&self.memory_growth_rate, snapshot.memory_growth_rate)
+        {
+            observer.observe_f64(instrument, growth_rate, attributes);
+        }
+        let details = &snapshot.memory_details;
+        if let (Some(instrument), Some(_)) = (&self.memory_breakdown, details.anonymous) {
+            for (state, bytes) in [
+                ("resident", Some(snapshot.memory)),
+                ("shared", details.shared),
+                ("anonymous", details.anonymous),
+                ("swap", snapshot.swap),
+            ] {
+                let Some(bytes) = bytes else { continue };
+                observer.observe_u64(
+                    instrument,
+                    bytes,
+                    &[attributes, &[MEMORY_STATE.string(state)]].concat(),
+                );
+            }
+        }
+        for (instrument, bytes) in [
+            (&self.memory_pss, details.pss),
+            (&self.memory_uss, details.uss),
+            (&self.memory_peak, details.peak),
+            (&self.memory_virtual_peak, details.virtual_peak),
+            (&self.memory_hugepages, details.hugepages),
+            (&self.memory_locked, details.locked),
+            (&self.oom_score, details.oom_score),
+        ] {
+            if let (Some(instrument), Some(bytes)) = (instrument, bytes) {
+                observer.observe_u64(instrument, bytes, attributes);
+            }
+        }
+        if let (Some(instrument), Some(oom_score_adj)) =
+            (&self.oom_score_adj, details.oom_score_adj)
+        {
+            observer.observe_i64(instrument, oom_score_adj, attributes);
+        }
+        if let (Some(instrument), Some(oom_kills)) =
+            (&self.cgroup_oom_kills, details.cgroup_oom_kills)
+        {
+            observer.observe_u64(instrument, oom_kills, attributes);
+        }
+        if let (Some(instrument), Some(handles)) = (&self.handle_count, snapshot.handles) {
+            observer.observe_u64(instrument, handles, attributes);
+        }
+        if let Some(instrument) = &self.gui_objects {
+            for (kind, objects) in [
+                ("gdi", snapshot.gdi_objects),
+                ("user", snapshot.user_objects),
+            ] {
+                if let Some(objects) = objects {
+                    observer.observe_u64(
+                        instrument,
+                        objects,
+                        &[attributes, &[GUI_OBJECT_TYPE.string(kind)]].concat(),
+                    );
+                }
+            }
+        }
+        if let Some(instrument) = &self.memory_numa {
+            for numa_node in &details.numa_nodes {
+                observer.observe_u64(
+                    instrument,
+                    numa_node.bytes,
+                    &[attributes, &[NUMA_NODE.i64(numa_node.node.into())]].concat(),
+                );
+            }
+        }
+        if let Some(instrument) = &self.disk_io_rate {
+            // The same attribute as `process.disk.io`.
+            let direction = if self.config.semconv {
+                crate::semconv::DIRECTION
+            } else {
+                DIRECTION
+            };
+            for (value, rate) in [
+                ("read", snapshot.disk_read_rate),
+                ("write", snapshot.disk_write_rate),
+            ] {
+                let Some(rate) = rate else { continue };
+                observer.observe_f64(
+                    instrument,
+                    rate,
+                    &[attributes, &[direction.clone().string(value)]].concat(),
+                );
+            }
+        }
+        if let (Some(stall), Some(stall_time)) = (&self.pressure_stall, &self.pressure_stall_time) {
+            for pressure in &snapshot.pressure {
+                let attributes = [
+                    attributes,
+                    &[
+                        PRESSURE_RESOURCE.string(pressure.resource),
+                        PRESSURE_KIND.string(pressure.kind),
+                    ],
+                ]
+                .concat();
+                observer.observe_f64(stall, pressure.avg10, &attributes);
+                observer.observe_f64(stall_time, pressure.total.as_secs_f64(), &attributes);
+            }
+        }
+
+        if let Some(instrument) = &self.network_connections {
+            for connection in &snapshot.connections {
+                let mut connection_attributes =
+                    [attributes, &[NETWORK_TRANSPORT.string(connection.protocol)]].concat();
+                if let Some(state) = connection.state {
+                    connection_attributes.push(NETWORK_CONNECTION_STATE.string(state));
+                }
+                observer.observe_u64(instrument, connection.count, &connection_attributes);
+            }
+        }
+
+        for gpu in &snapshot.gpus {
+            let mut gpu_attributes = [
+                attributes,
+                &[
+                    GPU_VENDOR.string(gpu.vendor),
+                    GPU_INDEX.i64(gpu.index.into()),
+                    GPU_UUID.string(gpu.uuid.clone()),
+                ],
+            ]
+            .concat();
+            if let Some(instance) = gpu.mig_instance {
+                gpu_attributes.push(GPU_MIG_INSTANCE.i64(instance.into()));
+            }
+
+            if let (Some(instrument), Some(memory_used)) = (&self.gpu_memory_usage, gpu.memory_used)
+            {
+                observer.observe_u64(instrument, memory_used, &gpu_attributes);
+            }
+            for (engine, utilization) in [
+                ("sm", gpu.sm_utilization),
+                ("encoder", gpu.encoder_utilization),
+                ("decoder", gpu.decoder_utilization),
+            ] {
+                let (Some(instrument), Some(utilization)) = (&self.gpu_utilization, utilization)
+                else {
+                    continue;
+                };
+                observer.observe_u64(
+                    instrument,
+                    utilization.into(),
+                    &[gpu_attributes.as_slice(), &[GPU_ENGINE.string(engine)]].concat(),
+                );
+            }
+        }
+    }
+
+    /// Record the CPU usage of a sample, whole or not, into the histogram
+    /// if any.
+    pub(crate) fn record_cpu(&self, cpu_usage: f32) {
+        self.process_instruments
+            .record_cpu(cpu_usage, &self.attributes);
+    }
+
+    /// Record the CPU usages sampled since the previous collection.
+    pub(crate) fn record_cpu_stats(&self, observer: &dyn Observer, cpu_stats: CpuStats) {
+        let (Some(instrument), Some((min, avg, max))) =
+            (&self.cpu_usage_aggregate, cpu_stats.get())
+        else {
+            return;
+        };
+        // sysinfo reports 100 per fully used CPU.
+        let normalize = |cpu_usage: f64| match self.cpu_count {
+            Some(cpu_count) if self.config.cpu_percent() => cpu_usage / cpu_count as f64,
+            Some(cpu_count) => cpu_usage / 100.0 / cpu_count as f64,
+            None => cpu_usage,
+        };
+        for (aggregation, cpu_usage) in [
+            ("min", f64::from(min)),
+            ("avg", avg),
+            ("max", f64::from(max)),
+        ] {
+            observer.observe_f64(
+                instrument,
+                normalize(cpu_usage),
+                &[
+                    self.attributes.as_slice(),
+                    &[AGGREGATION.string(aggregation)],
+                ]
+                .concat(),
+            );
+        }
+    }
+
+    /// Whether the exit of the process is recorded, see
+    /// [`crate::OnExit::ReportExit`].
+    pub(crate) fn reports_exit(&self) -> bool {
+        self.config.on_exit == crate::OnExit::ReportExit
+    }
+
+    /// Record that the process exited, once.
+    pub(crate) fn record_exit(&self, observer: &dyn Observer) {
+        if self.reports_exit() {
+            observer.observe_u64(&self.alive, 0, &self.attributes);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Detection of the runtime of interpreted processes, such as Python or
+//! Node.js, from their executable.
+
+use crate::ProcessInfo;
+
+/// The `process.runtime.name` and, when the executable has it in its name
+/// or in a directory, the `process.runtime.version` of the process. `None`
+/// for processes which are not interpreters.
+pub(crate) fn detect(info: &ProcessInfo) -> Option<(&'static str, Option<String>)> {
+    // The path may be unreadable for processes of other users.
+    let path = match info.executable_path.as_str() {
+        "" => info.command.first()?.as_str(),
+        path => path,
+    };
+    let path = path.replace('\\', "/");
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let exe = components.last()?.trim_end_matches(".exe");
+
+    // For instance `/usr/bin/python3.11`, which `/usr/bin/python3` usually
+    // links to, or `/opt/pypy3.10-v7.3.15-linux64/bin/pypy3.10`.
+    let (name, version_prefixes): (_, &[&str]) = if exe.starts_with("pypy") {
+        ("pypy", &["pypy"])
+    } else if exe.starts_with("python") {
+        ("cpython", &["python", "cpython-"])
+    } else if matches!(exe, "node" | "nodejs") {
+        // `~/.nvm/versions/node/v20.11.1/bin/node` or
+        // `/opt/node-v20.11.1-linux-x64/bin/node`.
+        ("nodejs", &["v", "node-v", "node@"])
+    } else if exe == "java" || exe == "javaw" {
+        // `/usr/lib/jvm/java-17-openjdk-amd64/bin/java` or
+        // `/opt/jdk-21.0.2/bin/java`.
+        ("java", &["java-", "jdk-", "jdk", "openjdk-"])
+    } else if exe.starts_with("ruby") {
+        ("ruby", &["ruby", "ruby-"])
+    } else {
+        return None;
+    };
+    let version = std::iter::once(exe)
+        .chain(components.iter().rev().skip(1).copied())
+        .find_map(|component| {
+            version_prefixes
+                .iter()
+                .find_map(|prefix| version(component.strip_prefix(prefix)?))
+        });
+    Some((name, version))
+}
+
+/// The version at the start of `s`, such as `3.11` of `3.11-config`.
+fn version(s: &str) -> Option<String> {
+    let end = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let version = s[..end].trim_end_matches('.');
+    let major = version.split('.').next()?;
+    // Major versions of the runtimes above have at most two digits.
+    (!major.is_empty() && major.len() <= 2).then(|| version.to_string())
+}
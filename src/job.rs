@@ -0,0 +1,192 @@
+//! Aggregation of a process and its children in a Job Object (Windows), see
+//! [`crate::ProcessObserverBuilder::with_job_object`].
+
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
+use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+use windows_sys::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicAndIoAccountingInformation,
+    JobObjectBasicProcessIdList, QueryInformationJobObject,
+    JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION, JOBOBJECT_BASIC_PROCESS_ID_LIST,
+};
+use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+
+use crate::sampler::ProcessSampler;
+use crate::{CpuTimes, Error, MetricGroups, ProcessInfo, ProcessMetricsSnapshot, Result};
+
+// Processes listed in a sample, the others are left out of the memory usage.
+const MAX_PROCESSES: usize = 1024;
+
+/// Replaces the CPU, memory and disk usage of the wrapped sampler with the
+/// totals of the Job Object the process was assigned to.
+pub(crate) struct JobObjectSampler {
+    sampler: Box<dyn ProcessSampler>,
+    job: HANDLE,
+    sys: Arc<Mutex<System>>,
+    last_cpu_time: Option<(f64, Instant)>,
+}
+
+impl JobObjectSampler {
+    /// Assign the process of `sampler` to a new Job Object. Nested jobs
+    /// need Windows 8 or later.
+    pub(crate) fn new(sampler: Box<dyn ProcessSampler>, sys: Arc<Mutex<System>>) -> Result<Self> {
+        let pid = sampler.pid();
+        // SAFETY: both pointers may be null for an unnamed job with the
+        // default security.
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if job == 0 {
+            return Err(Error::JobObject(std::io::Error::last_os_error()));
+        }
+        let mut sampler = Self {
+            sampler,
+            job,
+            sys,
+            last_cpu_time: None,
+        };
+        // SAFETY: the handle is checked and closed below.
+        let process = unsafe { OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid) };
+        if process == 0 {
+            return Err(Error::JobObject(std::io::Error::last_os_error()));
+        }
+        // SAFETY: both handles are valid.
+        let assigned = unsafe { AssignProcessToJobObject(job, process) };
+        let err = std::io::Error::last_os_error();
+        // SAFETY: `process` was opened above and is not used afterwards.
+        unsafe { CloseHandle(process) };
+        if assigned == 0 {
+            return Err(Error::JobObject(err));
+        }
+        // As the warm up of the other samplers, for the first sample to
+        // report the usage since the registration.
+        sampler.last_cpu_time = sampler.accounting().map(|info| {
+            let cpu_times = cpu_times(&info);
+            (cpu_times.user + cpu_times.system, Instant::now())
+        });
+        Ok(sampler)
+    }
+
+    fn accounting(&self) -> Option<JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION> {
+        // SAFETY: the structure is plain data, valid when zeroed.
+        let mut info: JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION = unsafe { mem::zeroed() };
+        // SAFETY: `info` is a valid buffer of the given size.
+        let ok = unsafe {
+            QueryInformationJobObject(
+                self.job,
+                JobObjectBasicAndIoAccountingInformation,
+                &mut info as *mut _ as *mut std::ffi::c_void,
+                mem::size_of_val(&info) as u32,
+                std::ptr::null_mut(),
+            )
+        };
+        (ok != 0).then_some(info)
+    }
+
+    /// The processes in the job, up to `MAX_PROCESSES`.
+    fn process_ids(&self) -> Vec<u32> {
+        let header = mem::offset_of!(JOBOBJECT_BASIC_PROCESS_ID_LIST, ProcessIdList);
+        let header_len = header / mem::size_of::<usize>();
+        let mut buffer = vec![0usize; header_len + MAX_PROCESSES];
+        // SAFETY: `buffer` is a valid buffer of the given size, aligned for
+        // the list.
+        let ok = unsafe {
+            QueryInformationJobObject(
+                self.job,
+                JobObjectBasicProcessIdList,
+                buffer.as_mut_ptr().cast(),
+                mem::size_of_val(buffer.as_slice()) as u32,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Vec::new();
+        }
+        // SAFETY: the buffer starts with the list.
+        let listed = unsafe {
+            (*buffer.as_ptr().cast::<JOBOBJECT_BASIC_PROCESS_ID_LIST>()).NumberOfProcessIdsInList
+        };
+        buffer[header_len..]
+            .iter()
+            .take(listed as usize)
+            .map(|&pid| pid as u32)
+            .collect()
+    }
+}
+
+/// Times of the processes of the job, including the ones which already
+/// exited.
+fn cpu_times(info: &JOBOBJECT_BASIC_AND_IO_ACCOUNTING_INFORMATION) -> CpuTimes {
+    // In 100 nanosecond intervals.
+    CpuTimes {
+        user: info.BasicInfo.TotalUserTime as f64 / 1e7,
+        system: info.BasicInfo.TotalKernelTime as f64 / 1e7,
+    }
+}
+
+impl Drop for JobObjectSampler {
+    fn drop(&mut self) {
+        // SAFETY: the job was created by `new`. Closing it does not terminate
+        // the processes in it.
+        unsafe { CloseHandle(self.job) };
+    }
+}
+
+impl ProcessSampler for JobObjectSampler {
+    fn pid(&self) -> u32 {
+        self.sampler.pid()
+    }
+
+    fn info(&mut self) -> Option<ProcessInfo> {
+        self.sampler.info()
+    }
+
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+        let mut snapshot = self.sampler.sample()?;
+        let Some(info) = self.accounting() else {
+            return Some(snapshot);
+        };
+
+        let cpu_times = cpu_times(&info);
+        let now = Instant::now();
+        let total = cpu_times.user + cpu_times.system;
+        snapshot.cpu_usage = match self.last_cpu_time.replace((total, now)) {
+            Some((last_total, last_time)) => {
+                let elapsed = now.duration_since(last_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    ((total - last_total).max(0.0) / elapsed * 100.0) as f32
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        snapshot.cpu_times = Some(cpu_times);
+        snapshot.disk_read = info.IoInfo.ReadTransferCount;
+        snapshot.disk_written = info.IoInfo.WriteTransferCount;
+
+        let mut sys = self.sys.lock().unwrap();
+        let (mut memory, mut virtual_memory) = (0, 0);
+        for pid in self.process_ids() {
+            let pid = Pid::from_u32(pid);
+            if sys.refresh_process_specifics(pid, ProcessRefreshKind::new()) {
+                if let Some(process) = sys.process(pid) {
+                    memory += process.memory();
+                    virtual_memory += process.virtual_memory();
+                }
+            }
+        }
+        snapshot.memory = memory;
+        snapshot.virtual_memory = virtual_memory;
+        Some(snapshot)
+    }
+
+    fn set_metric_groups(&mut self, metric_groups: MetricGroups) {
+        self.sampler.set_metric_groups(metric_groups);
+    }
+
+    fn sampled_groups(&self) -> MetricGroups {
+        self.sampler.sampled_groups()
+    }
+}
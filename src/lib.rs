@@ -1,10 +1,21 @@
 //! This is my awesome crate Enabling system metrics from process to be observed using opentelemetry.
 //! Current metrics observed are:
-//! - CPU
+//! - CPU, including the cumulative `process.cpu.time` in seconds
 //! - Memory
 //! - Disk
 //! - Network
+//! - GPU memory and utilization (NVIDIA, AMD with the `amd-gpu` feature,
+//!   Intel utilization only with the `intel-gpu` feature, Apple Silicon
+//!   memory only with the `apple-gpu` feature, Jetson memory only)
 //!
+//! The CPU, memory and disk instruments can follow the OpenTelemetry
+//! semantic conventions instead, see [`ProcessMetricsConfig::semconv`].
+//!
+//! Host level metrics can be observed separately with [`init_host_observer`]:
+//! - Load average
+//! - Swap
+//! - GPU utilization, power, temperature, clocks and throttle reasons (NVIDIA)
+//! - Battery (with the `battery` feature)
 //!
 //! # Getting started
 //!
@@ -16,44 +27,215 @@
 //! init_process_observer(meter);
 //! ```
 //!
+//! # Sampling
+//!
+//! [`init_process_observer`] and [`ProcessObserverBuilder::init`] register
+//! observable instruments whose callback samples the process, so every
+//! collection of the reader records a fresh sample and the export interval
+//! of the meter provider is the only interval to configure.
+//!
+//! [`init_process_observer_blocking`] and [`ProcessObserverBuilder::spawn`]
+//! sample the process every [`ProcessMetricsConfig::interval`] on a
+//! dedicated thread instead, and collections record the latest sample. With
+//! the `async` or `tokio` feature, `ProcessObserverBuilder::run` does the
+//! same from a future. The first sample is taken right after registration,
+//! once the CPU usage can be measured (200 ms on Linux), so that short lived
+//! processes are recorded too.
+//!
 
-use eyre::Context;
-use eyre::ContextCompat;
-use eyre::Result;
-use nvml_wrapper::enums::device::UsedGpuMemory;
-use nvml_wrapper::Nvml;
-use opentelemetry::metrics::Unit;
+use std::borrow::Cow;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use sysinfo::PidExt;
 
 use sysinfo::ProcessExt;
 use sysinfo::SystemExt;
-use sysinfo::{get_current_pid, System};
+use sysinfo::{get_current_pid, ProcessRefreshKind, System};
 
 use opentelemetry::metrics::Meter;
-use opentelemetry::Key;
+use opentelemetry::{Key, KeyValue};
+
+use derived::Derived;
+use instruments::{CountingObserver, ProcessRecorder};
+use sampler::{GpuSamplers, SysinfoSampler, WithCallbacks};
+
+#[cfg(feature = "alerts")]
+mod alerts;
+#[cfg(feature = "battery")]
+mod battery;
+#[cfg(target_os = "linux")]
+mod cgroup;
+#[cfg(feature = "tokio-process")]
+mod command;
+mod config;
+mod cpu;
+#[cfg(target_os = "macos")]
+mod darwin;
+mod derived;
+#[cfg(all(feature = "docker", target_os = "linux"))]
+mod docker;
+mod env;
+mod error;
+#[cfg(feature = "exit-events")]
+mod exit;
+#[cfg(any(feature = "otlp", feature = "stdout", feature = "prometheus"))]
+mod exporter;
+#[cfg(feature = "metrics")]
+mod facade;
+#[cfg(target_os = "freebsd")]
+mod freebsd;
+mod gpu;
+mod group;
+mod host;
+mod instruments;
+mod interpreter;
+#[cfg(windows)]
+mod job;
+#[cfg(target_os = "linux")]
+mod linux;
+mod network;
+mod periodic;
+mod priority;
+mod provider;
+mod registry;
+#[cfg(feature = "tokio-metrics")]
+mod runtime;
+mod sampler;
+#[cfg(feature = "sdk")]
+mod sdk;
+mod semconv;
+#[cfg(all(feature = "taskstats", target_os = "linux"))]
+mod taskstats;
+pub mod testing;
+mod unchanged;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(feature = "alerts")]
+pub use alerts::AlertRule;
+#[cfg(target_os = "linux")]
+pub use cgroup::{cgroup_pids, systemd_unit_cgroup};
+#[cfg(feature = "tokio-process")]
+pub use command::{ObservedChild, ObservedCommand};
+pub use config::{
+    CommandAttribute, CpuNormalization, GpuDevices, GroupIntervals, IdentityAttributes,
+    MetricGroups, OnExit, OnOverflow, ProcessMetricsConfig,
+};
+pub use cpu::CpuTimes;
+pub use error::{Error, Result};
+#[cfg(feature = "otlp")]
+pub use exporter::init_with_otlp;
+#[cfg(feature = "prometheus")]
+pub use exporter::init_with_prometheus;
+#[cfg(feature = "stdout")]
+pub use exporter::init_with_stdout;
+#[cfg(any(feature = "otlp", feature = "stdout"))]
+pub use exporter::ExporterGuard;
+pub use gpu::{GpuDeviceSample, GpuProcessSample, GpuSampler};
+pub use host::init_host_observer;
+pub use provider::MeterProviderExt;
+pub use registry::{ObserverHandle, SamplerRegistry};
+pub use sampler::{
+    ConnectionCount, DelayTimes, MemoryDetails, NumaNodeMemory, PressureStall, ProcessInfo,
+    ProcessMetricsSnapshot, ProcessSampler, ThreadCpuTimes,
+};
+#[cfg(feature = "sdk")]
+pub use sdk::{process_resource, recommended_views, HostResourceDetector};
+
+// Name of the meters created by the crate from a provider.
+const METER_NAME: &str = "opentelemetry-system-metrics";
 
 const PROCESS_PID: Key = Key::from_static_str("process.pid");
 const PROCESS_EXECUTABLE_NAME: Key = Key::from_static_str("process.executable.name");
 const PROCESS_EXECUTABLE_PATH: Key = Key::from_static_str("process.executable.path");
 const PROCESS_COMMAND: Key = Key::from_static_str("process.command");
+const PROCESS_OWNER: Key = Key::from_static_str("process.owner");
+const PROCESS_USER_ID: Key = Key::from_static_str("process.user.id");
+const PROCESS_GROUP_ID: Key = Key::from_static_str("process.group.id");
+const PROCESS_RUNTIME_NAME: Key = Key::from_static_str("process.runtime.name");
+const PROCESS_RUNTIME_VERSION: Key = Key::from_static_str("process.runtime.version");
+#[cfg(feature = "tokio-process")]
+const PROCESS_ROLE: Key = Key::from_static_str("process.role");
+#[cfg(target_os = "linux")]
+const SYSTEMD_UNIT: Key = Key::from_static_str("systemd.unit");
+#[cfg(all(feature = "docker", target_os = "linux"))]
+const CONTAINER_NAME: Key = Key::from_static_str("container.name");
+#[cfg(all(feature = "docker", target_os = "linux"))]
+const CONTAINER_ID: Key = Key::from_static_str("container.id");
 
 // Not implemented yet!
 //
 // const PROCESS_COMMAND_LINE: Key = Key::from_static_str("process.command_line");
 // const PROCESS_COMMAND_ARGS: Key = Key::from_static_str("process.command_args");
-// const PROCESS_OWNER: Key = Key::from_static_str("process.owner");
 
 const PROCESS_CPU_USAGE: &str = "process.cpu.usage";
 const PROCESS_CPU_UTILIZATION: &str = "process.cpu.utilization";
+const PROCESS_CPU_TIME: &str = "process.cpu.time";
+const PROCESS_CPU_ALLOWED: &str = "process.cpu.allowed";
+const PROCESS_NICE: &str = "process.nice";
+const PROCESS_PRIORITY_CLASS: &str = "process.priority_class";
+const PRIORITY_CLASS: Key = Key::from_static_str("priority.class");
+const PROCESS_CPU_USAGE_AGGREGATE: &str = "process.cpu.usage.aggregate";
+const PROCESS_THREAD_CPU_TIME: &str = "process.thread.cpu.time";
+const THREAD_ID: Key =
+    Key::from_static_str(opentelemetry_semantic_conventions::resource::THREAD_ID);
+const THREAD_NAME: Key =
+    Key::from_static_str(opentelemetry_semantic_conventions::resource::THREAD_NAME);
+const PROCESS_DELAY_TIME: &str = "process.delay.time";
+const DELAY_RESOURCE: Key = Key::from_static_str("delay.resource");
+const AGGREGATION: Key = Key::from_static_str("aggregation");
+const CPU_STATE: Key =
+    Key::from_static_str(opentelemetry_semantic_conventions::resource::PROCESS_CPU_STATE);
 const PROCESS_MEMORY_USAGE: &str = "process.memory.usage";
 const PROCESS_MEMORY_VIRTUAL: &str = "process.memory.virtual";
+const PROCESS_MEMORY_SWAP: &str = "process.memory.swap";
+const PROCESS_MEMORY_GROWTH_RATE: &str = "process.memory.growth_rate";
+const PROCESS_MEMORY_BREAKDOWN: &str = "process.memory.breakdown";
+const PROCESS_MEMORY_PSS: &str = "process.memory.pss";
+const PROCESS_MEMORY_USS: &str = "process.memory.uss";
+const PROCESS_MEMORY_PEAK: &str = "process.memory.peak";
+const PROCESS_MEMORY_VIRTUAL_PEAK: &str = "process.memory.virtual.peak";
+const PROCESS_MEMORY_HUGEPAGES: &str = "process.memory.hugepages";
+const PROCESS_MEMORY_LOCKED: &str = "process.memory.locked";
+const PROCESS_MEMORY_NUMA: &str = "process.memory.numa";
+const PROCESS_OOM_SCORE: &str = "process.oom.score";
+const PROCESS_OOM_SCORE_ADJ: &str = "process.oom.score_adj";
+const PROCESS_CGROUP_OOM_KILLS: &str = "process.cgroup.oom_kills";
+const PROCESS_HANDLE_COUNT: &str = "process.handle.count";
+const PROCESS_GUI_OBJECTS: &str = "process.gui_objects";
+const GUI_OBJECT_TYPE: Key = Key::from_static_str("gui_object.type");
+const PROCESS_CGROUP_PRESSURE_STALL: &str = "process.cgroup.pressure.stall";
+const PROCESS_CGROUP_PRESSURE_STALL_TIME: &str = "process.cgroup.pressure.stall.time";
+const PRESSURE_RESOURCE: Key = Key::from_static_str("pressure.resource");
+const PRESSURE_KIND: Key = Key::from_static_str("pressure.kind");
+const NUMA_NODE: Key = Key::from_static_str("numa.node");
+const MEMORY_STATE: Key = Key::from_static_str("state");
 const PROCESS_DISK_IO: &str = "process.disk.io";
+const PROCESS_DISK_IO_RATE: &str = "process.disk.io.rate";
+const PROCESS_NETWORK_CONNECTIONS: &str = "process.network.connections";
+const NETWORK_TRANSPORT: Key =
+    Key::from_static_str(opentelemetry_semantic_conventions::resource::NETWORK_TRANSPORT);
+const NETWORK_CONNECTION_STATE: Key = Key::from_static_str("network.connection.state");
+const PROCESS_ALIVE: &str = "process.alive";
+const PROCESS_STATE: Key = Key::from_static_str("process.state");
+const PROCESS_INFO: &str = "process.info";
+const PROCESS_GROUP_CPU_USAGE: &str = "process.group.cpu.usage";
+const PROCESS_GROUP_MEMORY_USAGE: &str = "process.group.memory.usage";
+const PROCESS_GROUP_MEMORY_VIRTUAL: &str = "process.group.memory.virtual";
+const PROCESS_GROUP_COUNT: &str = "process.group.count";
 // const PROCESS_NETWORK_IO: &str = "process.network.io";
 const DIRECTION: Key = Key::from_static_str("direction");
 
 // const PROCESS_GPU_USAGE: &str = "process.gpu.usage";
 const PROCESS_GPU_MEMORY_USAGE: &str = "process.gpu.memory.usage";
+const PROCESS_GPU_UTILIZATION: &str = "process.gpu.utilization";
+const GPU_ENGINE: Key = Key::from_static_str("gpu.engine");
+const GPU_VENDOR: Key = Key::from_static_str("gpu.vendor");
+const GPU_INDEX: Key = Key::from_static_str("gpu.index");
+const GPU_UUID: Key = Key::from_static_str("gpu.uuid");
+const GPU_MIG_INSTANCE: Key = Key::from_static_str("gpu.mig.instance");
 
 /// Record asynchronnously information about the current process.
 /// # Example
@@ -67,9 +249,15 @@ const PROCESS_GPU_MEMORY_USAGE: &str = "process.gpu.memory.usage";
 /// ```
 ///
 pub fn init_process_observer(meter: Meter) -> Result<()> {
-    let pid =
-        get_current_pid().map_err(|err| eyre::eyre!("could not get current pid. Error: {err}"))?;
-    register_metrics(meter, pid)
+    ProcessObserverBuilder::new(meter).init()
+}
+
+/// `name` after `prefix`, see [`ProcessMetricsConfig::metric_prefix`].
+fn prefixed(prefix: &str, name: &'static str) -> Cow<'static, str> {
+    match prefix {
+        "" => Cow::Borrowed(name),
+        prefix => Cow::Owned(format!("{prefix}{name}")),
+    }
 }
 
 /// Record asynchronously information about a specific process by its PID.
@@ -85,162 +273,1046 @@ pub fn init_process_observer(meter: Meter) -> Result<()> {
 /// ```
 ///
 pub fn init_process_observer_for_pid(meter: Meter, pid: u32) -> Result<()> {
+    ProcessObserverBuilder::new(meter).with_pid(pid).init()
+}
+
+/// Record asynchronously the processes of a systemd unit as one: the CPU
+/// time and disk I/O of its cgroup, including the workers which already
+/// exited, and the memory of the processes currently in it. The metrics are
+/// recorded with the attributes of the main process of the unit, or the
+/// oldest one, and a `systemd.unit` attribute. Requires cgroup v2.
+///
+/// Fails with [`Error::SystemdUnit`] if the unit is not running. Does
+/// nothing, without resolving the unit, when `OTEL_SDK_DISABLED` is `true`.
+/// # Example
+///
+/// ```no_run
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::init_process_observer_for_systemd_unit;
+///
+/// let meter = global::meter("process-meter");
+/// init_process_observer_for_systemd_unit(meter, "nginx.service").unwrap();
+/// ```
+///
+#[cfg(target_os = "linux")]
+pub fn init_process_observer_for_systemd_unit(meter: Meter, unit: &str) -> Result<()> {
+    // Before asking systemd for the unit.
+    if env::sdk_disabled() {
+        tracing::debug!("Process metrics disabled, not registering the observer");
+        return Ok(());
+    }
+    let (dir, main_pid) = cgroup::systemd_unit(unit)?;
+    let mut builder = ProcessObserverBuilder::new(meter)
+        .with_pid(main_pid)
+        .with_attributes([SYSTEMD_UNIT.string(unit.to_string())]);
+    builder.cgroup = Some(dir);
+    builder.init()
+}
+
+/// Record asynchronously the processes of a running Docker container as
+/// one, by its ID or name, as [`init_process_observer_for_systemd_unit`]
+/// does for systemd units. The metrics have `container.name` and
+/// `container.id` attributes. The container is resolved from the Docker
+/// socket, `/var/run/docker.sock` or the `unix://` address of `DOCKER_HOST`,
+/// and has to stay on the same host. Requires the `docker` feature and
+/// cgroup v2.
+///
+/// Fails with [`Error::Container`] if the container is not running. Does
+/// nothing, without resolving the container, when `OTEL_SDK_DISABLED` is
+/// `true`.
+/// # Example
+///
+/// ```no_run
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::init_process_observer_for_container;
+///
+/// let meter = global::meter("process-meter");
+/// init_process_observer_for_container(meter, "postgres").unwrap();
+/// ```
+///
+#[cfg(all(feature = "docker", target_os = "linux"))]
+pub fn init_process_observer_for_container(meter: Meter, container: &str) -> Result<()> {
+    // Before asking Docker for the container.
+    if env::sdk_disabled() {
+        tracing::debug!("Process metrics disabled, not registering the observer");
+        return Ok(());
+    }
+    let container = docker::inspect(container)?;
+    let mut builder = ProcessObserverBuilder::new(meter)
+        .with_pid(container.pid)
+        .with_attributes([
+            CONTAINER_NAME.string(container.name),
+            CONTAINER_ID.string(container.id),
+        ]);
+    builder.cgroup = Some(container.cgroup);
+    builder.init()
+}
+
+/// Record information about the current process from a dedicated thread,
+/// sampling it every 30 seconds. Suited to programs without an async runtime.
+///
+/// Observers share a single sampling thread, see [`SamplerRegistry`].
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::init_process_observer_blocking;
+///
+/// let meter = global::meter("process-meter");
+/// let handle = init_process_observer_blocking(meter);
+/// ```
+///
+pub fn init_process_observer_blocking(meter: Meter) -> Result<ObserverHandle> {
+    ProcessObserverBuilder::new(meter).spawn()
+}
+
+/// Run `program` with `args`, observe it every second until it exits, and
+/// return its exit status with its last sample. Blocks until the command
+/// exits. Suited to benchmarking harnesses measuring the resource usage of
+/// a subprocess.
+///
+/// Fails with [`Error::Command`] if the command could not be spawned or
+/// awaited. A command which is not observed, because it exited before the
+/// first sample or the observer could not be registered, has no snapshot.
+/// # Example
+///
+/// ```no_run
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::init_process_observer_for_command;
+///
+/// let meter = global::meter("process-meter");
+/// let run = init_process_observer_for_command(meter, "sleep", ["1"]).unwrap();
+/// assert!(run.status.success());
+/// if let Some(snapshot) = run.snapshot {
+///     println!("{} bytes in use", snapshot.memory);
+/// }
+/// ```
+///
+pub fn init_process_observer_for_command(
+    meter: Meter,
+    program: impl AsRef<std::ffi::OsStr>,
+    args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+) -> Result<CommandRun> {
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .spawn()
+        .map_err(Error::Command)?;
+    let latest = std::sync::Arc::new(Mutex::new(None));
+    let observer = {
+        let latest = latest.clone();
+        ProcessObserverBuilder::new(meter)
+            .with_pid(child.id())
+            .with_interval(Duration::from_secs(1))
+            .on_sample(move |snapshot| *latest.lock().unwrap() = Some(snapshot.clone()))
+            .spawn()
+    };
+    if let Err(err) = &observer {
+        tracing::warn!("Could not observe the command {}: {err}", child.id());
+    }
+    let status = child.wait().map_err(Error::Command)?;
+    drop(observer);
+    let snapshot = latest.lock().unwrap().take();
+    Ok(CommandRun { status, snapshot })
+}
+
+/// A command run by [`init_process_observer_for_command`].
+#[derive(Debug, Clone)]
+pub struct CommandRun {
+    /// How the command exited.
+    pub status: std::process::ExitStatus,
+    /// The last sample of the command, taken at most a second before it
+    /// exited. `None` if it was never sampled.
+    pub snapshot: Option<ProcessMetricsSnapshot>,
+}
+
+/// Sample the process `pid` once, without OpenTelemetry, for instance to log
+/// the values or serve them from another endpoint. With the `serde` feature,
+/// the snapshot can be serialized.
+///
+/// Blocks for the time needed to measure the CPU usage, 200 ms on Linux.
+/// Observers are better suited to sample periodically.
+/// # Example
+///
+/// ```
+/// use opentelemetry_system_metrics::sample_process_metrics;
+///
+/// let snapshot = sample_process_metrics(std::process::id()).unwrap();
+/// println!("{} bytes in use", snapshot.memory);
+/// ```
+///
+pub fn sample_process_metrics(pid: u32) -> Result<ProcessMetricsSnapshot> {
+    standalone_sampler(pid)?
+        .sample()
+        .ok_or(Error::PidNotFound(pid))
+}
+
+/// Sample the process `pid` right away, then every `interval` until it
+/// exits, without OpenTelemetry. Requires the `async` or `tokio` feature.
+///
+/// Fails with [`Error::PidNotFound`] if the process does not exist.
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use futures::StreamExt;
+/// use opentelemetry_system_metrics::process_metrics_stream;
+///
+/// # async fn run() -> opentelemetry_system_metrics::Result<()> {
+/// let stream = process_metrics_stream(std::process::id(), Duration::from_secs(5))?;
+/// let mut stream = std::pin::pin!(stream);
+/// while let Some(snapshot) = stream.next().await {
+///     println!("{} bytes in use", snapshot.memory);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+#[cfg(any(feature = "async", feature = "tokio"))]
+pub fn process_metrics_stream(
+    pid: u32,
+    interval: Duration,
+) -> Result<impl futures_util::Stream<Item = ProcessMetricsSnapshot>> {
+    let sampler = Box::new(standalone_sampler(pid)?);
+    let memory_growth_window = ProcessMetricsConfig::default().memory_growth_window;
+    Ok(periodic::stream(
+        Box::new(Derived::new(sampler, memory_growth_window)),
+        interval,
+    ))
+}
+
+/// A sampler of `pid` with the default configuration, outside of any
+/// observer.
+fn standalone_sampler(pid: u32) -> Result<SysinfoSampler> {
+    let system = SamplerRegistry::global().system().clone();
     let pid = sysinfo::Pid::from_u32(pid);
-    register_metrics(meter, pid)
-}
-
-fn register_metrics(meter: Meter, pid: sysinfo::Pid) -> Result<()> {
-    let sys_ = System::new_all();
-    let core_count = sys_
-        .physical_core_count()
-        .with_context(|| "Could not get physical core count")?;
-
-    let nvml = Nvml::init();
-
-    let process_cpu_utilization = meter
-        .f64_observable_gauge(PROCESS_CPU_USAGE)
-        .with_description("The percentage of CPU in use.")
-        .init();
-    let process_cpu_usage = meter
-        .f64_observable_gauge(PROCESS_CPU_UTILIZATION)
-        .with_description("The amount of CPU in use.")
-        .init();
-    let process_memory_usage = meter
-        .i64_observable_gauge(PROCESS_MEMORY_USAGE)
-        .with_description("The amount of physical memory in use.")
-        .with_unit(Unit::new("byte"))
-        .init();
-    let process_memory_virtual = meter
-        .i64_observable_gauge(PROCESS_MEMORY_VIRTUAL)
-        .with_description("The amount of committed virtual memory.")
-        .with_unit(Unit::new("byte"))
-        .init();
-    let process_disk_io = meter
-        .i64_observable_gauge(PROCESS_DISK_IO)
-        .with_description("Disk bytes transferred.")
-        .with_unit(Unit::new("byte"))
-        .init();
-
-    let process_gpu_memory_usage = meter
-        .u64_observable_gauge(PROCESS_GPU_MEMORY_USAGE)
-        .with_description("The amount of physical GPU memory in use.")
-        .with_unit(Unit::new("byte"))
-        .init();
-
-    meter
-        .register_callback(
-            &[
-                process_cpu_utilization.as_any(),
-                process_cpu_usage.as_any(),
-                process_memory_usage.as_any(),
-                process_memory_virtual.as_any(),
-                process_disk_io.as_any(),
-                process_gpu_memory_usage.as_any(),
-            ],
-            move |context| {
-                let mut sys = System::new_all();
-                sys.refresh_processes();
-
-                let common_attributes = if let Some(process) = sys.process(pid) {
-                    [
-                        PROCESS_PID.i64(pid.as_u32().into()),
-                        PROCESS_EXECUTABLE_NAME.string(process.name().to_string()),
-                        PROCESS_EXECUTABLE_PATH.string(process.exe().to_str().unwrap().to_string()),
-                        PROCESS_COMMAND.string(process.cmd().join(" ").to_string()),
-                    ]
-                } else {
-                    unimplemented!()
-                };
+    wait_for_process(&system, pid, None)?;
+    let cuda_env = CudaEnv::of(&system.lock().unwrap(), pid);
+    let gpu_samplers = default_gpu_samplers(&GpuDevices::default(), cuda_env);
+    Ok(SysinfoSampler::new(
+        pid,
+        system,
+        GpuSamplers::Ready(gpu_samplers),
+    ))
+}
 
-                sys.refresh_process(pid);
-
-                if let Some(process) = sys.process(pid) {
-                    let cpu_usage = process.cpu_usage();
-                    let disk_io = process.disk_usage();
-                    // let network_io = process.network_usage();
-
-                    context.observe_f64(&process_cpu_usage, cpu_usage.into(), &[]);
-                    context.observe_f64(
-                        &process_cpu_utilization,
-                        (cpu_usage / core_count as f32).into(),
-                        &common_attributes,
-                    );
-                    context.observe_i64(
-                        &process_memory_usage,
-                        (process.memory()).try_into().unwrap(),
-                        &common_attributes,
-                    );
-                    context.observe_i64(
-                        &process_memory_virtual,
-                        (process.virtual_memory()).try_into().unwrap(),
-                        &common_attributes,
-                    );
-                    context.observe_i64(
-                        &process_disk_io,
-                        disk_io.read_bytes.try_into().unwrap(),
-                        &[common_attributes.as_slice(), &[DIRECTION.string("read")]].concat(),
-                    );
-                    context.observe_i64(
-                        &process_disk_io,
-                        disk_io.written_bytes.try_into().unwrap(),
-                        &[common_attributes.as_slice(), &[DIRECTION.string("write")]].concat(),
-                    );
-
-                    // result.observe(
-                    //     &[common_attributes.as_slice(), &[DIRECTION.string("receive")]].concat(),
-                    //     &[process_network_io
-                    //         .observe(context,(network_io.received_bytes.try_into().unwrap())],
-                    // );
-                    // result.observe(
-                    //     &[
-                    //         common_attributes.as_slice(),
-                    //         &[DIRECTION.string("transmit")],
-                    //     ]
-                    //     .concat(),
-                    //     &[process_network_io
-                    //         .observe(context,(network_io.transmitted_bytes.try_into().unwrap())],
-                    // );
+/// Configure a process observer before registering it.
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{GpuDevices, ProcessObserverBuilder};
+///
+/// let meter = global::meter("process-meter");
+/// ProcessObserverBuilder::new(meter)
+///     .with_gpu_devices(GpuDevices::All)
+///     .init();
+/// ```
+///
+pub struct ProcessObserverBuilder {
+    meter: Meter,
+    pid: Option<u32>,
+    config: ProcessMetricsConfig,
+    // `None` uses the built-in samplers.
+    gpu_samplers: Option<Vec<Box<dyn GpuSampler>>>,
+    // `None` samples the process with sysinfo.
+    sampler: Option<Box<dyn ProcessSampler>>,
+    on_exit: Option<ExitCallback>,
+    on_sample: Vec<SampleCallback>,
+    #[cfg(feature = "alerts")]
+    alerts: Option<alerts::Alerts>,
+    #[cfg(feature = "metrics")]
+    metrics_facade: bool,
+    // `None` uses the global registry.
+    registry: Option<SamplerRegistry>,
+    #[cfg(feature = "tokio-metrics")]
+    tokio_runtime: Option<tokio::runtime::Handle>,
+    #[cfg(windows)]
+    job_object: bool,
+    // The cgroup whose processes are aggregated.
+    #[cfg(target_os = "linux")]
+    cgroup: Option<std::path::PathBuf>,
+    group: Option<KeyValue>,
+}
+
+/// Called with the PID once the observed process exited.
+pub(crate) type ExitCallback = Box<dyn FnOnce(u32) + Send>;
+
+/// Called with every sample of the observed process.
+pub(crate) type SampleCallback = Box<dyn FnMut(&ProcessMetricsSnapshot) + Send>;
+
+impl ProcessObserverBuilder {
+    /// Observe the current process, or the one set in
+    /// `OTEL_PROCESS_METRICS_PID`, with the configuration of
+    /// [`ProcessMetricsConfig::from_env`]. The builder methods override the
+    /// environment.
+    pub fn new(meter: Meter) -> Self {
+        Self {
+            meter,
+            pid: env::pid(),
+            config: ProcessMetricsConfig::from_env(),
+            gpu_samplers: None,
+            sampler: None,
+            on_exit: None,
+            on_sample: Vec::new(),
+            #[cfg(feature = "alerts")]
+            alerts: None,
+            #[cfg(feature = "metrics")]
+            metrics_facade: false,
+            registry: None,
+            #[cfg(feature = "tokio-metrics")]
+            tokio_runtime: None,
+            #[cfg(windows)]
+            job_object: false,
+            #[cfg(target_os = "linux")]
+            cgroup: None,
+            group: None,
+        }
+    }
+
+    /// Observe the process with the given PID instead of the current one.
+    pub fn with_pid(mut self, pid: u32) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Replace the whole configuration, including the settings from the
+    /// environment.
+    pub fn with_config(mut self, config: ProcessMetricsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Select the GPU devices to poll. Defaults to [`GpuDevices::Visible`].
+    pub fn with_gpu_devices(mut self, gpu_devices: GpuDevices) -> Self {
+        self.config.gpu_devices = gpu_devices;
+        self
+    }
+
+    /// Wait up to `timeout` in [`ProcessObserverBuilder::init`] for the
+    /// process to exist, instead of failing right away with
+    /// [`Error::PidNotFound`]. Useful when the observer is registered right
+    /// before the process is spawned. The calling thread is blocked while
+    /// waiting.
+    pub fn with_wait_for_process(mut self, timeout: Duration) -> Self {
+        self.config.wait_for_process = Some(timeout);
+        self
+    }
+
+    /// Share the sysinfo state and the sampling thread of `registry`
+    /// instead of the ones of [`SamplerRegistry::global`].
+    pub fn with_registry(mut self, registry: SamplerRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Time between two samples with [`ProcessObserverBuilder::spawn`] or
+    /// `run`.
+    /// Defaults to 30 seconds.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.config.interval = interval;
+        self
+    }
+
+    /// Also sample the CPU usage every `interval` between two samples, and
+    /// record its minimum, average and maximum since the previous
+    /// collection, see [`ProcessMetricsConfig::cpu_sampling_interval`].
+    pub fn with_cpu_sampling_interval(mut self, interval: Duration) -> Self {
+        self.config.cpu_sampling_interval = Some(interval);
+        self
+    }
+
+    /// Record the CPU time of the `top` busiest threads, see
+    /// [`ProcessMetricsConfig::thread_cpu_top`].
+    pub fn with_thread_cpu_top(mut self, top: usize) -> Self {
+        self.config.thread_cpu_top = Some(top);
+        self
+    }
+
+    /// Record the physical footprint as the memory usage on macOS, see
+    /// [`ProcessMetricsConfig::memory_footprint`].
+    pub fn with_memory_footprint(mut self, memory_footprint: bool) -> Self {
+        self.config.memory_footprint = memory_footprint;
+        self
+    }
+
+    /// Select where the attributes identifying the process are recorded.
+    /// Defaults to [`IdentityAttributes::DataPoints`].
+    pub fn with_identity_attributes(mut self, identity: IdentityAttributes) -> Self {
+        self.config.identity = identity;
+        self
+    }
+
+    /// Select what to do with the values which do not fit in their
+    /// instrument. Defaults to [`OnOverflow::Skip`].
+    pub fn with_overflow_behavior(mut self, on_overflow: OnOverflow) -> Self {
+        self.config.on_overflow = on_overflow;
+        self
+    }
+
+    /// Skip the data points which changed by at most `epsilon` since they
+    /// were last recorded, see [`ProcessMetricsConfig::suppress_unchanged`].
+    pub fn with_suppress_unchanged(mut self, epsilon: f64) -> Self {
+        self.config.suppress_unchanged = Some(epsilon);
+        self
+    }
+
+    /// Prepend `prefix` to the name of every instrument, see
+    /// [`ProcessMetricsConfig::metric_prefix`].
+    pub fn with_metric_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.metric_prefix = prefix.into();
+        self
+    }
+
+    /// Turn the observer into a no-op, see
+    /// [`ProcessMetricsConfig::disabled`].
+    pub fn with_disabled(mut self, disabled: bool) -> Self {
+        self.config.disabled = disabled;
+        self
+    }
+
+    /// Record the CPU utilization of every sample into a histogram instead
+    /// of a gauge, see [`ProcessMetricsConfig::cpu_histogram`].
+    pub fn with_cpu_histogram(mut self, cpu_histogram: bool) -> Self {
+        self.config.cpu_histogram = cpu_histogram;
+        self
+    }
+
+    /// Delay the first sample by a random duration up to `jitter`, see
+    /// [`ProcessMetricsConfig::start_jitter`].
+    pub fn with_start_jitter(mut self, jitter: Duration) -> Self {
+        self.config.start_jitter = jitter;
+        self
+    }
+
+    /// Sample some groups of metrics less often than the process, see
+    /// [`GroupIntervals`].
+    pub fn with_group_intervals(mut self, group_intervals: GroupIntervals) -> Self {
+        self.config.group_intervals = group_intervals;
+        self
+    }
+
+    /// Window of the samples `process.memory.growth_rate` is computed over.
+    /// Defaults to 10 minutes.
+    pub fn with_memory_growth_window(mut self, window: Duration) -> Self {
+        self.config.memory_growth_window = window;
+        self
+    }
+
+    /// Select what happens once the process exited. Defaults to
+    /// [`OnExit::Stop`].
+    pub fn with_exit_behavior(mut self, on_exit: OnExit) -> Self {
+        self.config.on_exit = on_exit;
+        self
+    }
+
+    /// Call `callback` with the PID once the process exited, from the
+    /// collection that noticed it. The callback should not block as it
+    /// delays the export of the metrics.
+    pub fn on_exit(mut self, callback: impl FnOnce(u32) + Send + 'static) -> Self {
+        self.on_exit = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `callback` with every sample of the process, before it is
+    /// recorded, for instance to apply backpressure or log the values. The
+    /// callback runs on the sampling thread, or in the collection with
+    /// [`ProcessObserverBuilder::init`], and should not block. Not called
+    /// while the process is a zombie, see [`ProcessMetricsSnapshot::zombie`].
+    ///
+    /// Can be called several times to add callbacks.
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// let meter = global::meter("process-meter");
+    /// ProcessObserverBuilder::new(meter)
+    ///     .on_sample(|snapshot| {
+    ///         if snapshot.memory > 2 << 30 {
+    ///             eprintln!("Using more than 2 GiB");
+    ///         }
+    ///     })
+    ///     .init();
+    /// ```
+    ///
+    pub fn on_sample(
+        mut self,
+        callback: impl FnMut(&ProcessMetricsSnapshot) + Send + 'static,
+    ) -> Self {
+        self.on_sample.push(Box::new(callback));
+        self
+    }
+
+    /// Emit a log record with `logger` when the samples of the process
+    /// cross the thresholds of `rules`: a warning once an alert fires, and
+    /// an info record once it resolves. Records are `process.alert` events
+    /// with the `alert.name`, `alert.state`, `alert.value`,
+    /// `alert.threshold` and `process.pid` attributes.
+    ///
+    /// Replaces the logger and rules of a previous call. Requires the
+    /// `alerts` feature.
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::global;
+    /// use opentelemetry::logs::NoopLoggerProvider;
+    /// use opentelemetry::logs::LoggerProvider as _;
+    /// use opentelemetry_system_metrics::{AlertRule, ProcessObserverBuilder};
+    ///
+    /// let meter = global::meter("process-meter");
+    /// let logger = NoopLoggerProvider::new().logger("process-alerts");
+    /// ProcessObserverBuilder::new(meter)
+    ///     .with_alerts(
+    ///         logger,
+    ///         [
+    ///             AlertRule::memory_above(2 << 30),
+    ///             AlertRule::cpu_above(90.0).for_samples(3),
+    ///         ],
+    ///     )
+    ///     .init();
+    /// ```
+    ///
+    #[cfg(feature = "alerts")]
+    pub fn with_alerts<L>(mut self, logger: L, rules: impl IntoIterator<Item = AlertRule>) -> Self
+    where
+        L: opentelemetry::logs::Logger + Send + 'static,
+    {
+        self.alerts = Some(alerts::Alerts::new(logger, rules));
+        self
+    }
+
+    /// Publish every sample through the macros of the `metrics` crate as
+    /// well, to the recorder installed there: the `process.cpu.usage`,
+    /// `process.memory.usage`, `process.memory.virtual` and
+    /// `process.memory.swap` gauges, the `process.disk.io` counter by
+    /// `direction` and the `process.gpu.memory.usage` gauge by `gpu.uuid`,
+    /// labelled with `process.pid` and the attributes of the observer. The CPU
+    /// usage is 100 per fully used CPU. Requires the `metrics` feature.
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// let meter = global::meter("process-meter");
+    /// ProcessObserverBuilder::new(meter)
+    ///     .with_metrics_facade()
+    ///     .init();
+    /// ```
+    ///
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics_facade(mut self) -> Self {
+        self.metrics_facade = true;
+        self
+    }
+
+    /// Record the metrics of the tokio runtime of `handle` on the same meter
+    /// and with the same attributes as the process metrics:
+    /// `tokio.runtime.workers`, `tokio.runtime.tasks.alive`,
+    /// `tokio.runtime.global_queue.depth`, and per `tokio.worker`,
+    /// `tokio.runtime.worker.busy.time` and `tokio.runtime.worker.park.count`,
+    /// with `tokio.runtime.worker.busy.ratio` the share of the time the
+    /// workers were busy since the previous collection.
+    ///
+    /// The runtime metrics are recorded until the meter is dropped, even
+    /// after the process exited. Requires the `tokio-metrics` feature.
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let meter = global::meter("process-meter");
+    /// ProcessObserverBuilder::new(meter)
+    ///     .with_tokio_runtime(tokio::runtime::Handle::current())
+    ///     .init();
+    /// # }
+    /// ```
+    ///
+    #[cfg(feature = "tokio-metrics")]
+    pub fn with_tokio_runtime(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.tokio_runtime = Some(handle);
+        self
+    }
+
+    /// Assign the process to a new Job Object and record the CPU, memory and
+    /// disk usage of the whole job: the process and the children it spawns
+    /// afterwards. The CPU times and I/O include the processes of the job
+    /// which already exited. Children spawned before the observer is
+    /// registered are not in the job.
+    ///
+    /// Fails the registration with [`Error::JobObject`] if the process
+    /// cannot be assigned, for instance because it is in a job which forbids
+    /// nesting. Only available on Windows.
+    #[cfg(windows)]
+    pub fn with_job_object(mut self, job_object: bool) -> Self {
+        self.job_object = job_object;
+        self
+    }
+
+    /// Select what the CPU utilization is divided by. Defaults to
+    /// [`CpuNormalization::PhysicalCoresPercent`].
+    pub fn with_cpu_normalization(mut self, cpu_normalization: CpuNormalization) -> Self {
+        self.config.cpu_normalization = cpu_normalization;
+        self
+    }
+
+    /// Follow the OpenTelemetry semantic conventions for the CPU, memory
+    /// and disk instruments, see [`ProcessMetricsConfig::semconv`].
+    pub fn with_semconv(mut self, semconv: bool) -> Self {
+        self.config.semconv = semconv;
+        self
+    }
+
+    /// Record `attributes` on every metric, in addition to the ones
+    /// identifying the process.
+    pub fn with_attributes(mut self, attributes: impl IntoIterator<Item = KeyValue>) -> Self {
+        self.config.attributes.extend(attributes);
+        self
+    }
+
+    /// Label the processes of the observer as a group, such as the workers
+    /// of a pool with `role=worker`. The label is recorded on the metrics of
+    /// every process, and an observer created with
+    /// [`ProcessObserverBuilder::spawn`] also records the totals of its
+    /// processes, including the ones added with [`ObserverHandle::add_pid`]:
+    /// `process.group.cpu.usage`, the sum of the percentages of a single
+    /// CPU, `process.group.memory.usage`, `process.group.memory.virtual`
+    /// and `process.group.count`, with the label and the attributes of
+    /// [`ProcessObserverBuilder::with_attributes`].
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::{global, KeyValue};
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// let meter = global::meter("process-meter");
+    /// let mut workers = ProcessObserverBuilder::new(meter)
+    ///     .with_group(KeyValue::new("role", "worker"))
+    ///     .spawn()
+    ///     .unwrap();
+    /// // workers.add_pid(worker_pid);
+    /// ```
+    ///
+    pub fn with_group(mut self, label: KeyValue) -> Self {
+        self.group = Some(label);
+        self
+    }
+
+    /// Select how the command line is recorded as the `process.command`
+    /// attribute. Defaults to [`CommandAttribute::Full`].
+    pub fn with_command_attribute(mut self, command: CommandAttribute) -> Self {
+        self.config.command = command;
+        self
+    }
+
+    /// Select the groups of metrics to sample and record. Defaults to
+    /// every group.
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::{MetricGroups, ProcessObserverBuilder};
+    ///
+    /// let meter = global::meter("process-meter");
+    /// ProcessObserverBuilder::new(meter)
+    ///     .with_metric_groups(MetricGroups {
+    ///         gpu: false,
+    ///         ..Default::default()
+    ///     })
+    ///     .init();
+    /// ```
+    ///
+    pub fn with_metric_groups(mut self, metric_groups: MetricGroups) -> Self {
+        self.config.metric_groups = metric_groups;
+        self
+    }
+
+    /// Sample GPUs with `sampler` instead of the built-in samplers.
+    ///
+    /// Can be called several times to combine samplers.
+    pub fn with_gpu_sampler(mut self, sampler: impl GpuSampler + 'static) -> Self {
+        self.gpu_samplers
+            .get_or_insert_with(Vec::new)
+            .push(Box::new(sampler));
+        self
+    }
+
+    /// Sample the process with `sampler` instead of sysinfo, for instance
+    /// [`testing::MockSampler`] in tests.
+    ///
+    /// The PID, the GPU devices and samplers and the wait for the process
+    /// are then ignored, `sampler` is responsible for them.
+    pub fn with_sampler(mut self, sampler: impl ProcessSampler + 'static) -> Self {
+        self.sampler = Some(Box::new(sampler));
+        self
+    }
+
+    /// Register the instruments on the meter.
+    ///
+    /// The process is sampled by the callback of the instruments, once per
+    /// collection of the reader.
+    ///
+    /// Fails with [`Error::PidNotFound`] if the process does not exist.
+    pub fn init(self) -> Result<()> {
+        if self.disabled() {
+            tracing::debug!("Process metrics disabled, not registering the observer");
+            return Ok(());
+        }
+        let meter = self.meter.clone();
+        let Prepared {
+            sampler,
+            recorder,
+            on_exit,
+        } = self.prepare()?;
+
+        // `None` once the process exited.
+        let sampler = Mutex::new(Some(sampler));
+        let on_exit = Mutex::new(on_exit);
+        meter.register_callback(&recorder.instruments(), move |observer| {
+            let mut sampler = sampler.lock().unwrap();
+            let Some(active) = sampler.as_mut() else {
+                return;
+            };
+            let span = tracing::debug_span!(
+                "process_observer_sample",
+                pid = active.pid(),
+                duration = tracing::field::Empty,
+                metrics_recorded = tracing::field::Empty
+            )
+            .entered();
+            let start = Instant::now();
+            let sample = active.sample();
+            span.record("duration", tracing::field::debug(start.elapsed()));
+            match sample {
+                Some(snapshot) => {
+                    if !snapshot.zombie {
+                        recorder.record_cpu(snapshot.cpu_usage);
+                    }
+                    let observer = CountingObserver::new(observer);
+                    recorder.record(&observer, &snapshot);
+                    span.record("metrics_recorded", observer.count());
                 }
+                None => {
+                    let pid = active.pid();
+                    tracing::debug!("Process {pid} exited, stopping the observer");
+                    *sampler = None;
+                    recorder.record_exit(observer);
+                    if let Some(on_exit) = on_exit.lock().unwrap().take() {
+                        on_exit(pid);
+                    }
+                }
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Register the instruments on the meter and sample the process every
+    /// [`ProcessMetricsConfig::interval`] on a dedicated thread, until it
+    /// exits or the returned handle is dropped. Collections record the
+    /// latest sample.
+    ///
+    /// No async runtime is needed.
+    /// # Example
+    ///
+    /// ```
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// let meter = global::meter("process-meter");
+    /// let handle = ProcessObserverBuilder::new(meter).spawn().unwrap();
+    /// // ...
+    /// handle.stop();
+    /// ```
+    ///
+    pub fn spawn(self) -> Result<ObserverHandle> {
+        let meter = self.meter.clone();
+        let registry = self
+            .registry
+            .clone()
+            .unwrap_or_else(SamplerRegistry::global);
+        let mut config = self.config.clone();
+        if self.disabled() {
+            tracing::debug!("Process metrics disabled, not spawning the observer");
+            // For `add_pid` to do nothing either.
+            config.disabled = true;
+            return Ok(registry.disabled(meter, config));
+        }
+        // For the processes added later.
+        config.attributes.extend(self.group.clone());
+        let group = self.group.is_some();
+        let prepared = self.prepare()?;
+        registry.spawn(meter, config, prepared, group)
+    }
+
+    /// Same as [`ProcessObserverBuilder::spawn`], sampling from the returned
+    /// future instead of a thread.
+    ///
+    /// Requires the `async` feature, for a future that does not depend on
+    /// any async runtime, or the `tokio` feature, to use the timer of tokio
+    /// and avoid the helper thread of the runtime agnostic one. Dropping
+    /// the future stops recording. With [`ProcessMetricsConfig::disabled`],
+    /// the future never resolves and does nothing.
+    /// # Example
+    ///
+    /// ```no_run
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// # async fn run() -> opentelemetry_system_metrics::Result<()> {
+    /// let meter = global::meter("process-meter");
+    /// ProcessObserverBuilder::new(meter).run().await
+    /// # }
+    /// ```
+    ///
+    #[cfg(any(feature = "async", feature = "tokio"))]
+    pub async fn run(self) -> Result<()> {
+        if self.disabled() {
+            tracing::debug!("Process metrics disabled, not running the observer");
+            return std::future::pending().await;
+        }
+        let meter = self.meter.clone();
+        let config = self.config.clone();
+        let Prepared {
+            sampler,
+            recorder,
+            on_exit,
+        } = self.prepare()?;
+        let recorder = std::sync::Arc::new(recorder);
+        let (latest, registration) = periodic::register(&meter, recorder.clone(), sampler.pid())?;
+        periodic::run(sampler, recorder, latest, registration, &config, on_exit).await;
+        Ok(())
+    }
+
+    /// Whether the observer is a no-op, by its configuration or
+    /// `OTEL_SDK_DISABLED`, even if the configuration was not read from the
+    /// environment.
+    fn disabled(&self) -> bool {
+        self.config.disabled || env::sdk_disabled()
+    }
+
+    /// Resolve the process and create the instruments.
+    fn prepare(self) -> Result<Prepared> {
+        let mut config = self.config;
+        config.attributes.extend(self.group);
+        let registry = self.registry.unwrap_or_else(SamplerRegistry::global);
+        let system = registry.system().clone();
 
-                // let mut last_timestamp = last_timestamp.lock().unwrap().clone();
-                match &nvml {
-                    Ok(nvml) => {
-                        // Get the first `Device` (GPU) in the system
-                        if let Ok(device) = nvml.device_by_index(0) {
-                            if let Ok(gpu_stats) = device.running_compute_processes() {
-                                for stat in gpu_stats.iter() {
-                                    if stat.pid == pid.as_u32() {
-                                        let memory_used = match stat.used_gpu_memory {
-                                            UsedGpuMemory::Used(bytes) => bytes,
-                                            UsedGpuMemory::Unavailable => 0,
-                                        };
-
-                                        context.observe_u64(
-                                            &process_gpu_memory_usage,
-                                            memory_used,
-                                            &common_attributes,
-                                        );
-
-                                        break;
-                                    }
-                                }
-
-                                // If the loop finishes and no pid matched our pid, put 0.
-                                context.observe_u64(
-                                    &process_gpu_memory_usage,
-                                    0,
-                                    &common_attributes,
-                                );
-                            };
-                        }
+        let mut sampler = match self.sampler {
+            Some(sampler) => sampler,
+            None => {
+                let pid = match self.pid {
+                    Some(pid) => sysinfo::Pid::from_u32(pid),
+                    None => {
+                        get_current_pid().map_err(|_| Error::SysinfoUnavailable("current pid"))?
                     }
-                    Err(err) => tracing::info!(
-                        "Could not initiate NVML for observing GPU memory usage. Error: {:?}",
-                        err
-                    ),
+                };
+                wait_for_process(&system, pid, config.wait_for_process)?;
+                let gpu_samplers = match self.gpu_samplers {
+                    Some(gpu_samplers) => GpuSamplers::Pending(Box::new(move || gpu_samplers)),
+                    None => {
+                        // Read now, the process may be gone once the GPU
+                        // group is enabled.
+                        let sys = system.lock().unwrap();
+                        let cuda_env = CudaEnv::of(&sys, pid);
+                        let gpu_devices = config.gpu_devices.clone();
+                        GpuSamplers::Pending(Box::new(move || {
+                            default_gpu_samplers(&gpu_devices, cuda_env)
+                        }))
+                    }
+                };
+                Box::new(
+                    SysinfoSampler::new(pid, system.clone(), gpu_samplers)
+                        .with_group_intervals(config.group_intervals)
+                        .with_thread_cpu_top(config.thread_cpu_top)
+                        .with_memory_footprint(config.memory_footprint),
+                )
+            }
+        };
+
+        #[cfg(windows)]
+        if self.job_object {
+            sampler = Box::new(job::JobObjectSampler::new(sampler, system.clone())?);
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(dir) = self.cgroup {
+            sampler = Box::new(cgroup::CgroupSampler::new(sampler, dir, system.clone()));
+        }
+
+        // Before the callbacks, so they see the derived values.
+        sampler = Box::new(Derived::new(sampler, config.memory_growth_window));
+
+        #[allow(unused_mut)]
+        let mut callbacks = self.on_sample;
+        #[cfg(feature = "alerts")]
+        if let Some(mut alerts) = self.alerts {
+            let pid = sampler.pid();
+            callbacks.push(Box::new(move |snapshot| alerts.check(pid, snapshot)));
+        }
+        #[cfg(feature = "metrics")]
+        if self.metrics_facade {
+            let facade =
+                facade::Facade::new(&config.metric_prefix, sampler.pid(), &config.attributes);
+            callbacks.push(Box::new(move |snapshot| facade.publish(snapshot)));
+        }
+        if !callbacks.is_empty() {
+            sampler = Box::new(WithCallbacks { sampler, callbacks });
+        }
+        sampler.set_metric_groups(config.metric_groups);
+
+        let cpu_count = {
+            let sys = system.lock().unwrap();
+            match config.cpu_normalization {
+                CpuNormalization::PhysicalCoresPercent | CpuNormalization::PhysicalCores => {
+                    Some(sys.physical_core_count().unwrap_or_else(|| {
+                        tracing::debug!("Physical core count unavailable, using the logical CPUs");
+                        sys.cpus().len()
+                    }))
                 }
-            },
-        )
-        .context("could not register traceback")?;
+                CpuNormalization::LogicalCores => Some(sys.cpus().len()),
+                #[cfg(target_os = "linux")]
+                CpuNormalization::AllowedCpus => {
+                    Some(linux::allowed_cpus(sampler.pid()).unwrap_or_else(|| sys.cpus().len()))
+                }
+                #[cfg(not(target_os = "linux"))]
+                CpuNormalization::AllowedCpus => Some(sys.cpus().len()),
+                CpuNormalization::None => None,
+            }
+        };
+
+        // Computed once, they are still needed after the process exited.
+        let pid = sampler.pid();
+        let info = sampler.info().ok_or(Error::PidNotFound(pid))?;
+        let runtime = interpreter::detect(&info);
+        let mut identity = vec![
+            PROCESS_PID.i64(pid.into()),
+            PROCESS_EXECUTABLE_NAME.string(info.name),
+            PROCESS_EXECUTABLE_PATH.string(info.executable_path),
+        ];
+        if let Some(command) = config.command.apply(&info.command) {
+            identity.push(PROCESS_COMMAND.string(command));
+        }
+        if let Some((runtime, version)) = runtime {
+            identity.push(PROCESS_RUNTIME_NAME.string(runtime));
+            identity.extend(version.map(|version| PROCESS_RUNTIME_VERSION.string(version)));
+        }
+        identity.extend(info.owner.map(|owner| PROCESS_OWNER.string(owner)));
+        identity.extend(info.user_id.map(|id| PROCESS_USER_ID.i64(id.into())));
+        identity.extend(info.group_id.map(|id| PROCESS_GROUP_ID.i64(id.into())));
+        let mut attributes = match config.identity {
+            IdentityAttributes::DataPoints => identity.clone(),
+            IdentityAttributes::InfoMetric => vec![PROCESS_PID.i64(pid.into())],
+            IdentityAttributes::Resource => Vec::new(),
+        };
+        attributes.extend(config.attributes.iter().cloned());
+        identity.extend(config.attributes.iter().cloned());
+        let info_attributes =
+            (config.identity == IdentityAttributes::InfoMetric).then_some(identity);
+
+        #[cfg(feature = "tokio-metrics")]
+        if let Some(handle) = self.tokio_runtime {
+            runtime::register_runtime_metrics(
+                &self.meter,
+                handle,
+                attributes.clone(),
+                &config.metric_prefix,
+            )?;
+        }
+
+        let recorder =
+            ProcessRecorder::new(&self.meter, &config, cpu_count, attributes, info_attributes);
+        Ok(Prepared {
+            sampler,
+            recorder,
+            on_exit: self.on_exit,
+        })
+    }
+}
+
+/// A process observer ready to be registered.
+struct Prepared {
+    sampler: Box<dyn ProcessSampler>,
+    recorder: ProcessRecorder,
+    on_exit: Option<ExitCallback>,
+}
+
+/// Wait up to `timeout` for the process to appear, for observers registered
+/// right before spawning it.
+fn wait_for_process(
+    sys: &Mutex<System>,
+    pid: sysinfo::Pid,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    let deadline = Instant::now() + timeout.unwrap_or_default();
+    // The lock is released while sleeping, the system may be shared.
+    while !sys
+        .lock()
+        .unwrap()
+        .refresh_process_specifics(pid, ProcessRefreshKind::new())
+    {
+        if Instant::now() >= deadline {
+            return Err(Error::PidNotFound(pid.as_u32()));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
     Ok(())
 }
+
+/// The GPU samplers enabled by cargo features.
+fn default_gpu_samplers(gpu_devices: &GpuDevices, cuda_env: CudaEnv) -> Vec<Box<dyn GpuSampler>> {
+    #[allow(unused_mut)]
+    let mut samplers: Vec<Box<dyn GpuSampler>> = vec![
+        Box::new(gpu::NvmlSampler::new(gpu_devices.resolve(
+            cuda_env.visible_devices.as_deref(),
+            cuda_env.device_order.as_deref(),
+        ))),
+        #[cfg(all(feature = "amd-gpu", target_os = "linux"))]
+        Box::new(gpu::AmdSampler::default()),
+        #[cfg(all(feature = "apple-gpu", target_os = "macos"))]
+        Box::new(gpu::AppleSampler),
+        #[cfg(all(feature = "intel-gpu", target_os = "linux"))]
+        Box::new(gpu::IntelSampler::default()),
+    ];
+    // NVML is not available on Jetson boards.
+    #[cfg(target_os = "linux")]
+    if let Some(jetson) = gpu::JetsonSampler::detect() {
+        samplers.push(Box::new(jetson));
+    }
+    samplers
+}
+
+/// The CUDA variables selecting the devices, as seen by the observed process.
+struct CudaEnv {
+    visible_devices: Option<String>,
+    device_order: Option<String>,
+}
+
+impl CudaEnv {
+    fn of(sys: &System, pid: sysinfo::Pid) -> Self {
+        // The environment of other processes may not be readable, fall back
+        // on our own which is inherited by children in the common case.
+        let environ = sys
+            .process(pid)
+            .map(|process| process.environ())
+            .filter(|environ| !environ.is_empty());
+        let var = |name: &str| match environ {
+            Some(environ) => environ.iter().find_map(|variable| {
+                variable
+                    .strip_prefix(name)
+                    .and_then(|rest| rest.strip_prefix('='))
+                    .map(str::to_string)
+            }),
+            None => std::env::var(name).ok(),
+        };
+        Self {
+            visible_devices: var("CUDA_VISIBLE_DEVICES"),
+            device_order: var("CUDA_DEVICE_ORDER"),
+        }
+    }
+}
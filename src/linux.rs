@@ -0,0 +1,443 @@
+//! Linux specific readers for information sysinfo does not expose.
+
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+#[cfg(feature = "numa")]
+use crate::NumaNodeMemory;
+use crate::{CpuTimes, MemoryDetails, PressureStall, ThreadCpuTimes};
+
+/// Parsed content of `/proc/<pid>/status`, or of files in the same format.
+pub(crate) struct ProcStatus {
+    fields: HashMap<String, String>,
+}
+
+impl ProcStatus {
+    pub(crate) fn read(pid: u32) -> Option<Self> {
+        Self::read_file(&format!("/proc/{pid}/status"))
+    }
+
+    /// Memory of every mapping of the process summed up. The kernel walks
+    /// the page tables, which is slower than reading the status.
+    #[cfg(feature = "smaps")]
+    pub(crate) fn read_smaps_rollup(pid: u32) -> Option<Self> {
+        Self::read_file(&format!("/proc/{pid}/smaps_rollup"))
+    }
+
+    /// I/O counters of the process, which need the permission to trace it.
+    #[cfg(feature = "procfs")]
+    pub(crate) fn read_io(pid: u32) -> Option<Self> {
+        Self::read_file(&format!("/proc/{pid}/io"))
+    }
+
+    fn read_file(path: &str) -> Option<Self> {
+        Some(Self::parse(&fs::read_to_string(path).ok()?))
+    }
+
+    fn parse(content: &str) -> Self {
+        let fields = content
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+            .collect();
+        Self { fields }
+    }
+
+    /// Value of a memory field such as `VmSwap`, converted from kB to bytes.
+    pub(crate) fn bytes(&self, key: &str) -> Option<u64> {
+        let value = self.fields.get(key)?;
+        let kilobytes = value.trim_end_matches("kB").trim().parse::<u64>().ok()?;
+        Some(kilobytes * 1024)
+    }
+
+    /// Value of a field without unit, such as `read_bytes`.
+    #[cfg(feature = "procfs")]
+    pub(crate) fn value(&self, key: &str) -> Option<u64> {
+        self.fields.get(key)?.parse().ok()
+    }
+
+    pub(crate) fn memory_details(&self) -> MemoryDetails {
+        MemoryDetails {
+            shared: self
+                .bytes("RssFile")
+                .zip(self.bytes("RssShmem"))
+                .map(|(file, shmem)| file + shmem),
+            anonymous: self.bytes("RssAnon"),
+            peak: self.bytes("VmHWM"),
+            virtual_peak: self.bytes("VmPeak"),
+            hugepages: self.bytes("HugetlbPages"),
+            locked: self.bytes("VmLck"),
+            ..Default::default()
+        }
+    }
+
+    /// Proportional and unique set sizes, from `smaps_rollup`.
+    #[cfg(feature = "smaps")]
+    pub(crate) fn pss_uss(&self) -> (Option<u64>, Option<u64>) {
+        let uss = self
+            .bytes("Private_Clean")
+            .zip(self.bytes("Private_Dirty"))
+            .map(|(clean, dirty)| clean + dirty);
+        (self.bytes("Pss"), uss)
+    }
+}
+
+/// Fields of `/proc/<pid>/stat`, the CPU times in clock ticks.
+pub(crate) struct ProcStat {
+    /// `R`, `S`, `D`, `Z` for zombies, and so on.
+    pub(crate) state: char,
+    pub(crate) utime: u64,
+    pub(crate) stime: u64,
+    pub(crate) nice: i32,
+}
+
+impl ProcStat {
+    pub(crate) fn read(pid: u32) -> Option<Self> {
+        Self::read_file(&format!("/proc/{pid}/stat"))
+    }
+
+    fn read_file(path: &str) -> Option<Self> {
+        Self::parse(&fs::read_to_string(path).ok()?)
+    }
+
+    fn parse(content: &str) -> Option<Self> {
+        // The command name may contain spaces and parentheses, the fields
+        // start after the last closing one with the state (field 3).
+        let (_, fields) = content.rsplit_once(')')?;
+        let mut fields = fields.split_whitespace();
+        let state = fields.next()?.chars().next()?;
+        let mut fields = fields.skip(10);
+        let utime = fields.next()?.parse().ok()?;
+        let stime = fields.next()?.parse().ok()?;
+        // After `cutime`, `cstime` and `priority`.
+        let nice = fields.nth(3)?.parse().ok()?;
+        Some(Self {
+            state,
+            utime,
+            stime,
+            nice,
+        })
+    }
+}
+
+/// Memory usage and disk I/O of the process read from `/proc` directly,
+/// instead of the broader refresh of sysinfo. `None` if the process is
+/// gone.
+#[cfg(feature = "procfs")]
+pub(crate) fn sample_procfs(pid: u32, disk: bool) -> Option<crate::ProcessMetricsSnapshot> {
+    // Sizes in pages: total, resident, shared, text, 0, data and stack, 0.
+    let statm = fs::read_to_string(format!("/proc/{pid}/statm")).ok()?;
+    let mut pages = statm.split_whitespace().map(|pages| pages.parse::<u64>());
+    let (Some(Ok(size)), Some(Ok(resident))) = (pages.next(), pages.next()) else {
+        return None;
+    };
+    // SAFETY: sysconf has no preconditions.
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+    let mut snapshot = crate::ProcessMetricsSnapshot {
+        memory: resident * page_size,
+        virtual_memory: size * page_size,
+        ..Default::default()
+    };
+    // Without the permission to trace the process, as with sysinfo.
+    if let (true, Some(io)) = (disk, ProcStatus::read_io(pid)) {
+        snapshot.disk_read = io.value("read_bytes").unwrap_or_default();
+        snapshot.disk_written = io.value("write_bytes").unwrap_or_default();
+    }
+    Some(snapshot)
+}
+
+/// Resident memory per NUMA node, summed over the mappings of
+/// `/proc/<pid>/numa_maps`. Like `smaps_rollup`, reading it walks the page
+/// tables.
+#[cfg(feature = "numa")]
+pub(crate) fn numa_memory(pid: u32) -> Option<Vec<NumaNodeMemory>> {
+    parse_numa_maps(&fs::read_to_string(format!("/proc/{pid}/numa_maps")).ok()?)
+}
+
+#[cfg(feature = "numa")]
+fn parse_numa_maps(content: &str) -> Option<Vec<NumaNodeMemory>> {
+    let mut nodes = std::collections::BTreeMap::<u32, u64>::new();
+    // Lines look like `7f0c1a2b3000 default file=/usr/lib/libc.so.6 mapped=3
+    // N0=2 N1=1 kernelpagesize_kB=4`, with page counts per node.
+    for line in content.lines() {
+        let mut page_size = 4096;
+        let mut pages = Vec::new();
+        for field in line.split_whitespace() {
+            if let Some(kilobytes) = field.strip_prefix("kernelpagesize_kB=") {
+                page_size = kilobytes.parse::<u64>().ok()? * 1024;
+            } else if let Some((node, count)) = field
+                .strip_prefix('N')
+                .and_then(|field| field.split_once('='))
+            {
+                if let (Ok(node), Ok(count)) = (node.parse::<u32>(), count.parse::<u64>()) {
+                    pages.push((node, count));
+                }
+            }
+        }
+        for (node, count) in pages {
+            *nodes.entry(node).or_default() += count * page_size;
+        }
+    }
+    Some(
+        nodes
+            .into_iter()
+            .map(|(node, bytes)| NumaNodeMemory { node, bytes })
+            .collect(),
+    )
+}
+
+/// A file of `/proc/<pid>` holding a single number.
+pub(crate) fn read_value<T: std::str::FromStr>(pid: u32, name: &str) -> Option<T> {
+    fs::read_to_string(format!("/proc/{pid}/{name}"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Directory of the cgroup v2 of the process.
+pub(crate) fn cgroup_dir(pid: u32) -> Option<String> {
+    let cgroups = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    // The unified hierarchy is the only one with the ID 0.
+    let path = cgroups.lines().find_map(|line| line.strip_prefix("0::"))?;
+    Some(format!("/sys/fs/cgroup{path}"))
+}
+
+/// The `oom_kill` count of `memory.events` of the cgroup v2 of the process.
+pub(crate) fn cgroup_oom_kills(pid: u32) -> Option<u64> {
+    let events = fs::read_to_string(format!("{}/memory.events", cgroup_dir(pid)?)).ok()?;
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Pressure stall information of the host, from `/proc/pressure`.
+pub(crate) fn host_pressure() -> Vec<PressureStall> {
+    read_pressure(|resource| format!("/proc/pressure/{resource}"))
+}
+
+/// Pressure stall information of the cgroup v2 of the process.
+pub(crate) fn cgroup_pressure(pid: u32) -> Vec<PressureStall> {
+    let Some(dir) = cgroup_dir(pid) else {
+        return Vec::new();
+    };
+    read_pressure(|resource| format!("{dir}/{resource}.pressure"))
+}
+
+/// Stalls of every resource, skipping the unavailable ones. Files look like
+/// `some avg10=0.12 avg60=0.05 avg300=0.01 total=123456`, with a `full`
+/// line in the same format, the total in microseconds.
+fn read_pressure(path: impl Fn(&str) -> String) -> Vec<PressureStall> {
+    let mut stalls = Vec::new();
+    for resource in ["cpu", "memory", "io"] {
+        if let Ok(content) = fs::read_to_string(path(resource)) {
+            stalls.extend(parse_pressure(resource, &content));
+        }
+    }
+    stalls
+}
+
+/// The stalls of a pressure file of `resource`, skipping the malformed lines.
+fn parse_pressure(resource: &'static str, content: &str) -> Vec<PressureStall> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let kind = match fields.next()? {
+                "some" => "some",
+                "full" => "full",
+                _ => return None,
+            };
+            let mut avg10 = None;
+            let mut total = None;
+            for field in fields {
+                if let Some(value) = field.strip_prefix("avg10=") {
+                    avg10 = value.parse().ok();
+                } else if let Some(value) = field.strip_prefix("total=") {
+                    total = value.parse::<u64>().ok();
+                }
+            }
+            Some(PressureStall {
+                resource,
+                kind,
+                avg10: avg10?,
+                total: Duration::from_micros(total?),
+            })
+        })
+        .collect()
+}
+
+/// CPU times of every thread of the process, in `/proc/<pid>/task`.
+pub(crate) fn thread_cpu_times(pid: u32) -> Vec<ThreadCpuTimes> {
+    let Ok(tasks) = fs::read_dir(format!("/proc/{pid}/task")) else {
+        return Vec::new();
+    };
+    let ticks = clock_ticks_per_second() as f64;
+    tasks
+        .flatten()
+        .filter_map(|task| {
+            let id = task.file_name().to_str()?.parse().ok()?;
+            let path = task.path();
+            // Threads may exit while being read.
+            let stat = ProcStat::read_file(&path.join("stat").to_string_lossy())?;
+            let name = fs::read_to_string(path.join("comm")).ok()?;
+            Some(ThreadCpuTimes {
+                id,
+                name: name.trim_end().to_string(),
+                cpu_times: CpuTimes {
+                    user: stat.utime as f64 / ticks,
+                    system: stat.stime as f64 / ticks,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Number of CPUs in the affinity mask of the main thread of the process,
+/// set with `taskset` or cpusets for instance.
+pub(crate) fn allowed_cpus(pid: u32) -> Option<usize> {
+    // SAFETY: `cpu_set_t` is valid when zeroed, and `set` is valid for the
+    // duration of the calls.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(
+            pid as libc::pid_t,
+            std::mem::size_of::<libc::cpu_set_t>(),
+            &mut set,
+        ) != 0
+        {
+            return None;
+        }
+        Some(libc::CPU_COUNT(&set) as usize)
+    }
+}
+
+/// Number of clock ticks per second used by `/proc/<pid>/stat`.
+pub(crate) fn clock_ticks_per_second() -> u64 {
+    // SAFETY: sysconf has no preconditions.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        100
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_stat() {
+        let cases = [
+            (
+                "1234 (cat) S 1 1234 1234 0 -1 4194560 100 0 0 0 250 75 0 0 20 5 1 0",
+                Some(('S', 250, 75, 5)),
+            ),
+            // The command name may contain spaces and parentheses.
+            (
+                "1234 (my (odd) cmd) Z 1 1234 1234 0 -1 4194560 100 0 0 0 1 2 0 0 39 -19 1 0",
+                Some(('Z', 1, 2, -19)),
+            ),
+            (
+                "1234 (cat) S 1 1234 1234 0 -1 4194560 100 0 0 0 250 75",
+                None,
+            ),
+            (
+                "1234 (cat) S 1 1234 1234 0 -1 4194560 100 0 0 0 x 75 0 0 20 5",
+                None,
+            ),
+            ("", None),
+        ];
+        for (content, expected) in cases {
+            let stat = ProcStat::parse(content)
+                .map(|stat| (stat.state, stat.utime, stat.stime, stat.nice));
+            assert_eq!(stat, expected, "{content:?}");
+        }
+    }
+
+    #[test]
+    fn proc_status() {
+        let status = ProcStatus::parse(
+            "Name:\tcat\nVmPeak:\t    8192 kB\nVmHWM:\t    2048 kB\nRssAnon:\t     1024 kB\n\
+             RssFile:\t     512 kB\nRssShmem:\t       0 kB\nVmSwap:\t     128 kB\nVmLck:\tlots kB\n",
+        );
+        let cases = [
+            ("VmSwap", Some(128 * 1024)),
+            ("RssAnon", Some(1024 * 1024)),
+            ("VmLck", None),
+            ("Name", None),
+            ("HugetlbPages", None),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(status.bytes(key), expected, "{key}");
+        }
+        let details = status.memory_details();
+        assert_eq!(details.shared, Some(512 * 1024));
+        assert_eq!(details.anonymous, Some(1024 * 1024));
+        assert_eq!(details.peak, Some(2048 * 1024));
+        assert_eq!(details.virtual_peak, Some(8192 * 1024));
+        assert_eq!(details.locked, None);
+    }
+
+    #[test]
+    fn pressure() {
+        let cases = [
+            (
+                "some avg10=0.12 avg60=0.05 avg300=0.01 total=123456\n\
+                 full avg10=1.50 avg60=0.00 avg300=0.00 total=42\n",
+                vec![("some", 0.12, 123_456), ("full", 1.5, 42)],
+            ),
+            // The CPU has no `full` line before Linux 5.13.
+            (
+                "some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n",
+                vec![("some", 0.0, 0)],
+            ),
+            (
+                "some avg10=x total=1\nfull avg10=0.00\nother avg10=0.00 total=1\n",
+                vec![],
+            ),
+            ("", vec![]),
+        ];
+        for (content, expected) in cases {
+            let expected: Vec<_> = expected
+                .into_iter()
+                .map(|(kind, avg10, total)| PressureStall {
+                    resource: "memory",
+                    kind,
+                    avg10,
+                    total: Duration::from_micros(total),
+                })
+                .collect();
+            assert_eq!(parse_pressure("memory", content), expected, "{content:?}");
+        }
+    }
+
+    #[cfg(feature = "numa")]
+    #[test]
+    fn numa_maps() {
+        let cases = [
+            (
+                "7f0c1a2b3000 default file=/usr/lib/libc.so.6 mapped=3 N0=2 N1=1 kernelpagesize_kB=4\n\
+                 7f0c1a400000 default anon=1 dirty=1 N1=1 kernelpagesize_kB=2048\n",
+                Some(vec![(0, 2 * 4096), (1, 4096 + 2048 * 1024)]),
+            ),
+            // Mappings without resident pages have no node counts.
+            ("7f0c1a2b3000 default stack\n", Some(vec![])),
+            ("7f0c1a2b3000 default N0=1 kernelpagesize_kB=huge\n", None),
+        ];
+        for (content, expected) in cases {
+            let expected = expected.map(|nodes| {
+                nodes
+                    .into_iter()
+                    .map(|(node, bytes)| NumaNodeMemory { node, bytes })
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(parse_numa_maps(content), expected, "{content:?}");
+        }
+    }
+}
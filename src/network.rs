@@ -0,0 +1,282 @@
+//! Sockets of a process, which sysinfo does not expose.
+
+use std::collections::BTreeMap;
+#[cfg(target_os = "linux")]
+use std::collections::HashSet;
+
+use crate::ConnectionCount;
+
+/// Sockets of the process by protocol, and state for TCP. Empty when the
+/// platform is not supported, the process is gone, or its sockets cannot be
+/// read.
+pub(crate) fn connections(pid: u32) -> Vec<ConnectionCount> {
+    let mut counts = BTreeMap::new();
+    for connection in read(pid) {
+        *counts.entry(connection).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|((protocol, state), count)| ConnectionCount {
+            protocol,
+            state,
+            count,
+        })
+        .collect()
+}
+
+/// Protocol and state of a socket.
+type Connection = (&'static str, Option<&'static str>);
+
+/// Sockets of the process, from the inodes of its file descriptors and the
+/// socket tables of its network namespace. Sockets in `time_wait` belong to
+/// no process anymore and are not counted.
+#[cfg(target_os = "linux")]
+fn read(pid: u32) -> Vec<Connection> {
+    use std::fs;
+
+    let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return Vec::new();
+    };
+    // Links look like `socket:[12345]`.
+    let inodes: HashSet<String> = fds
+        .flatten()
+        .filter_map(|fd| {
+            let target = fs::read_link(fd.path()).ok()?;
+            let inode = target
+                .to_str()?
+                .strip_prefix("socket:[")?
+                .strip_suffix(']')?;
+            Some(inode.to_string())
+        })
+        .collect();
+    if inodes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut connections = Vec::new();
+    for (protocol, table) in [
+        ("tcp", "tcp"),
+        ("tcp", "tcp6"),
+        ("udp", "udp"),
+        ("udp", "udp6"),
+    ] {
+        if let Ok(content) = fs::read_to_string(format!("/proc/{pid}/net/{table}")) {
+            connections.extend(parse_socket_table(protocol, &content, &inodes));
+        }
+    }
+    connections
+}
+
+/// The sockets of a table of `/proc/<pid>/net` whose inode is in `inodes`.
+#[cfg(target_os = "linux")]
+fn parse_socket_table(
+    protocol: &'static str,
+    content: &str,
+    inodes: &HashSet<String>,
+) -> Vec<Connection> {
+    // After the header, lines look like `0: 0100007F:1F90 00000000:0000
+    // 0A 00000000:00000000 00:00000000 00000000 1000 0 12345 ...`, with
+    // the state in hexadecimal and the inode.
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (state, inode) = (fields.get(3)?, fields.get(9)?);
+            if !inodes.contains(*inode) {
+                return None;
+            }
+            let state = match protocol {
+                "tcp" => tcp_state(u8::from_str_radix(state, 16).unwrap_or_default()),
+                _ => None,
+            };
+            Some((protocol, state))
+        })
+        .collect()
+}
+
+/// Name of a state of `include/net/tcp_states.h`.
+#[cfg(target_os = "linux")]
+fn tcp_state(state: u8) -> Option<&'static str> {
+    Some(match state {
+        0x01 => "established",
+        0x02 => "syn_sent",
+        0x03 => "syn_received",
+        0x04 => "fin_wait_1",
+        0x05 => "fin_wait_2",
+        0x06 => "time_wait",
+        0x07 => "close",
+        0x08 => "close_wait",
+        0x09 => "last_ack",
+        0x0A => "listen",
+        0x0B => "closing",
+        _ => return None,
+    })
+}
+
+/// Sockets of the process, from the TCP and UDP tables of the host. Sockets
+/// in `time_wait` belong to no process anymore and are not counted.
+#[cfg(windows)]
+fn read(pid: u32) -> Vec<Connection> {
+    use windows_sys::Win32::NetworkManagement::IpHelper::{
+        GetExtendedTcpTable, GetExtendedUdpTable, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID,
+        MIB_UDP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID, TCP_TABLE_OWNER_PID_ALL, UDP_TABLE_OWNER_PID,
+    };
+    use windows_sys::Win32::Networking::WinSock::{AF_INET, AF_INET6};
+
+    let mut connections = Vec::new();
+    for family in [AF_INET, AF_INET6] {
+        let family = u32::from(family);
+        // SAFETY: `table` calls it with a buffer of `size` bytes.
+        let tcp = table(|buffer, size| unsafe {
+            GetExtendedTcpTable(buffer, size, 0, family, TCP_TABLE_OWNER_PID_ALL, 0)
+        });
+        let tcp = if family == u32::from(AF_INET) {
+            rows::<MIB_TCPROW_OWNER_PID>(&tcp)
+                .iter()
+                .filter(|row| row.dwOwningPid == pid)
+                .map(|row| row.dwState)
+                .collect::<Vec<_>>()
+        } else {
+            rows::<MIB_TCP6ROW_OWNER_PID>(&tcp)
+                .iter()
+                .filter(|row| row.dwOwningPid == pid)
+                .map(|row| row.dwState)
+                .collect()
+        };
+        connections.extend(tcp.into_iter().map(|state| ("tcp", tcp_state(state))));
+
+        // SAFETY: `table` calls it with a buffer of `size` bytes.
+        let udp = table(|buffer, size| unsafe {
+            GetExtendedUdpTable(buffer, size, 0, family, UDP_TABLE_OWNER_PID, 0)
+        });
+        let udp = if family == u32::from(AF_INET) {
+            rows::<MIB_UDPROW_OWNER_PID>(&udp)
+                .iter()
+                .filter(|row| row.dwOwningPid == pid)
+                .count()
+        } else {
+            rows::<MIB_UDP6ROW_OWNER_PID>(&udp)
+                .iter()
+                .filter(|row| row.dwOwningPid == pid)
+                .count()
+        };
+        connections.extend(std::iter::repeat_n(("udp", None), udp));
+    }
+    connections
+}
+
+/// A table of `GetExtendedTcpTable` or `GetExtendedUdpTable`, empty if it
+/// could not be read. The buffer is grown until the table fits, as
+/// connections may be opened between two calls.
+#[cfg(windows)]
+fn table(get: impl Fn(*mut std::ffi::c_void, *mut u32) -> u32) -> Vec<u32> {
+    use windows_sys::Win32::Foundation::{ERROR_INSUFFICIENT_BUFFER, NO_ERROR};
+
+    let mut buffer = Vec::<u32>::new();
+    for _ in 0..3 {
+        let mut size = (buffer.len() * 4) as u32;
+        match get(buffer.as_mut_ptr().cast(), &mut size) {
+            NO_ERROR => return buffer,
+            ERROR_INSUFFICIENT_BUFFER => buffer.resize((size as usize).div_ceil(4), 0),
+            _ => break,
+        }
+    }
+    Vec::new()
+}
+
+/// The rows of a table, after its number of entries.
+#[cfg(windows)]
+fn rows<Row>(table: &[u32]) -> &[Row] {
+    let Some((&count, rows)) = table.split_first() else {
+        return &[];
+    };
+    let count = (count as usize).min(std::mem::size_of_val(rows) / std::mem::size_of::<Row>());
+    // SAFETY: the rows are made of `u32`s and bytes, with the alignment of
+    // `u32`, and `count` of them fit in the buffer.
+    unsafe { std::slice::from_raw_parts(rows.as_ptr().cast(), count) }
+}
+
+/// Name of a `MIB_TCP_STATE`.
+#[cfg(windows)]
+fn tcp_state(state: u32) -> Option<&'static str> {
+    Some(match state {
+        1 => "close",
+        2 => "listen",
+        3 => "syn_sent",
+        4 => "syn_received",
+        5 => "established",
+        6 => "fin_wait_1",
+        7 => "fin_wait_2",
+        8 => "close_wait",
+        9 => "closing",
+        10 => "last_ack",
+        11 => "time_wait",
+        _ => return None,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read(_pid: u32) -> Vec<Connection> {
+    Vec::new()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when \
+                          retrnsmt   uid  timeout inode";
+
+    #[test]
+    fn socket_table() {
+        let inodes = HashSet::from(["12345".to_string(), "23456".to_string()]);
+        let cases = [
+            (
+                "tcp",
+                "   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 12345 1 0000000000000000 100 0 0 10 0",
+                vec![("tcp", Some("listen"))],
+            ),
+            (
+                "tcp",
+                "   1: 0100007F:9C40 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 23456 1 0000000000000000 20 4 30 10 -1",
+                vec![("tcp", Some("established"))],
+            ),
+            // Sockets of other processes, and in `time_wait` without inode.
+            (
+                "tcp",
+                "   2: 0100007F:9C41 0100007F:1F90 06 00000000:00000000 03:00000F9F 00000000     0        0 0 3 0000000000000000",
+                vec![],
+            ),
+            (
+                "tcp",
+                "   3: 0100007F:9C42 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 99999 1",
+                vec![],
+            ),
+            (
+                "tcp",
+                "   4: 0100007F:9C43 0100007F:1F90 FF 00000000:00000000 00:00000000 00000000  1000        0 12345 1",
+                vec![("tcp", None)],
+            ),
+            (
+                "udp",
+                "  5: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000   104        0 23456 2 0000000000000000 0",
+                vec![("udp", None)],
+            ),
+            ("tcp", "   6: 0100007F:1F90 00000000:0000 0A", vec![]),
+        ];
+        for (protocol, line, expected) in cases {
+            let content = format!("{HEADER}\n{line}\n");
+            assert_eq!(
+                parse_socket_table(protocol, &content, &inodes),
+                expected,
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn header_only() {
+        assert!(parse_socket_table("tcp", HEADER, &HashSet::new()).is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+//! Sampling on a fixed interval, decoupled from the collections of the
+//! reader which record the latest sample.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use opentelemetry::metrics::{CallbackRegistration, Meter};
+
+use crate::instruments::{CountingObserver, ProcessRecorder};
+use crate::sampler::{ProcessMetricsSnapshot, ProcessSampler};
+use crate::Result;
+#[cfg(any(feature = "async", feature = "tokio"))]
+use crate::{ExitCallback, ProcessMetricsConfig};
+
+/// State of the process shared by the sampling loop and the callback.
+#[derive(Default)]
+pub(crate) enum Latest {
+    /// Nothing sampled yet.
+    #[default]
+    Pending,
+    Sample {
+        snapshot: Box<ProcessMetricsSnapshot>,
+        // CPU usage of every sample since the previous collection.
+        cpu_stats: CpuStats,
+    },
+    Exited {
+        reported: bool,
+    },
+}
+
+/// Minimum, average and maximum of CPU usages.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct CpuStats {
+    min: f32,
+    max: f32,
+    sum: f64,
+    count: u32,
+}
+
+impl CpuStats {
+    fn add(&mut self, cpu_usage: f32) {
+        if self.count == 0 {
+            self.min = cpu_usage;
+            self.max = cpu_usage;
+        } else {
+            self.min = self.min.min(cpu_usage);
+            self.max = self.max.max(cpu_usage);
+        }
+        self.sum += f64::from(cpu_usage);
+        self.count += 1;
+    }
+
+    /// The minimum, average and maximum, `None` without usage.
+    pub(crate) fn get(&self) -> Option<(f32, f64, f32)> {
+        (self.count > 0).then(|| (self.min, self.sum / f64::from(self.count), self.max))
+    }
+}
+
+/// Register a callback recording the latest sample of the process `pid`.
+pub(crate) fn register(
+    meter: &Meter,
+    recorder: Arc<ProcessRecorder>,
+    pid: u32,
+) -> Result<(Arc<Mutex<Latest>>, Registration)> {
+    let latest = Arc::new(Mutex::new(Latest::Pending));
+    let callback_latest = latest.clone();
+    let slot = Arc::new(Mutex::new(None));
+    let callback_slot = slot.clone();
+    let registration = meter.register_callback(&recorder.instruments(), move |observer| {
+        let span = tracing::debug_span!(
+            "process_observer_record",
+            pid,
+            metrics_recorded = tracing::field::Empty
+        )
+        .entered();
+        let observer = &CountingObserver::new(observer);
+        match &mut *callback_latest.lock().unwrap() {
+            Latest::Pending => {}
+            Latest::Sample {
+                snapshot,
+                cpu_stats,
+            } => {
+                recorder.record(observer, snapshot);
+                recorder.record_cpu_stats(observer, std::mem::take(cpu_stats));
+            }
+            Latest::Exited { reported } => {
+                if !*reported {
+                    recorder.record_exit(observer);
+                    *reported = true;
+                    // Only kept registered until the exit is reported.
+                    if let Some(registration) = callback_slot.lock().unwrap().take() {
+                        unregister_later(registration);
+                    }
+                }
+            }
+        }
+        span.record("metrics_recorded", observer.count());
+    })?;
+    *slot.lock().unwrap() = Some(registration);
+    Ok((latest, Registration(Some(slot))))
+}
+
+/// Shared with the callback, which unregisters itself once it reported the
+/// exit of the process.
+type Slot = Arc<Mutex<Option<Box<dyn CallbackRegistration>>>>;
+
+/// Unregisters the callback when the sampling loop is stopped.
+pub(crate) struct Registration(Option<Slot>);
+
+impl Registration {
+    pub(crate) fn new(registration: Box<dyn CallbackRegistration>) -> Self {
+        Self(Some(Arc::new(Mutex::new(Some(registration)))))
+    }
+
+    /// Once the process exited: keep the callback registered until it
+    /// reported the exit with [`crate::OnExit::ReportExit`], otherwise
+    /// unregister it now.
+    pub(crate) fn exited(mut self, report_exit: bool) {
+        if report_exit {
+            self.0.take();
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let Some(slot) = self.0.take() else {
+            return;
+        };
+        let registration = slot.lock().unwrap().take();
+        if let Some(registration) = registration {
+            unregister(registration);
+        }
+    }
+}
+
+fn unregister(mut registration: Box<dyn CallbackRegistration>) {
+    if let Err(err) = registration.unregister() {
+        tracing::debug!(
+            "Could not unregister the process callback. Error: {:?}",
+            err
+        );
+    }
+}
+
+/// Unregister a callback from within a collection, which holds the lock of
+/// the pipeline the registration needs, once the collection is over.
+fn unregister_later(registration: Box<dyn CallbackRegistration>) {
+    let spawned = std::thread::Builder::new()
+        .name("otel-process-unregister".to_string())
+        .spawn(move || unregister(registration));
+    if let Err(err) = spawned {
+        tracing::debug!("Could not unregister the process callback. Error: {err}");
+    }
+}
+
+/// Store a new sample, returning `false` once the process exited.
+pub(crate) fn update(
+    sampler: &mut dyn ProcessSampler,
+    recorder: &ProcessRecorder,
+    latest: &Mutex<Latest>,
+    registration: &mut Option<Registration>,
+) -> bool {
+    let span = tracing::debug_span!(
+        "process_observer_sample",
+        pid = sampler.pid(),
+        duration = tracing::field::Empty
+    )
+    .entered();
+    let start = Instant::now();
+    let sample = sampler.sample();
+    span.record("duration", tracing::field::debug(start.elapsed()));
+    match sample {
+        Some(snapshot) => {
+            let mut latest = latest.lock().unwrap();
+            let mut cpu_stats = match &*latest {
+                Latest::Sample { cpu_stats, .. } => *cpu_stats,
+                _ => CpuStats::default(),
+            };
+            if !snapshot.zombie {
+                recorder.record_cpu(snapshot.cpu_usage);
+                cpu_stats.add(snapshot.cpu_usage);
+            }
+            *latest = Latest::Sample {
+                snapshot: Box::new(snapshot),
+                cpu_stats,
+            };
+            true
+        }
+        None => {
+            *latest.lock().unwrap() = Latest::Exited { reported: false };
+            if let Some(registration) = registration.take() {
+                registration.exited(recorder.reports_exit());
+            }
+            false
+        }
+    }
+}
+
+/// Add the CPU usage alone to the latest sample, see
+/// [`crate::ProcessMetricsConfig::cpu_sampling_interval`]. The exit of the
+/// process is noticed by the next sample.
+pub(crate) fn update_cpu(
+    sampler: &mut dyn ProcessSampler,
+    recorder: &ProcessRecorder,
+    latest: &Mutex<Latest>,
+) {
+    let Some(cpu_usage) = sampler.sample_cpu() else {
+        return;
+    };
+    recorder.record_cpu(cpu_usage);
+    if let Latest::Sample { cpu_stats, .. } = &mut *latest.lock().unwrap() {
+        cpu_stats.add(cpu_usage);
+    }
+}
+
+/// A random delay up to `max`, to spread the samples of observers started
+/// at the same time.
+pub(crate) fn jitter(max: std::time::Duration) -> std::time::Duration {
+    use std::hash::{BuildHasher, Hasher};
+
+    // Seeded randomly by the standard library, no need for a dependency.
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    max.mul_f64(random as f64 / u64::MAX as f64)
+}
+
+/// Sample after up to [`ProcessMetricsConfig::start_jitter`], then every
+/// `interval` until the process exits, and the CPU usage alone every
+/// `cpu_sampling_interval` in between. The timer of tokio is used with the
+/// `tokio` feature, otherwise no async runtime is needed.
+#[cfg(any(feature = "async", feature = "tokio"))]
+pub(crate) async fn run(
+    mut sampler: Box<dyn ProcessSampler>,
+    recorder: Arc<ProcessRecorder>,
+    latest: Arc<Mutex<Latest>>,
+    registration: Registration,
+    config: &ProcessMetricsConfig,
+    on_exit: Option<ExitCallback>,
+) {
+    let interval = config.interval;
+    // Dropping the future, for instance when the task is aborted, drops the
+    // registration and stops recording.
+    let mut registration = Some(registration);
+    // Wait for the warm up instead of letting the sampler block.
+    sleep(crate::sampler::WARM_UP + jitter(config.start_jitter)).await;
+    while update(sampler.as_mut(), &recorder, &latest, &mut registration) {
+        let next_sample = Instant::now() + interval;
+        if let Some(cpu_interval) = config.cpu_sampling_interval {
+            while Instant::now() + cpu_interval < next_sample {
+                sleep(cpu_interval).await;
+                update_cpu(sampler.as_mut(), &recorder, &latest);
+            }
+        }
+        sleep(next_sample.saturating_duration_since(Instant::now())).await;
+    }
+    if let Some(on_exit) = on_exit {
+        on_exit(sampler.pid());
+    }
+}
+
+/// Sample right away, then every `interval` until the process exits.
+#[cfg(any(feature = "async", feature = "tokio"))]
+pub(crate) fn stream(
+    sampler: Box<dyn ProcessSampler>,
+    interval: std::time::Duration,
+) -> impl futures_util::Stream<Item = ProcessMetricsSnapshot> {
+    futures_util::stream::unfold(
+        (sampler, crate::sampler::WARM_UP),
+        move |(mut sampler, delay)| async move {
+            sleep(delay).await;
+            let snapshot = sampler.sample()?;
+            Some((snapshot, (sampler, interval)))
+        },
+    )
+}
+
+#[cfg(feature = "tokio")]
+async fn sleep(duration: std::time::Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(all(feature = "async", not(feature = "tokio")))]
+async fn sleep(duration: std::time::Duration) {
+    futures_timer::Delay::new(duration).await;
+}
@@ -0,0 +1,75 @@
+//! Scheduling priority of a process, which sysinfo does not expose.
+
+/// Nice value of the process, from -20, the highest priority, to 19. `None`
+/// when the platform is not supported or the process is gone.
+#[cfg(target_os = "linux")]
+pub(crate) fn nice(pid: u32) -> Option<i32> {
+    crate::linux::ProcStat::read(pid).map(|stat| stat.nice)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn nice(pid: u32) -> Option<i32> {
+    use std::mem;
+
+    let mut info: libc::proc_bsdinfo = unsafe { mem::zeroed() };
+    let size = mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+    // SAFETY: `info` is a valid buffer of `size` bytes.
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    (written == size).then_some(info.pbi_nice)
+}
+
+#[cfg(target_os = "freebsd")]
+pub(crate) fn nice(pid: u32) -> Option<i32> {
+    crate::freebsd::kinfo_proc(pid).map(|info| info.ki_nice.into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+pub(crate) fn nice(_pid: u32) -> Option<i32> {
+    None
+}
+
+/// Priority class of the process, such as `normal` or `high`. `None` when
+/// the platform is not supported or the process is gone.
+#[cfg(windows)]
+pub(crate) fn priority_class(pid: u32) -> Option<&'static str> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        GetPriorityClass, OpenProcess, ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS,
+        HIGH_PRIORITY_CLASS, IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+        PROCESS_QUERY_LIMITED_INFORMATION, REALTIME_PRIORITY_CLASS,
+    };
+
+    // SAFETY: the handle is checked and closed below.
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle == 0 {
+        return None;
+    }
+    // SAFETY: `handle` is a valid process handle.
+    let class = unsafe { GetPriorityClass(handle) };
+    // SAFETY: `handle` was opened above and is not used afterwards.
+    unsafe { CloseHandle(handle) };
+
+    match class {
+        IDLE_PRIORITY_CLASS => Some("idle"),
+        BELOW_NORMAL_PRIORITY_CLASS => Some("below_normal"),
+        NORMAL_PRIORITY_CLASS => Some("normal"),
+        ABOVE_NORMAL_PRIORITY_CLASS => Some("above_normal"),
+        HIGH_PRIORITY_CLASS => Some("high"),
+        REALTIME_PRIORITY_CLASS => Some("realtime"),
+        // 0 when the call failed.
+        _ => None,
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn priority_class(_pid: u32) -> Option<&'static str> {
+    None
+}
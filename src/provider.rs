@@ -0,0 +1,34 @@
+//! Registration of the observer on an existing meter provider.
+
+use opentelemetry::metrics::MeterProvider;
+
+use crate::{ProcessMetricsConfig, ProcessObserverBuilder, Result};
+
+/// Register a process observer while building the meter provider of the
+/// application, instead of separately.
+/// # Example
+///
+/// ```
+/// use opentelemetry_sdk::metrics::{ManualReader, SdkMeterProvider};
+/// use opentelemetry_system_metrics::{MeterProviderExt, ProcessMetricsConfig};
+///
+/// let provider = SdkMeterProvider::builder()
+///     .with_reader(ManualReader::builder().build())
+///     .build()
+///     .with_process_metrics(ProcessMetricsConfig::from_env())
+///     .unwrap();
+/// ```
+///
+pub trait MeterProviderExt: MeterProvider + Sized {
+    /// Observe the current process, or the one set in
+    /// `OTEL_PROCESS_METRICS_PID`, with `config` on a meter of the provider,
+    /// as [`ProcessObserverBuilder::init`] does, and return the provider.
+    fn with_process_metrics(self, config: ProcessMetricsConfig) -> Result<Self> {
+        ProcessObserverBuilder::new(self.meter(crate::METER_NAME))
+            .with_config(config)
+            .init()?;
+        Ok(self)
+    }
+}
+
+impl<T: MeterProvider> MeterProviderExt for T {}
@@ -0,0 +1,772 @@
+//! Sampling resources shared by every process observer.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use opentelemetry::metrics::Meter;
+use sysinfo::{CpuRefreshKind, RefreshKind, System, SystemExt};
+
+use crate::group::Group;
+use crate::instruments::ProcessRecorder;
+use crate::periodic::{self, Latest, Registration};
+use crate::sampler::{self, ProcessSampler};
+use crate::{
+    Error, ExitCallback, MetricGroups, Prepared, ProcessMetricsConfig, ProcessObserverBuilder,
+    Result,
+};
+
+/// Sampling resources shared by process observers: a single sysinfo
+/// `System`, and a single thread sampling every observer created with
+/// [`crate::ProcessObserverBuilder::spawn`].
+///
+/// Observers use [`SamplerRegistry::global`] unless another registry is
+/// given with [`crate::ProcessObserverBuilder::with_registry`].
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{ProcessObserverBuilder, SamplerRegistry};
+///
+/// let registry = SamplerRegistry::new();
+/// let meter = global::meter("process-meter");
+/// let handle = ProcessObserverBuilder::new(meter)
+///     .with_registry(registry)
+///     .spawn();
+/// ```
+///
+#[derive(Clone)]
+pub struct SamplerRegistry {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    // Created on first use, observers which are disabled do not read
+    // anything.
+    system: OnceLock<Arc<Mutex<System>>>,
+    scheduler: Mutex<Scheduler>,
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct Scheduler {
+    entries: Vec<Entry>,
+    // Completed once the processes are sampled, after the given instant,
+    // see `ObserverHandle::trigger`.
+    triggers: Vec<(Instant, Arc<Mutex<Trigger>>)>,
+    next_handle: u64,
+    running: bool,
+}
+
+#[derive(Default)]
+struct Trigger {
+    done: bool,
+    waker: Option<Waker>,
+}
+
+impl Trigger {
+    fn complete(trigger: &Mutex<Trigger>) {
+        let mut trigger = trigger.lock().unwrap();
+        trigger.done = true;
+        if let Some(waker) = trigger.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves once the trigger is completed.
+struct Triggered(Arc<Mutex<Trigger>>);
+
+impl Future for Triggered {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut trigger = self.0.lock().unwrap();
+        if trigger.done {
+            Poll::Ready(())
+        } else {
+            trigger.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// A process sampled by the thread of the registry.
+struct Entry {
+    // The `ObserverHandle` of the process.
+    handle: u64,
+    pid: u32,
+    // Locked while sampling, without the lock of the scheduler so that the
+    // callbacks of the sampler can use the handle.
+    sampler: Arc<Mutex<Box<dyn ProcessSampler>>>,
+    // Applied to the sampler before its next sample, see
+    // `ObserverHandle::set_metric_groups`.
+    metric_groups: Option<MetricGroups>,
+    // Kept to create the instruments again when the metric groups change.
+    recorder: Arc<ProcessRecorder>,
+    latest: Arc<Mutex<Latest>>,
+    // `None` once the process exited.
+    registration: Option<Registration>,
+    interval: Duration,
+    next_sample: Instant,
+    // Until the first sample, scheduled after the warm up and the jitter.
+    sampled: bool,
+    // Sampling of the CPU usage alone, between two samples.
+    cpu_interval: Option<Duration>,
+    next_cpu_sample: Instant,
+    on_exit: Option<ExitCallback>,
+}
+
+/// An entry due, sampled once the lock of the scheduler is released.
+struct Due {
+    sampler: Arc<Mutex<Box<dyn ProcessSampler>>>,
+    metric_groups: Option<MetricGroups>,
+    recorder: Arc<ProcessRecorder>,
+    latest: Arc<Mutex<Latest>>,
+    // Of the CPU usage alone, see `ProcessMetricsConfig::cpu_sampling_interval`.
+    cpu_only: bool,
+}
+
+impl Entry {
+    /// When the entry should be sampled next, of its CPU usage alone or not.
+    fn next_due(&self) -> Instant {
+        match self.cpu_interval {
+            Some(_) => self.next_sample.min(self.next_cpu_sample),
+            None => self.next_sample,
+        }
+    }
+}
+
+impl SamplerRegistry {
+    /// A registry independent of the global one.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                system: OnceLock::new(),
+                scheduler: Mutex::new(Scheduler::default()),
+                wake: Condvar::new(),
+            }),
+        }
+    }
+
+    /// The registry shared by every observer of the program, unless
+    /// configured otherwise.
+    pub fn global() -> Self {
+        static GLOBAL: OnceLock<SamplerRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(SamplerRegistry::new).clone()
+    }
+
+    pub(crate) fn system(&self) -> &Arc<Mutex<System>> {
+        self.inner.system.get_or_init(|| {
+            // Processes are only read when observed, the CPU list is needed
+            // for the normalization of the CPU usage.
+            Arc::new(Mutex::new(System::new_with_specifics(
+                RefreshKind::new().with_cpu(CpuRefreshKind::new()),
+            )))
+        })
+    }
+
+    /// A handle sampling nothing, for a disabled observer.
+    pub(crate) fn disabled(&self, meter: Meter, config: ProcessMetricsConfig) -> ObserverHandle {
+        let mut scheduler = self.inner.scheduler.lock().unwrap();
+        scheduler.next_handle += 1;
+        ObserverHandle {
+            registry: self.clone(),
+            id: scheduler.next_handle,
+            meter,
+            config,
+            group: None,
+        }
+    }
+
+    /// Sample the prepared observer every [`ProcessMetricsConfig::interval`]
+    /// on the thread of the registry, recording the totals of its processes
+    /// if `group` is set.
+    pub(crate) fn spawn(
+        &self,
+        meter: Meter,
+        config: ProcessMetricsConfig,
+        prepared: Prepared,
+        group: bool,
+    ) -> Result<ObserverHandle> {
+        let handle = {
+            let mut scheduler = self.inner.scheduler.lock().unwrap();
+            scheduler.next_handle += 1;
+            scheduler.next_handle
+        };
+        self.insert(handle, &meter, &config, prepared)?;
+        let group = match group {
+            true => Some(Group::register(&meter, &config)?),
+            false => None,
+        };
+        let handle = ObserverHandle {
+            registry: self.clone(),
+            id: handle,
+            meter,
+            config,
+            group,
+        };
+        handle.update_group();
+        Ok(handle)
+    }
+
+    /// Register the instruments of `prepared` and sample it as part of
+    /// `handle`.
+    fn insert(
+        &self,
+        handle: u64,
+        meter: &Meter,
+        config: &ProcessMetricsConfig,
+        prepared: Prepared,
+    ) -> Result<()> {
+        let recorder = Arc::new(prepared.recorder);
+        let pid = prepared.sampler.pid();
+        let (latest, registration) = periodic::register(meter, recorder.clone(), pid)?;
+
+        let start = Instant::now() + sampler::WARM_UP + periodic::jitter(config.start_jitter);
+        let mut scheduler = self.inner.scheduler.lock().unwrap();
+        if !scheduler.running {
+            let inner = self.inner.clone();
+            thread::Builder::new()
+                .name("process-observer".to_string())
+                .spawn(move || inner.run())
+                .map_err(Error::Thread)?;
+            scheduler.running = true;
+        }
+        scheduler.entries.push(Entry {
+            handle,
+            pid,
+            sampler: Arc::new(Mutex::new(prepared.sampler)),
+            metric_groups: None,
+            recorder,
+            latest,
+            registration: Some(registration),
+            interval: config.interval,
+            // Sampled right away, once the CPU usage is meaningful, unless
+            // jittered.
+            next_sample: start,
+            sampled: false,
+            cpu_interval: config.cpu_sampling_interval,
+            next_cpu_sample: start,
+            on_exit: prepared.on_exit,
+        });
+        self.inner.wake.notify_all();
+        Ok(())
+    }
+}
+
+impl Default for SamplerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    /// Sample the observers when they are due, until none is left.
+    fn run(&self) {
+        let mut scheduler = self.scheduler.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let mut due = Vec::new();
+            for entry in scheduler.entries.iter_mut() {
+                let cpu_only = if entry.next_sample <= now {
+                    entry.next_sample = now + entry.interval;
+                    entry.sampled = true;
+                    entry.next_cpu_sample = now + entry.cpu_interval.unwrap_or(entry.interval);
+                    false
+                } else if let Some(cpu_interval) =
+                    entry.cpu_interval.filter(|_| entry.next_cpu_sample <= now)
+                {
+                    entry.next_cpu_sample = now + cpu_interval;
+                    true
+                } else {
+                    continue;
+                };
+                due.push(Due {
+                    sampler: entry.sampler.clone(),
+                    metric_groups: entry.metric_groups.take(),
+                    recorder: entry.recorder.clone(),
+                    latest: entry.latest.clone(),
+                    cpu_only,
+                });
+            }
+            // Triggered entries are due.
+            let (triggers, pending) = std::mem::take(&mut scheduler.triggers)
+                .into_iter()
+                .partition::<Vec<_>, _>(|(due, _)| *due <= now);
+            scheduler.triggers = pending;
+            drop(scheduler);
+
+            let mut exited = Vec::new();
+            for due in due {
+                let mut sampler = due.sampler.lock().unwrap();
+                if let Some(metric_groups) = due.metric_groups {
+                    sampler.set_metric_groups(metric_groups);
+                }
+                if due.cpu_only {
+                    periodic::update_cpu(sampler.as_mut(), &due.recorder, &due.latest);
+                } else if !periodic::update(sampler.as_mut(), &due.recorder, &due.latest, &mut None)
+                {
+                    drop(sampler);
+                    exited.push(due.sampler);
+                }
+            }
+            for (_, trigger) in triggers {
+                Trigger::complete(&trigger);
+            }
+
+            scheduler = self.scheduler.lock().unwrap();
+            let mut on_exits = Vec::new();
+            if !exited.is_empty() {
+                scheduler.entries.retain_mut(|entry| {
+                    if !exited
+                        .iter()
+                        .any(|sampler| Arc::ptr_eq(sampler, &entry.sampler))
+                    {
+                        return true;
+                    }
+                    if let Some(registration) = entry.registration.take() {
+                        registration.exited(entry.recorder.reports_exit());
+                    }
+                    on_exits.extend(entry.on_exit.take().map(|on_exit| (on_exit, entry.pid)));
+                    false
+                });
+            }
+            // Exit callbacks may use the registry.
+            if !on_exits.is_empty() {
+                drop(scheduler);
+                for (on_exit, pid) in on_exits {
+                    on_exit(pid);
+                }
+                scheduler = self.scheduler.lock().unwrap();
+                continue;
+            }
+
+            let Some(next_sample) = scheduler.entries.iter().map(Entry::next_due).min() else {
+                scheduler.running = false;
+                // Their processes are not observed anymore.
+                for (_, trigger) in scheduler.triggers.drain(..) {
+                    Trigger::complete(&trigger);
+                }
+                return;
+            };
+            let timeout = next_sample.saturating_duration_since(Instant::now());
+            scheduler = self.wake.wait_timeout(scheduler, timeout).unwrap().0;
+        }
+    }
+}
+
+/// Handle of a process observer sampling on the thread of a
+/// [`SamplerRegistry`], see [`crate::ProcessObserverBuilder::spawn`].
+///
+/// The observer can be reconfigured through the handle while it runs, and
+/// observe other processes with the same configuration. Dropping the handle
+/// stops observing every process.
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{MetricGroups, ProcessObserverBuilder};
+///
+/// let meter = global::meter("process-meter");
+/// let mut handle = ProcessObserverBuilder::new(meter).spawn().unwrap();
+/// handle.set_interval(Duration::from_secs(5));
+/// handle
+///     .set_metric_groups(MetricGroups {
+///         gpu: false,
+///         ..Default::default()
+///     })
+///     .unwrap();
+/// ```
+///
+pub struct ObserverHandle {
+    registry: SamplerRegistry,
+    id: u64,
+    // To observe other processes the same way.
+    meter: Meter,
+    config: ProcessMetricsConfig,
+    // See `ProcessObserverBuilder::with_group`.
+    group: Option<Group>,
+}
+
+impl ObserverHandle {
+    /// Sum the processes of the observer in the group instruments, after
+    /// they changed.
+    fn update_group(&self) {
+        let Some(group) = &self.group else {
+            return;
+        };
+        let scheduler = self.registry.inner.scheduler.lock().unwrap();
+        group.set_members(
+            scheduler
+                .entries
+                .iter()
+                .filter(|entry| entry.handle == self.id)
+                .map(|entry| entry.latest.clone()),
+        );
+    }
+
+    /// Whether the observer is still sampling a process, `false` once every
+    /// process exited or the observer was stopped.
+    pub fn is_running(&self) -> bool {
+        let scheduler = self.registry.inner.scheduler.lock().unwrap();
+        scheduler
+            .entries
+            .iter()
+            .any(|entry| entry.handle == self.id)
+    }
+
+    /// PIDs of the processes sampled, excluding the ones that exited.
+    pub fn pids(&self) -> Vec<u32> {
+        let scheduler = self.registry.inner.scheduler.lock().unwrap();
+        scheduler
+            .entries
+            .iter()
+            .filter(|entry| entry.handle == self.id)
+            .map(|entry| entry.pid)
+            .collect()
+    }
+
+    /// Sample every `interval` from now on. A sample is taken right away if
+    /// the previous one is older than `interval`. A zero interval, which
+    /// would sample in a busy loop, is ignored with a warning.
+    pub fn set_interval(&mut self, interval: Duration) {
+        if interval.is_zero() {
+            tracing::warn!("Ignoring a zero sampling interval");
+            return;
+        }
+        self.config.interval = interval;
+        let mut scheduler = self.registry.inner.scheduler.lock().unwrap();
+        let now = Instant::now();
+        for entry in scheduler.entries.iter_mut() {
+            if entry.handle == self.id {
+                entry.next_sample = entry.next_sample.min(now + interval);
+                entry.interval = interval;
+            }
+        }
+        self.registry.inner.wake.notify_all();
+    }
+
+    /// Sample and record only `metric_groups` from now on. The instruments
+    /// are registered again, so that disabled groups are not exported
+    /// anymore.
+    pub fn set_metric_groups(&mut self, metric_groups: MetricGroups) -> Result<()> {
+        self.config.metric_groups = metric_groups;
+        let mut scheduler = self.registry.inner.scheduler.lock().unwrap();
+        for entry in scheduler.entries.iter_mut() {
+            if entry.handle != self.id {
+                continue;
+            }
+            // The sampler may be sampling, and calling back into the handle.
+            entry.metric_groups = Some(metric_groups);
+            let recorder = Arc::new(
+                entry
+                    .recorder
+                    .with_metric_groups(&self.meter, metric_groups),
+            );
+            let (latest, registration) =
+                periodic::register(&self.meter, recorder.clone(), entry.pid)?;
+            *latest.lock().unwrap() = std::mem::take(&mut *entry.latest.lock().unwrap());
+            entry.recorder = recorder;
+            entry.latest = latest;
+            // Unregisters the former instruments.
+            entry.registration = Some(registration);
+        }
+        drop(scheduler);
+        if self.group.is_some() {
+            // Unregisters the former instruments first.
+            self.group = None;
+            self.group = Some(Group::register(&self.meter, &self.config)?);
+            self.update_group();
+        }
+        Ok(())
+    }
+
+    /// Also observe the process `pid`, with the configuration of the
+    /// observer. Custom samplers and callbacks of the builder, GPU samplers
+    /// included, are not used for it. Does nothing if the process is already
+    /// observed.
+    ///
+    /// Fails with [`crate::Error::PidNotFound`] if the process does not
+    /// exist. Does nothing either if the observer is disabled, see
+    /// [`crate::ProcessMetricsConfig::disabled`].
+    pub fn add_pid(&mut self, pid: u32) -> Result<()> {
+        if self.config.disabled || self.pids().contains(&pid) {
+            return Ok(());
+        }
+        let prepared = ProcessObserverBuilder::new(self.meter.clone())
+            .with_config(self.config.clone())
+            .with_pid(pid)
+            .with_registry(self.registry.clone())
+            .prepare()?;
+        self.registry
+            .insert(self.id, &self.meter, &self.config, prepared)?;
+        self.update_group();
+        Ok(())
+    }
+
+    /// Sample every process of the observer now, outside of the interval,
+    /// and resolve once the samples are recorded for the next collection.
+    /// Processes which were not sampled yet are sampled once their warm up
+    /// and start jitter elapsed, as they would be otherwise, so that their
+    /// CPU usage is meaningful.
+    /// Useful to capture the final state before shutting down, after
+    /// awaiting it the meter provider can be flushed.
+    ///
+    /// No async runtime is needed.
+    /// # Example
+    ///
+    /// ```no_run
+    /// use opentelemetry::global;
+    /// use opentelemetry_system_metrics::ProcessObserverBuilder;
+    ///
+    /// # async fn run() -> opentelemetry_system_metrics::Result<()> {
+    /// let meter = global::meter("process-meter");
+    /// let handle = ProcessObserverBuilder::new(meter).spawn()?;
+    /// // ...
+    /// handle.trigger().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    pub async fn trigger(&self) {
+        let trigger = Arc::new(Mutex::new(Trigger::default()));
+        {
+            let mut scheduler = self.registry.inner.scheduler.lock().unwrap();
+            let now = Instant::now();
+            let mut due = None;
+            for entry in scheduler.entries.iter_mut() {
+                if entry.handle == self.id {
+                    // The first sample waits for the warm up, for the CPU
+                    // usage to be meaningful.
+                    if entry.sampled {
+                        entry.next_sample = now;
+                    }
+                    due = due.max(Some(entry.next_sample));
+                }
+            }
+            // Without any process left, there is nothing to wait for.
+            let Some(due) = due else {
+                return;
+            };
+            scheduler.triggers.push((due, trigger.clone()));
+            self.registry.inner.wake.notify_all();
+        }
+        Triggered(trigger).await
+    }
+
+    /// Stop observing the process `pid` and unregister its instruments,
+    /// returning `false` if it was not observed.
+    pub fn remove_pid(&mut self, pid: u32) -> bool {
+        let mut scheduler = self.registry.inner.scheduler.lock().unwrap();
+        let count = scheduler.entries.len();
+        scheduler
+            .entries
+            .retain(|entry| entry.handle != self.id || entry.pid != pid);
+        let removed = scheduler.entries.len() != count;
+        drop(scheduler);
+        self.update_group();
+        removed
+    }
+
+    /// Stop sampling and unregister the instruments. A sample in progress
+    /// completes without being recorded.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for ObserverHandle {
+    fn drop(&mut self) {
+        let mut scheduler = self.registry.inner.scheduler.lock().unwrap();
+        scheduler.entries.retain(|entry| entry.handle != self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use opentelemetry::global;
+    use opentelemetry::metrics::AsyncInstrument;
+    use opentelemetry::KeyValue;
+
+    use super::*;
+
+    #[test]
+    fn callbacks_use_their_handle() {
+        let slot: Arc<Mutex<Option<ObserverHandle>>> = Arc::default();
+        let (sender, receiver) = mpsc::channel();
+        let callback_slot = slot.clone();
+        let handle = ProcessObserverBuilder::new(global::meter("test"))
+            .with_registry(SamplerRegistry::new())
+            .with_interval(Duration::from_millis(10))
+            .on_sample(move |_| {
+                if let Some(handle) = callback_slot.lock().unwrap().as_mut() {
+                    handle.set_interval(Duration::from_millis(20));
+                    let _ = sender.send((handle.is_running(), handle.pids()));
+                }
+            })
+            .spawn()
+            .unwrap();
+        *slot.lock().unwrap() = Some(handle);
+
+        let (running, pids) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(running);
+        assert_eq!(pids, [std::process::id()]);
+        slot.lock().unwrap().take();
+    }
+
+    #[test]
+    fn trigger_waits_for_the_warm_up() {
+        let start = Instant::now();
+        // Samples right away, unlike the sysinfo sampler waiting for its
+        // warm up.
+        let handle = ProcessObserverBuilder::new(global::meter("test"))
+            .with_registry(SamplerRegistry::new())
+            .with_sampler(crate::testing::MockSampler::new(std::process::id()))
+            .spawn()
+            .unwrap();
+        futures_executor(handle.trigger());
+        assert!(start.elapsed() >= sampler::WARM_UP);
+    }
+
+    #[test]
+    fn zero_intervals_are_ignored() {
+        let mut handle = ProcessObserverBuilder::new(global::meter("test"))
+            .with_registry(SamplerRegistry::new())
+            .with_sampler(crate::testing::MockSampler::new(42))
+            .with_interval(Duration::from_secs(10))
+            .spawn()
+            .unwrap();
+        handle.set_interval(Duration::ZERO);
+        assert_eq!(handle.config.interval, Duration::from_secs(10));
+        let scheduler = handle.registry.inner.scheduler.lock().unwrap();
+        assert!(scheduler
+            .entries
+            .iter()
+            .all(|entry| entry.interval == Duration::from_secs(10)));
+    }
+
+    /// A meter collecting on demand with `collect`, counting the calls of
+    /// the registered callbacks.
+    #[derive(Clone, Default)]
+    struct Callbacks {
+        callbacks: Arc<Mutex<Vec<Option<Arc<MultiCallback>>>>>,
+    }
+
+    type MultiCallback = dyn Fn(&dyn opentelemetry::metrics::Observer) + Send + Sync;
+
+    impl Callbacks {
+        fn meter(&self) -> Meter {
+            Meter::new(Arc::new(self.clone()))
+        }
+
+        /// Run the callbacks, returning how many there are.
+        fn collect(&self) -> usize {
+            let callbacks: Vec<_> = self
+                .callbacks
+                .lock()
+                .unwrap()
+                .iter()
+                .flatten()
+                .cloned()
+                .collect();
+            for callback in &callbacks {
+                callback(&Ignore);
+            }
+            callbacks.len()
+        }
+    }
+
+    struct Ignore;
+
+    impl opentelemetry::metrics::Observer for Ignore {
+        fn observe_f64(&self, _: &dyn AsyncInstrument<f64>, _: f64, _: &[KeyValue]) {}
+        fn observe_u64(&self, _: &dyn AsyncInstrument<u64>, _: u64, _: &[KeyValue]) {}
+        fn observe_i64(&self, _: &dyn AsyncInstrument<i64>, _: i64, _: &[KeyValue]) {}
+    }
+
+    struct Unregister(Callbacks, usize);
+
+    impl opentelemetry::metrics::CallbackRegistration for Unregister {
+        fn unregister(&mut self) -> opentelemetry::metrics::Result<()> {
+            self.0.callbacks.lock().unwrap()[self.1] = None;
+            Ok(())
+        }
+    }
+
+    impl opentelemetry::metrics::InstrumentProvider for Callbacks {
+        fn register_callback(
+            &self,
+            _instruments: &[Arc<dyn std::any::Any>],
+            callback: Box<MultiCallback>,
+        ) -> opentelemetry::metrics::Result<Box<dyn opentelemetry::metrics::CallbackRegistration>>
+        {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            callbacks.push(Some(callback.into()));
+            Ok(Box::new(Unregister(self.clone(), callbacks.len() - 1)))
+        }
+    }
+
+    /// Observe a process exiting after its first sample, until it exited.
+    fn observe_exit(callbacks: &Callbacks, on_exit: crate::OnExit) -> ObserverHandle {
+        let handle = ProcessObserverBuilder::new(callbacks.meter())
+            .with_registry(SamplerRegistry::new())
+            .with_sampler(crate::testing::MockSampler::new(42).exit_after(1))
+            .with_interval(Duration::from_millis(10))
+            .with_exit_behavior(on_exit)
+            .spawn()
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while handle.is_running() {
+            assert!(Instant::now() < deadline, "the process did not exit");
+            thread::sleep(Duration::from_millis(10));
+        }
+        handle
+    }
+
+    #[test]
+    fn exited_processes_are_unregistered() {
+        let callbacks = Callbacks::default();
+        let _handle = observe_exit(&callbacks, crate::OnExit::Stop);
+        assert_eq!(callbacks.collect(), 0);
+    }
+
+    #[test]
+    fn exited_processes_are_unregistered_once_reported() {
+        let callbacks = Callbacks::default();
+        let _handle = observe_exit(&callbacks, crate::OnExit::ReportExit);
+        // Reports the exit.
+        assert_eq!(callbacks.collect(), 1);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while callbacks.collect() > 0 {
+            assert!(
+                Instant::now() < deadline,
+                "the callback is still registered"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Poll `future` on the current thread until it resolves.
+    fn futures_executor(future: impl Future<Output = ()>) {
+        struct Unpark(thread::Thread);
+        impl std::task::Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut context = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        while future.as_mut().poll(&mut context).is_pending() {
+            thread::park();
+        }
+    }
+}
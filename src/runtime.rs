@@ -0,0 +1,130 @@
+//! Metrics of a tokio runtime, see
+//! [`crate::ProcessObserverBuilder::with_tokio_runtime`].
+
+#[cfg(target_has_atomic = "64")]
+use std::sync::Mutex;
+#[cfg(target_has_atomic = "64")]
+use std::time::{Duration, Instant};
+
+use opentelemetry::metrics::{Meter, Unit};
+use opentelemetry::{Key, KeyValue};
+use tokio::runtime::Handle;
+
+use crate::{prefixed, Result};
+
+const TOKIO_RUNTIME_WORKERS: &str = "tokio.runtime.workers";
+const TOKIO_RUNTIME_TASKS_ALIVE: &str = "tokio.runtime.tasks.alive";
+const TOKIO_RUNTIME_GLOBAL_QUEUE_DEPTH: &str = "tokio.runtime.global_queue.depth";
+#[cfg(target_has_atomic = "64")]
+const TOKIO_RUNTIME_WORKER_BUSY_TIME: &str = "tokio.runtime.worker.busy.time";
+#[cfg(target_has_atomic = "64")]
+const TOKIO_RUNTIME_WORKER_BUSY_RATIO: &str = "tokio.runtime.worker.busy.ratio";
+#[cfg(target_has_atomic = "64")]
+const TOKIO_RUNTIME_WORKER_PARK_COUNT: &str = "tokio.runtime.worker.park.count";
+#[cfg(target_has_atomic = "64")]
+const TOKIO_WORKER: Key = Key::from_static_str("tokio.worker");
+
+pub(crate) fn register_runtime_metrics(
+    meter: &Meter,
+    handle: Handle,
+    attributes: Vec<KeyValue>,
+    prefix: &str,
+) -> Result<()> {
+    let workers = meter
+        .u64_observable_gauge(prefixed(prefix, TOKIO_RUNTIME_WORKERS))
+        .with_description("The number of worker threads of the runtime.")
+        .with_unit(Unit::new("{thread}"))
+        .init();
+    let tasks_alive = meter
+        .u64_observable_gauge(prefixed(prefix, TOKIO_RUNTIME_TASKS_ALIVE))
+        .with_description("The number of tasks spawned on the runtime which did not complete.")
+        .with_unit(Unit::new("{task}"))
+        .init();
+    let global_queue_depth = meter
+        .u64_observable_gauge(prefixed(prefix, TOKIO_RUNTIME_GLOBAL_QUEUE_DEPTH))
+        .with_description("The number of tasks in the global queue of the runtime.")
+        .with_unit(Unit::new("{task}"))
+        .init();
+    #[allow(unused_mut)]
+    let mut instruments = vec![
+        workers.as_any(),
+        tasks_alive.as_any(),
+        global_queue_depth.as_any(),
+    ];
+
+    // Busy time and park count need 64 bit atomics in tokio.
+    #[cfg(target_has_atomic = "64")]
+    let worker_busy_time = meter
+        .f64_observable_counter(prefixed(prefix, TOKIO_RUNTIME_WORKER_BUSY_TIME))
+        .with_description("The time the worker thread spent polling tasks.")
+        .with_unit(Unit::new("s"))
+        .init();
+    #[cfg(target_has_atomic = "64")]
+    let worker_busy_ratio = meter
+        .f64_observable_gauge(prefixed(prefix, TOKIO_RUNTIME_WORKER_BUSY_RATIO))
+        .with_description(
+            "The share of the time since the previous collection the worker threads spent polling tasks, from 0 to 1.",
+        )
+        .with_unit(Unit::new("1"))
+        .init();
+    #[cfg(target_has_atomic = "64")]
+    let worker_park_count = meter
+        .u64_observable_counter(prefixed(prefix, TOKIO_RUNTIME_WORKER_PARK_COUNT))
+        .with_description("The number of times the worker thread parked for lack of tasks.")
+        .with_unit(Unit::new("{park}"))
+        .init();
+    #[cfg(target_has_atomic = "64")]
+    instruments.extend([
+        worker_busy_time.as_any(),
+        worker_busy_ratio.as_any(),
+        worker_park_count.as_any(),
+    ]);
+
+    // Total busy time of the workers at the previous collection.
+    #[cfg(target_has_atomic = "64")]
+    let previous: Mutex<Option<(Instant, Duration)>> = Mutex::new(None);
+
+    meter.register_callback(&instruments, move |context| {
+        let metrics = handle.metrics();
+        let num_workers = metrics.num_workers();
+        context.observe_u64(&workers, num_workers as u64, &attributes);
+        context.observe_u64(&tasks_alive, metrics.num_alive_tasks() as u64, &attributes);
+        context.observe_u64(
+            &global_queue_depth,
+            metrics.global_queue_depth() as u64,
+            &attributes,
+        );
+
+        #[cfg(target_has_atomic = "64")]
+        {
+            let mut busy = Duration::ZERO;
+            for worker in 0..num_workers {
+                let worker_attributes =
+                    [attributes.as_slice(), &[TOKIO_WORKER.i64(worker as i64)]].concat();
+                let worker_busy = metrics.worker_total_busy_duration(worker);
+                busy += worker_busy;
+                context.observe_f64(
+                    &worker_busy_time,
+                    worker_busy.as_secs_f64(),
+                    &worker_attributes,
+                );
+                context.observe_u64(
+                    &worker_park_count,
+                    metrics.worker_park_count(worker),
+                    &worker_attributes,
+                );
+            }
+
+            let now = Instant::now();
+            let mut previous = previous.lock().unwrap();
+            if let Some((last_time, last_busy)) = previous.replace((now, busy)) {
+                let elapsed = now.duration_since(last_time).as_secs_f64() * num_workers as f64;
+                if elapsed > 0.0 {
+                    let ratio = busy.saturating_sub(last_busy).as_secs_f64() / elapsed;
+                    context.observe_f64(&worker_busy_ratio, ratio.min(1.0), &attributes);
+                }
+            }
+        }
+    })?;
+    Ok(())
+}
@@ -0,0 +1,838 @@
+//! Sampling of the observed process, independent of the instruments.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt, UserExt};
+
+use crate::cpu::CpuTimes;
+use crate::gpu::{GpuProcessSample, GpuSampler};
+use crate::{GroupIntervals, MetricGroups};
+
+/// A source of process metrics, sysinfo unless another sampler is given
+/// with [`crate::ProcessObserverBuilder::with_sampler`].
+///
+/// Custom samplers let tests and unsupported platforms provide their own
+/// values, see [`crate::testing::MockSampler`].
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::{
+///     ProcessInfo, ProcessMetricsSnapshot, ProcessObserverBuilder, ProcessSampler,
+/// };
+///
+/// struct FixedSampler;
+///
+/// impl ProcessSampler for FixedSampler {
+///     fn pid(&self) -> u32 {
+///         42
+///     }
+///
+///     fn info(&mut self) -> Option<ProcessInfo> {
+///         Some(ProcessInfo {
+///             name: "fixed".to_string(),
+///             ..Default::default()
+///         })
+///     }
+///
+///     fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+///         Some(ProcessMetricsSnapshot {
+///             memory: 1024,
+///             ..Default::default()
+///         })
+///     }
+/// }
+///
+/// let meter = global::meter("process-meter");
+/// ProcessObserverBuilder::new(meter)
+///     .with_sampler(FixedSampler)
+///     .init();
+/// ```
+///
+pub trait ProcessSampler: Send {
+    /// PID of the sampled process, recorded as the `process.pid` attribute.
+    fn pid(&self) -> u32;
+
+    /// Identity of the process, read once when the observer is registered.
+    /// `None` if the process does not exist.
+    fn info(&mut self) -> Option<ProcessInfo>;
+
+    /// Usage of the process, `None` once it exited.
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot>;
+
+    /// CPU usage of the process alone, between two samples, see
+    /// [`crate::ProcessMetricsConfig::cpu_sampling_interval`]. `None` once
+    /// it exited. Defaults to a whole sample.
+    fn sample_cpu(&mut self) -> Option<f32> {
+        self.sample().map(|snapshot| snapshot.cpu_usage)
+    }
+
+    /// Called when the observer is registered and when its metric groups
+    /// change, see [`crate::ObserverHandle::set_metric_groups`]. The values
+    /// of disabled groups are not recorded, samplers may skip them.
+    fn set_metric_groups(&mut self, metric_groups: MetricGroups) {
+        let _ = metric_groups;
+    }
+
+    /// The groups read by the last sample, the values of the others are
+    /// those of a previous sample, see
+    /// [`crate::ProcessMetricsConfig::group_intervals`]. Defaults to every
+    /// group.
+    fn sampled_groups(&self) -> MetricGroups {
+        MetricGroups {
+            cpu: true,
+            memory: true,
+            disk: true,
+            gpu: true,
+            pressure: true,
+            network: true,
+        }
+    }
+}
+
+/// Identity of the observed process, recorded as attributes of every
+/// metric.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessInfo {
+    /// Recorded as the `process.executable.name` attribute.
+    pub name: String,
+    /// Recorded as the `process.executable.path` attribute.
+    pub executable_path: String,
+    /// Recorded as the `process.command` attribute, see
+    /// [`crate::CommandAttribute`].
+    pub command: Vec<String>,
+    /// Name of the user running the process, recorded as the
+    /// `process.owner` attribute. `None` if it cannot be resolved.
+    pub owner: Option<String>,
+    /// Recorded as the `process.user.id` attribute. Only available on Unix.
+    pub user_id: Option<u32>,
+    /// Recorded as the `process.group.id` attribute. Only available on
+    /// Unix.
+    pub group_id: Option<u32>,
+}
+
+/// Resource usage of the process at a point in time, see
+/// [`crate::sample_process_metrics`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProcessMetricsSnapshot {
+    /// Usage since the previous sample, 100 per fully used CPU.
+    pub cpu_usage: f32,
+    /// Time spent on the CPU since the process started. `None` when the
+    /// platform is not supported.
+    pub cpu_times: Option<CpuTimes>,
+    /// Number of CPUs the process is allowed to run on, from its affinity
+    /// mask. Only available on Linux.
+    pub allowed_cpus: Option<usize>,
+    /// Nice value, from -20, the highest priority, to 19. Only available on
+    /// Linux, macOS and FreeBSD.
+    pub nice: Option<i32>,
+    /// Priority class, `idle`, `below_normal`, `normal`, `above_normal`,
+    /// `high` or `realtime`. Only available on Windows.
+    pub priority_class: Option<&'static str>,
+    /// Resident memory, in bytes, or the physical footprint on macOS with
+    /// [`crate::ProcessMetricsConfig::memory_footprint`].
+    pub memory: u64,
+    /// Virtual memory, in bytes.
+    pub virtual_memory: u64,
+    /// Memory swapped out, in bytes. Only available on Linux.
+    pub swap: Option<u64>,
+    /// Details of the memory usage, only available on Linux.
+    pub memory_details: MemoryDetails,
+    /// Kernel handles open by the process. Only available on Windows.
+    pub handles: Option<u64>,
+    /// GDI objects, such as bitmaps and fonts, open by the process. Only
+    /// available on Windows.
+    pub gdi_objects: Option<u64>,
+    /// USER objects, such as windows and menus, open by the process. Only
+    /// available on Windows.
+    pub user_objects: Option<u64>,
+    /// Bytes read from disk since the process started.
+    pub disk_read: u64,
+    /// Bytes written to disk since the process started.
+    pub disk_written: u64,
+    /// Usage of every GPU device, see [`GpuProcessSample`].
+    pub gpus: Vec<GpuProcessSample>,
+    /// CPU times of the busiest threads since the previous sample, with
+    /// [`crate::ProcessMetricsConfig::thread_cpu_top`]. Only available on
+    /// Linux.
+    pub threads: Vec<ThreadCpuTimes>,
+    /// Time the process waited for resources since it started. Requires the
+    /// `taskstats` feature and the `CAP_NET_ADMIN` capability, only
+    /// available on Linux.
+    pub delays: Option<DelayTimes>,
+    /// Pressure stall information of the cgroup v2 of the process, with
+    /// the `pressure` group. Only available on Linux.
+    pub pressure: Vec<PressureStall>,
+    /// Sockets of the process, with the `network` group. Only available on
+    /// Linux and Windows.
+    pub connections: Vec<ConnectionCount>,
+    /// Growth of the resident memory, in bytes per minute, over
+    /// [`crate::ProcessMetricsConfig::memory_growth_window`]. A steady
+    /// positive rate hints at a memory leak. `None` until two samples were
+    /// taken; filled by the observer for samplers that leave it `None`.
+    pub memory_growth_rate: Option<f64>,
+    /// Bytes read from disk per second since the previous sample. `None`
+    /// for the first sample; filled by the observer for samplers that leave
+    /// it `None`.
+    pub disk_read_rate: Option<f64>,
+    /// Bytes written to disk per second since the previous sample, as
+    /// [`ProcessMetricsSnapshot::disk_read_rate`].
+    pub disk_write_rate: Option<f64>,
+    /// The process exited but its parent did not reap it yet. The other
+    /// values are then left empty, and only `process.alive` is recorded, as
+    /// 0 with a `process.state` attribute of `defunct`.
+    pub zombie: bool,
+}
+
+/// Details of the memory usage of the process, from `/proc/<pid>/status`.
+/// Every field is `None` on other platforms.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MemoryDetails {
+    /// Resident memory shared with other processes, such as shared libraries
+    /// and shared memory, in bytes.
+    pub shared: Option<u64>,
+    /// Resident anonymous memory, such as the heap and stacks, in bytes.
+    pub anonymous: Option<u64>,
+    /// Proportional set size: the resident memory, with pages shared by
+    /// several processes divided among them, in bytes. Requires the `smaps`
+    /// feature.
+    pub pss: Option<u64>,
+    /// Unique set size: the resident memory not shared with any other
+    /// process, in bytes. Requires the `smaps` feature.
+    pub uss: Option<u64>,
+    /// Highest resident memory since the process started, in bytes.
+    pub peak: Option<u64>,
+    /// Highest virtual memory since the process started, in bytes.
+    pub virtual_peak: Option<u64>,
+    /// Memory in huge pages of hugetlbfs, in bytes.
+    pub hugepages: Option<u64>,
+    /// Memory locked in RAM, with `mlock` for instance, in bytes.
+    pub locked: Option<u64>,
+    /// Resident memory on every NUMA node, from `/proc/<pid>/numa_maps`.
+    /// Requires the `numa` feature.
+    pub numa_nodes: Vec<NumaNodeMemory>,
+    /// Badness of the process for the OOM killer, from 0 to 1000 or more
+    /// with `oom_score_adj`. The process with the highest score is killed
+    /// first.
+    pub oom_score: Option<u64>,
+    /// Adjustment of `oom_score`, from -1000, never killed, to 1000.
+    pub oom_score_adj: Option<i64>,
+    /// Processes killed by the OOM killer in the cgroup of the process since
+    /// it was created, from `memory.events`. Only available with cgroup v2.
+    pub cgroup_oom_kills: Option<u64>,
+}
+
+/// Time spent by a thread of the process on the CPU since it started.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ThreadCpuTimes {
+    /// Recorded as the `thread.id` attribute.
+    pub id: u32,
+    /// Recorded as the `thread.name` attribute.
+    pub name: String,
+    pub cpu_times: CpuTimes,
+}
+
+/// Time the threads of the process spent waiting for a resource, from the
+/// delay accounting of the kernel, see
+/// <https://docs.kernel.org/accounting/delay-accounting.html>. Every delay
+/// stays at zero unless delay accounting is enabled.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DelayTimes {
+    /// Waiting on the run queue for a CPU.
+    pub cpu: Duration,
+    /// Waiting for block I/O to complete.
+    pub block_io: Duration,
+    /// Waiting for pages to be swapped in.
+    pub swap_in: Duration,
+}
+
+/// Time some or all tasks were stalled waiting for a resource, see
+/// <https://docs.kernel.org/accounting/psi.html>.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PressureStall {
+    /// `cpu`, `memory` or `io`.
+    pub resource: &'static str,
+    /// `some` when at least one task was stalled, `full` when all of them
+    /// were at the same time.
+    pub kind: &'static str,
+    /// Percentage of the last 10 seconds the tasks were stalled.
+    pub avg10: f64,
+    /// Time the tasks were stalled since the start of the host or the
+    /// creation of the cgroup.
+    pub total: Duration,
+}
+
+/// Number of sockets of the process with the same protocol and state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConnectionCount {
+    /// `tcp` or `udp`, recorded as the `network.transport` attribute.
+    pub protocol: &'static str,
+    /// State of TCP sockets, such as `established`, `close_wait` or
+    /// `listen`, recorded as the `network.connection.state` attribute.
+    pub state: Option<&'static str>,
+    pub count: u64,
+}
+
+/// Resident memory of the process on a NUMA node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NumaNodeMemory {
+    /// Index of the node.
+    pub node: u32,
+    /// Memory on the node, in bytes.
+    pub bytes: u64,
+}
+
+/// The fields sampled on every refresh. sysinfo always refreshes the memory
+/// usage, and only reads the command line, environment and executable once,
+/// when it first sees the process.
+#[cfg(not(all(feature = "procfs", target_os = "linux")))]
+fn refresh_kind(cpu: bool, disk: bool) -> ProcessRefreshKind {
+    let mut refresh_kind = ProcessRefreshKind::new();
+    if cpu {
+        refresh_kind = refresh_kind.with_cpu();
+    }
+    if disk {
+        refresh_kind = refresh_kind.with_disk_usage();
+    }
+    refresh_kind
+}
+
+/// Time between the warm up of a sampler and its first sample for the CPU
+/// usage to be meaningful. Loops should wait for it before the first sample,
+/// otherwise the sampler blocks until then.
+pub(crate) const WARM_UP: Duration = System::MINIMUM_CPU_UPDATE_INTERVAL;
+
+/// GPU samplers, only created once the GPU group is enabled as creating
+/// them loads the drivers.
+pub(crate) enum GpuSamplers {
+    Pending(Box<dyn FnOnce() -> Vec<Box<dyn GpuSampler>> + Send>),
+    Ready(Vec<Box<dyn GpuSampler>>),
+}
+
+impl GpuSamplers {
+    fn create(&mut self) {
+        let pending = std::mem::replace(self, GpuSamplers::Ready(Vec::new()));
+        *self = match pending {
+            GpuSamplers::Pending(create) => GpuSamplers::Ready(create()),
+            ready => ready,
+        };
+    }
+}
+
+/// Samples a single process with sysinfo and the GPU samplers.
+pub(crate) struct SysinfoSampler {
+    pid: Pid,
+    // Shared by the observers of a `SamplerRegistry`.
+    sys: Arc<Mutex<System>>,
+    gpu_samplers: GpuSamplers,
+    metric_groups: MetricGroups,
+    // Total CPU time at the previous sample. The CPU usage computed by
+    // sysinfo covers the time since the previous refresh of any process of
+    // the shared system, so it is only used without CPU times.
+    last_cpu_time: Option<(f64, Instant)>,
+    // Time of the CPU refresh done when the sampler was created, `None`
+    // once the first sample was taken.
+    warm_up: Option<Instant>,
+    group_intervals: GroupIntervals,
+    // The previous sample, and when every group was last sampled, to record
+    // again the groups that are not due.
+    previous: Option<ProcessMetricsSnapshot>,
+    sampled: GroupTimes,
+    // The groups due at the last sample.
+    sampled_groups: MetricGroups,
+    // Number of samples which found a zombie.
+    zombie_samples: u32,
+    // Number of threads sampled, and their total CPU time at the previous
+    // sample to find the busiest ones.
+    thread_cpu_top: Option<usize>,
+    #[cfg(target_os = "linux")]
+    thread_cpu_times: std::collections::HashMap<u32, f64>,
+    memory_footprint: bool,
+    // `None` if the netlink socket could not be opened.
+    #[cfg(all(feature = "taskstats", target_os = "linux"))]
+    taskstats: Option<crate::taskstats::Taskstats>,
+}
+
+/// Time of the last sample of every group.
+#[derive(Default)]
+struct GroupTimes {
+    cpu: Option<Instant>,
+    memory: Option<Instant>,
+    disk: Option<Instant>,
+    gpu: Option<Instant>,
+    pressure: Option<Instant>,
+    network: Option<Instant>,
+}
+
+/// Whether a group sampled at `last` should be sampled again at `now`.
+fn due(interval: Option<Duration>, last: Option<Instant>, now: Instant) -> bool {
+    match (interval, last) {
+        (Some(interval), Some(last)) => now.duration_since(last) >= interval,
+        _ => true,
+    }
+}
+
+impl SysinfoSampler {
+    pub(crate) fn new(pid: Pid, sys: Arc<Mutex<System>>, gpu_samplers: GpuSamplers) -> Self {
+        let mut sampler = Self {
+            pid,
+            sys,
+            gpu_samplers,
+            metric_groups: MetricGroups::default(),
+            last_cpu_time: None,
+            warm_up: None,
+            group_intervals: GroupIntervals::default(),
+            previous: None,
+            sampled: GroupTimes::default(),
+            sampled_groups: MetricGroups::default(),
+            zombie_samples: 0,
+            thread_cpu_top: None,
+            #[cfg(target_os = "linux")]
+            thread_cpu_times: Default::default(),
+            memory_footprint: false,
+            #[cfg(all(feature = "taskstats", target_os = "linux"))]
+            taskstats: crate::taskstats::Taskstats::open(),
+        };
+        sampler.warm_up();
+        sampler
+    }
+
+    /// Read the CPU usage once, so that the first sample reports the usage
+    /// since the creation of the sampler instead of nothing.
+    fn warm_up(&mut self) {
+        match CpuTimes::read(self.pid.as_u32()) {
+            Some(cpu_times) => {
+                self.last_cpu_time = Some((cpu_times.user + cpu_times.system, Instant::now()));
+            }
+            None => {
+                self.sys
+                    .lock()
+                    .unwrap()
+                    .refresh_process_specifics(self.pid, ProcessRefreshKind::new().with_cpu());
+            }
+        }
+        self.warm_up = Some(Instant::now());
+    }
+
+    pub(crate) fn with_group_intervals(mut self, group_intervals: GroupIntervals) -> Self {
+        self.group_intervals = group_intervals;
+        self
+    }
+
+    pub(crate) fn with_thread_cpu_top(mut self, thread_cpu_top: Option<usize>) -> Self {
+        self.thread_cpu_top = thread_cpu_top;
+        self
+    }
+
+    pub(crate) fn with_memory_footprint(mut self, memory_footprint: bool) -> Self {
+        self.memory_footprint = memory_footprint;
+        self
+    }
+
+    /// The `top` threads which used the most CPU since the previous sample.
+    #[cfg(target_os = "linux")]
+    fn busiest_threads(&mut self, top: usize) -> Vec<ThreadCpuTimes> {
+        let threads = crate::linux::thread_cpu_times(self.pid.as_u32());
+        let previous = std::mem::replace(
+            &mut self.thread_cpu_times,
+            threads
+                .iter()
+                .map(|thread| (thread.id, thread.cpu_times.user + thread.cpu_times.system))
+                .collect(),
+        );
+        let mut busiest: Vec<(f64, ThreadCpuTimes)> = threads
+            .into_iter()
+            .map(|thread| {
+                let total = thread.cpu_times.user + thread.cpu_times.system;
+                (total - previous.get(&thread.id).unwrap_or(&0.0), thread)
+            })
+            .collect();
+        busiest.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        busiest
+            .into_iter()
+            .take(top)
+            .map(|(_, thread)| thread)
+            .collect()
+    }
+
+    /// The enabled groups due at `now`, see [`GroupIntervals`].
+    fn due_groups(&self, now: Instant) -> MetricGroups {
+        let enabled = self.metric_groups;
+        let intervals = self.group_intervals;
+        let sampled = &self.sampled;
+        MetricGroups {
+            cpu: enabled.cpu && due(intervals.cpu, sampled.cpu, now),
+            memory: enabled.memory && due(intervals.memory, sampled.memory, now),
+            disk: enabled.disk && due(intervals.disk, sampled.disk, now),
+            gpu: enabled.gpu && due(intervals.gpu, sampled.gpu, now),
+            pressure: enabled.pressure && due(intervals.pressure, sampled.pressure, now),
+            network: enabled.network && due(intervals.network, sampled.network, now),
+        }
+    }
+
+    /// A zombie exited but was not reaped by its parent yet, its last
+    /// values would be reported as long as it is not: only its state is.
+    /// Children are zombies until their parent waits for them, which is
+    /// only worth a warning once it did not for a whole interval.
+    fn zombie(&mut self) -> Option<ProcessMetricsSnapshot> {
+        self.zombie_samples = self.zombie_samples.saturating_add(1);
+        match self.zombie_samples {
+            1 => tracing::debug!("Process {} is a zombie", self.pid),
+            2 => tracing::warn!(
+                "Process {} is still a zombie, its parent did not reap it",
+                self.pid
+            ),
+            _ => {}
+        }
+        Some(ProcessMetricsSnapshot {
+            zombie: true,
+            ..Default::default()
+        })
+    }
+
+    /// Wait for the end of the warm up, once.
+    fn finish_warm_up(&mut self) {
+        if let Some(warm_up) = self.warm_up.take() {
+            std::thread::sleep(WARM_UP.saturating_sub(warm_up.elapsed()));
+        }
+    }
+
+    /// CPU usage since the previous sample, from the total CPU time.
+    fn cpu_usage(&mut self, cpu_times: CpuTimes) -> f32 {
+        let now = Instant::now();
+        let total = cpu_times.user + cpu_times.system;
+        // Without warm up, the first sample has nothing to compare with.
+        match self.last_cpu_time.replace((total, now)) {
+            Some((last_total, last_time)) => {
+                let elapsed = now.duration_since(last_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    ((total - last_total).max(0.0) / elapsed * 100.0) as f32
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+}
+
+impl ProcessSampler for SysinfoSampler {
+    fn pid(&self) -> u32 {
+        self.pid.as_u32()
+    }
+
+    fn info(&mut self) -> Option<ProcessInfo> {
+        let mut sys = self.sys.lock().unwrap();
+        // Read once per observer, the users are not refreshed otherwise.
+        sys.refresh_users_list();
+        sys.refresh_process_specifics(self.pid, ProcessRefreshKind::new().with_user());
+        let process = sys.process(self.pid)?;
+        let owner = process
+            .user_id()
+            .and_then(|uid| sys.get_user_by_id(uid))
+            .map(|user| user.name().to_string());
+        #[cfg(unix)]
+        let (user_id, group_id) = (
+            process.user_id().map(|uid| **uid),
+            process.group_id().map(|gid| *gid),
+        );
+        #[cfg(not(unix))]
+        let (user_id, group_id) = (None, None);
+        Some(ProcessInfo {
+            name: process.name().to_string(),
+            executable_path: process.exe().to_string_lossy().to_string(),
+            command: process.cmd().to_vec(),
+            owner,
+            user_id,
+            group_id,
+        })
+    }
+
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+        self.finish_warm_up();
+
+        let pid = self.pid.as_u32();
+        let now = Instant::now();
+        let groups = self.due_groups(now);
+        let cpu_times = groups.cpu.then(|| CpuTimes::read(pid)).flatten();
+        // sysinfo keeps returning the last values of zombies.
+        #[cfg(target_os = "linux")]
+        if crate::linux::ProcStat::read(pid).is_some_and(|stat| stat.state == 'Z') {
+            return self.zombie();
+        }
+        #[cfg(all(feature = "procfs", target_os = "linux"))]
+        let Some(mut snapshot) = crate::linux::sample_procfs(pid, groups.disk) else {
+            tracing::debug!("Could not read /proc/{pid}");
+            return None;
+        };
+        #[cfg(not(all(feature = "procfs", target_os = "linux")))]
+        let mut snapshot = {
+            let mut sys = self.sys.lock().unwrap();
+            let refresh_kind = refresh_kind(groups.cpu && cpu_times.is_none(), groups.disk);
+            if !sys.refresh_process_specifics(self.pid, refresh_kind) {
+                tracing::debug!("sysinfo could not refresh process {}", self.pid);
+                return None;
+            }
+            let process = sys.process(self.pid)?;
+            #[cfg(not(target_os = "linux"))]
+            if process.status() == sysinfo::ProcessStatus::Zombie {
+                drop(sys);
+                return self.zombie();
+            }
+            let disk_usage = process.disk_usage();
+            ProcessMetricsSnapshot {
+                cpu_usage: process.cpu_usage(),
+                memory: process.memory(),
+                virtual_memory: process.virtual_memory(),
+                disk_read: disk_usage.total_read_bytes,
+                disk_written: disk_usage.total_written_bytes,
+                ..Default::default()
+            }
+        };
+
+        if let Some(cpu_times) = cpu_times {
+            snapshot.cpu_usage = self.cpu_usage(cpu_times);
+            snapshot.cpu_times = Some(cpu_times);
+        }
+        #[cfg(target_os = "linux")]
+        if let (true, Some(top)) = (groups.cpu, self.thread_cpu_top) {
+            snapshot.threads = self.busiest_threads(top);
+        }
+        if groups.cpu {
+            snapshot.nice = crate::priority::nice(pid);
+            snapshot.priority_class = crate::priority::priority_class(pid);
+        }
+        #[cfg(target_os = "linux")]
+        if groups.cpu {
+            snapshot.allowed_cpus = crate::linux::allowed_cpus(pid);
+        }
+        #[cfg(all(feature = "taskstats", target_os = "linux"))]
+        if let (true, Some(taskstats)) = (groups.cpu, &mut self.taskstats) {
+            snapshot.delays = taskstats.delays(pid);
+        }
+        #[cfg(target_os = "linux")]
+        if groups.memory {
+            if let Some(status) = crate::linux::ProcStatus::read(pid) {
+                snapshot.swap = status.bytes("VmSwap");
+                snapshot.memory_details = status.memory_details();
+            }
+            let details = &mut snapshot.memory_details;
+            details.oom_score = crate::linux::read_value(pid, "oom_score");
+            details.oom_score_adj = crate::linux::read_value(pid, "oom_score_adj");
+            details.cgroup_oom_kills = crate::linux::cgroup_oom_kills(pid);
+            #[cfg(feature = "smaps")]
+            if let Some(smaps) = crate::linux::ProcStatus::read_smaps_rollup(pid) {
+                let (pss, uss) = smaps.pss_uss();
+                snapshot.memory_details.pss = pss;
+                snapshot.memory_details.uss = uss;
+            }
+            #[cfg(feature = "numa")]
+            if let Some(numa_nodes) = crate::linux::numa_memory(pid) {
+                snapshot.memory_details.numa_nodes = numa_nodes;
+            }
+        }
+        #[cfg(target_os = "macos")]
+        if groups.memory && self.memory_footprint {
+            if let Some(info) = crate::darwin::TaskVmInfo::read(pid) {
+                snapshot.memory = info.phys_footprint;
+            }
+        }
+        #[cfg(windows)]
+        if groups.memory {
+            if let Some((handles, gdi_objects, user_objects)) = crate::windows::handle_counts(pid) {
+                snapshot.handles = Some(handles);
+                snapshot.gdi_objects = Some(gdi_objects);
+                snapshot.user_objects = Some(user_objects);
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if groups.pressure {
+            snapshot.pressure = crate::linux::cgroup_pressure(pid);
+        }
+        if groups.network {
+            snapshot.connections = crate::network::connections(pid);
+        }
+        if let (true, GpuSamplers::Ready(gpu_samplers)) = (groups.gpu, &self.gpu_samplers) {
+            snapshot.gpus = gpu_samplers
+                .iter()
+                .flat_map(|sampler| sampler.sample(pid))
+                .collect();
+        }
+
+        // Record the previous values of the groups that are not due.
+        if let Some(previous) = self.previous.take() {
+            if !groups.cpu {
+                snapshot.cpu_usage = previous.cpu_usage;
+                snapshot.cpu_times = previous.cpu_times;
+                snapshot.allowed_cpus = previous.allowed_cpus;
+                snapshot.nice = previous.nice;
+                snapshot.priority_class = previous.priority_class;
+                snapshot.threads = previous.threads;
+                snapshot.delays = previous.delays;
+            }
+            if !groups.memory {
+                snapshot.memory = previous.memory;
+                snapshot.virtual_memory = previous.virtual_memory;
+                snapshot.swap = previous.swap;
+                snapshot.memory_details = previous.memory_details;
+                snapshot.handles = previous.handles;
+                snapshot.gdi_objects = previous.gdi_objects;
+                snapshot.user_objects = previous.user_objects;
+            }
+            if !groups.disk {
+                snapshot.disk_read = previous.disk_read;
+                snapshot.disk_written = previous.disk_written;
+            }
+            if !groups.gpu {
+                snapshot.gpus = previous.gpus;
+            }
+            if !groups.pressure {
+                snapshot.pressure = previous.pressure;
+            }
+            if !groups.network {
+                snapshot.connections = previous.connections;
+            }
+        }
+        for (sampled, last) in [
+            (groups.cpu, &mut self.sampled.cpu),
+            (groups.memory, &mut self.sampled.memory),
+            (groups.disk, &mut self.sampled.disk),
+            (groups.gpu, &mut self.sampled.gpu),
+            (groups.pressure, &mut self.sampled.pressure),
+            (groups.network, &mut self.sampled.network),
+        ] {
+            if sampled {
+                *last = Some(now);
+            }
+        }
+        self.sampled_groups = groups;
+        if self.group_intervals != GroupIntervals::default() {
+            self.previous = Some(snapshot.clone());
+        }
+        Some(snapshot)
+    }
+
+    fn sample_cpu(&mut self) -> Option<f32> {
+        self.finish_warm_up();
+        match CpuTimes::read(self.pid.as_u32()) {
+            Some(cpu_times) => Some(self.cpu_usage(cpu_times)),
+            None => {
+                let mut sys = self.sys.lock().unwrap();
+                if !sys.refresh_process_specifics(self.pid, ProcessRefreshKind::new().with_cpu()) {
+                    return None;
+                }
+                sys.process(self.pid).map(|process| process.cpu_usage())
+            }
+        }
+    }
+
+    fn set_metric_groups(&mut self, metric_groups: MetricGroups) {
+        self.metric_groups = metric_groups;
+        // The samplers are kept while the group is disabled, for it to be
+        // enabled again.
+        if metric_groups.gpu {
+            self.gpu_samplers.create();
+        }
+    }
+
+    fn sampled_groups(&self) -> MetricGroups {
+        self.sampled_groups
+    }
+}
+
+/// Calls the callbacks of [`crate::ProcessObserverBuilder::on_sample`] with
+/// every sample of the wrapped sampler.
+pub(crate) struct WithCallbacks {
+    pub(crate) sampler: Box<dyn ProcessSampler>,
+    pub(crate) callbacks: Vec<crate::SampleCallback>,
+}
+
+impl ProcessSampler for WithCallbacks {
+    fn pid(&self) -> u32 {
+        self.sampler.pid()
+    }
+
+    fn info(&mut self) -> Option<ProcessInfo> {
+        self.sampler.info()
+    }
+
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+        let snapshot = self.sampler.sample()?;
+        if snapshot.zombie {
+            return Some(snapshot);
+        }
+        for callback in &mut self.callbacks {
+            callback(&snapshot);
+        }
+        Some(snapshot)
+    }
+
+    // Not a sample of every metric, the callbacks are not called.
+    fn sample_cpu(&mut self) -> Option<f32> {
+        self.sampler.sample_cpu()
+    }
+
+    fn set_metric_groups(&mut self, metric_groups: MetricGroups) {
+        self.sampler.set_metric_groups(metric_groups);
+    }
+
+    fn sampled_groups(&self) -> MetricGroups {
+        self.sampler.sampled_groups()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeGpu;
+
+    impl GpuSampler for FakeGpu {
+        fn sample(&self, _pid: u32) -> Vec<GpuProcessSample> {
+            vec![GpuProcessSample {
+                vendor: "fake",
+                memory_used: Some(1),
+                ..Default::default()
+            }]
+        }
+    }
+
+    #[test]
+    fn gpu_group_toggled_off_and_on() {
+        let pid = sysinfo::get_current_pid().unwrap();
+        let gpu_samplers = GpuSamplers::Pending(Box::new(|| vec![Box::new(FakeGpu) as _]));
+        let mut sampler = SysinfoSampler::new(pid, Arc::default(), gpu_samplers);
+        let groups = MetricGroups::default();
+
+        for (gpu, sampled) in [(false, 0), (true, 1), (false, 0), (true, 1)] {
+            sampler.set_metric_groups(MetricGroups { gpu, ..groups });
+            let snapshot = sampler.sample().unwrap();
+            assert_eq!(snapshot.gpus.len(), sampled, "gpu group enabled: {gpu}");
+        }
+    }
+
+    #[test]
+    fn slower_groups_are_not_sampled_until_due() {
+        let pid = sysinfo::get_current_pid().unwrap();
+        let mut sampler = SysinfoSampler::new(pid, Arc::default(), GpuSamplers::Ready(Vec::new()))
+            .with_group_intervals(GroupIntervals {
+                disk: Some(Duration::from_secs(3600)),
+                ..Default::default()
+            });
+        sampler.set_metric_groups(MetricGroups::default());
+
+        for (sample, disk) in [(0, true), (1, false), (2, false)] {
+            sampler.sample().unwrap();
+            let sampled = sampler.sampled_groups();
+            assert_eq!((sampled.cpu, sampled.disk), (true, disk), "sample {sample}");
+        }
+    }
+}
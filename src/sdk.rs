@@ -0,0 +1,196 @@
+//! Recommended configuration of the OpenTelemetry SDK for the metrics of
+//! this crate.
+
+use std::time::Duration;
+
+use opentelemetry::metrics::Unit;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::{Aggregation, Instrument, InstrumentKind, Stream, View};
+use opentelemetry_sdk::resource::ResourceDetector;
+use opentelemetry_sdk::Resource;
+use opentelemetry_semantic_conventions::resource::{
+    HOST_ARCH, HOST_NAME, OS_DESCRIPTION, OS_TYPE, OS_VERSION, PROCESS_RUNTIME_NAME,
+};
+use sysinfo::{System, SystemExt};
+
+use crate::{
+    PROCESS_CPU_USAGE, PROCESS_CPU_UTILIZATION, PROCESS_EXECUTABLE_NAME, PROCESS_EXECUTABLE_PATH,
+    PROCESS_PID,
+};
+
+/// Views to register on the meter provider along with the observers:
+/// - the `byte` unit of the instruments of this crate is replaced with
+///   `By`, the UCUM unit expected by the OpenTelemetry semantic conventions
+/// - the histogram of [`crate::ProcessMetricsConfig::cpu_histogram`] gets
+///   bucket boundaries suited to CPU usage, instead of the default ones
+///   which go up to 10000
+///
+/// Requires the `sdk` feature.
+/// # Example
+///
+/// ```
+/// use opentelemetry_sdk::metrics::{ManualReader, SdkMeterProvider};
+/// use opentelemetry_system_metrics::recommended_views;
+///
+/// let mut builder = SdkMeterProvider::builder().with_reader(ManualReader::builder().build());
+/// for view in recommended_views() {
+///     builder = builder.with_view(view);
+/// }
+/// let provider = builder.build();
+/// ```
+///
+pub fn recommended_views() -> Vec<Box<dyn View>> {
+    vec![Box::new(byte_unit), Box::new(cpu_histogram)]
+}
+
+/// The name of an instrument of this crate without the
+/// [`crate::ProcessMetricsConfig::metric_prefix`], recognized by the
+/// namespaces of the names. `None` for other instruments.
+fn own_name(instrument: &Instrument) -> Option<&str> {
+    let name = &*instrument.name;
+    ["process.", "system.", "gpu."]
+        .iter()
+        .find_map(|namespace| {
+            let start = name.find(namespace)?;
+            (start == 0 || name[..start].ends_with('.')).then(|| &name[start..])
+        })
+}
+
+/// `By` instead of `byte`.
+fn byte_unit(instrument: &Instrument) -> Option<Stream> {
+    (own_name(instrument).is_some() && instrument.unit.as_str() == "byte")
+        .then(|| stream(instrument).unit(Unit::new("By")))
+}
+
+/// Bucket boundaries for ratios of the CPUs, and for percentages of a
+/// single CPU with [`crate::CpuNormalization::None`] or of the physical
+/// cores by default.
+fn cpu_histogram(instrument: &Instrument) -> Option<Stream> {
+    if instrument.kind != Some(InstrumentKind::Histogram)
+        || !own_name(instrument)
+            .is_some_and(|name| [PROCESS_CPU_USAGE, PROCESS_CPU_UTILIZATION].contains(&name))
+    {
+        return None;
+    }
+    let boundaries = match instrument.unit.as_str() {
+        "1" => vec![0.05, 0.1, 0.25, 0.5, 0.75, 0.9, 0.95, 1.0],
+        // Percentages without unit with the default normalization.
+        "%" | "" => vec![
+            5.0, 10.0, 25.0, 50.0, 75.0, 100.0, 200.0, 400.0, 800.0, 1600.0,
+        ],
+        _ => return None,
+    };
+    Some(
+        stream(instrument).aggregation(Aggregation::ExplicitBucketHistogram {
+            boundaries,
+            record_min_max: true,
+        }),
+    )
+}
+
+/// The stream of the instrument, unchanged.
+fn stream(instrument: &Instrument) -> Stream {
+    Stream::new()
+        .name(instrument.name.clone())
+        .description(instrument.description.clone())
+        .unit(instrument.unit.clone())
+}
+
+/// The default resource of the SDK, with the attributes of the
+/// [`HostResourceDetector`] and the `process.pid`, `process.executable.name`
+/// and `process.executable.path` attributes of the current process added.
+/// The attributes of the default resource, such as the ones of
+/// `OTEL_RESOURCE_ATTRIBUTES`, take precedence.
+///
+/// Requires the `sdk` feature.
+/// # Example
+///
+/// ```
+/// use opentelemetry_sdk::metrics::{ManualReader, SdkMeterProvider};
+/// use opentelemetry_system_metrics::process_resource;
+///
+/// let provider = SdkMeterProvider::builder()
+///     .with_reader(ManualReader::builder().build())
+///     .with_resource(process_resource())
+///     .build();
+/// ```
+///
+pub fn process_resource() -> Resource {
+    let mut attributes = vec![PROCESS_PID.i64(std::process::id().into())];
+    if let Ok(path) = std::env::current_exe() {
+        if let Some(name) = path.file_name() {
+            attributes.push(PROCESS_EXECUTABLE_NAME.string(name.to_string_lossy().into_owned()));
+        }
+        attributes.push(PROCESS_EXECUTABLE_PATH.string(path.to_string_lossy().into_owned()));
+    }
+    HostResourceDetector
+        .detect(Duration::ZERO)
+        .merge(&Resource::new(attributes))
+        .merge(&Resource::default())
+}
+
+/// Detects the `host.name`, `host.arch`, `os.type`, `os.description`,
+/// `os.version` and `process.runtime.name` resource attributes, from
+/// sysinfo and the target of the build. Rust has no runtime whose version
+/// could be read, so `process.runtime.version` is left out.
+///
+/// Works without any observer. Requires the `sdk` feature.
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use opentelemetry_sdk::Resource;
+/// use opentelemetry_system_metrics::HostResourceDetector;
+///
+/// let resource = Resource::from_detectors(
+///     Duration::from_secs(1),
+///     vec![Box::new(HostResourceDetector)],
+/// );
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostResourceDetector;
+
+impl ResourceDetector for HostResourceDetector {
+    fn detect(&self, _timeout: Duration) -> Resource {
+        let mut attributes = vec![
+            KeyValue::new(HOST_ARCH, host_arch()),
+            KeyValue::new(OS_TYPE, os_type()),
+            KeyValue::new(PROCESS_RUNTIME_NAME, "rust"),
+        ];
+        // Nothing is refreshed, the values are read on every call.
+        let sys = System::new();
+        if let Some(host_name) = sys.host_name() {
+            attributes.push(KeyValue::new(HOST_NAME, host_name));
+        }
+        if let Some(description) = sys.long_os_version() {
+            attributes.push(KeyValue::new(OS_DESCRIPTION, description));
+        }
+        if let Some(version) = sys.os_version() {
+            attributes.push(KeyValue::new(OS_VERSION, version));
+        }
+        Resource::new(attributes)
+    }
+}
+
+/// The `host.arch` value of the semantic conventions.
+fn host_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "arm" => "arm32",
+        "powerpc" => "ppc32",
+        "powerpc64" => "ppc64",
+        arch => arch,
+    }
+}
+
+/// The `os.type` value of the semantic conventions.
+fn os_type() -> &'static str {
+    match std::env::consts::OS {
+        "macos" | "ios" => "darwin",
+        "dragonfly" => "dragonflybsd",
+        os => os,
+    }
+}
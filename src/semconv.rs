@@ -0,0 +1,153 @@
+//! Instruments following the OpenTelemetry semantic conventions for process
+//! metrics, see
+//! <https://opentelemetry.io/docs/specs/semconv/system/process-metrics/>.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::instruments::{Overflow, ProcessInstruments};
+use crate::sampler::ProcessMetricsSnapshot;
+use crate::{prefixed, MetricGroups, OnOverflow};
+use opentelemetry::metrics::{
+    Histogram, Meter, ObservableCounter, ObservableGauge, ObservableUpDownCounter, Observer, Unit,
+};
+use opentelemetry::{Key, KeyValue};
+use opentelemetry_semantic_conventions::resource::DISK_IO_DIRECTION;
+
+// The semantic conventions crate only defines attributes. `process.cpu.time`
+// is recorded in both modes.
+const PROCESS_CPU_UTILIZATION: &str = "process.cpu.utilization";
+const PROCESS_MEMORY_USAGE: &str = "process.memory.usage";
+const PROCESS_MEMORY_VIRTUAL: &str = "process.memory.virtual";
+const PROCESS_DISK_IO: &str = "process.disk.io";
+pub(crate) const DIRECTION: Key = Key::from_static_str(DISK_IO_DIRECTION);
+const CPU_UTILIZATION_DESCRIPTION: &str = "Difference in process.cpu.time since the last measurement, divided by the elapsed time and number of CPUs available to the process.";
+
+pub(crate) struct SemconvInstruments {
+    // `None` reports the ratio of a single CPU.
+    cpu_count: Option<usize>,
+    // `None` for disabled groups.
+    cpu_utilization: Option<ObservableGauge<f64>>,
+    // Instead of `cpu_utilization`, with `ProcessMetricsConfig::cpu_histogram`.
+    cpu_utilization_histogram: Option<Histogram<f64>>,
+    memory_usage: Option<ObservableUpDownCounter<i64>>,
+    memory_virtual: Option<ObservableUpDownCounter<i64>>,
+    disk_io: Option<ObservableCounter<u64>>,
+    overflow: Overflow,
+}
+
+impl SemconvInstruments {
+    pub(crate) fn new(
+        meter: &Meter,
+        cpu_count: Option<usize>,
+        groups: MetricGroups,
+        cpu_histogram: bool,
+        prefix: &str,
+        on_overflow: OnOverflow,
+    ) -> Self {
+        Self {
+            cpu_count,
+            cpu_utilization: (groups.cpu && !cpu_histogram).then(|| {
+                meter
+                    .f64_observable_gauge(prefixed(prefix, PROCESS_CPU_UTILIZATION))
+                    .with_description(CPU_UTILIZATION_DESCRIPTION)
+                    .with_unit(Unit::new("1"))
+                    .init()
+            }),
+            cpu_utilization_histogram: (groups.cpu && cpu_histogram).then(|| {
+                meter
+                    .f64_histogram(prefixed(prefix, PROCESS_CPU_UTILIZATION))
+                    .with_description(CPU_UTILIZATION_DESCRIPTION)
+                    .with_unit(Unit::new("1"))
+                    .init()
+            }),
+            memory_usage: groups.memory.then(|| {
+                meter
+                    .i64_observable_up_down_counter(prefixed(prefix, PROCESS_MEMORY_USAGE))
+                    .with_description("The amount of physical memory in use.")
+                    .with_unit(Unit::new("By"))
+                    .init()
+            }),
+            memory_virtual: groups.memory.then(|| {
+                meter
+                    .i64_observable_up_down_counter(prefixed(prefix, PROCESS_MEMORY_VIRTUAL))
+                    .with_description("The amount of committed virtual memory.")
+                    .with_unit(Unit::new("By"))
+                    .init()
+            }),
+            disk_io: groups.disk.then(|| {
+                meter
+                    .u64_observable_counter(prefixed(prefix, PROCESS_DISK_IO))
+                    .with_description("Disk bytes transferred.")
+                    .with_unit(Unit::new("By"))
+                    .init()
+            }),
+            overflow: Overflow::new(on_overflow),
+        }
+    }
+
+    fn cpu_utilization(&self, cpu_usage: f32) -> f64 {
+        // sysinfo reports 100 per fully used CPU.
+        f64::from(cpu_usage) / 100.0 / self.cpu_count.unwrap_or(1) as f64
+    }
+}
+
+impl ProcessInstruments for SemconvInstruments {
+    fn instruments(&self) -> Vec<Arc<dyn Any>> {
+        [
+            self.cpu_utilization.as_ref().map(|i| i.as_any()),
+            self.memory_usage.as_ref().map(|i| i.as_any()),
+            self.memory_virtual.as_ref().map(|i| i.as_any()),
+            self.disk_io.as_ref().map(|i| i.as_any()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn observe(
+        &self,
+        observer: &dyn Observer,
+        snapshot: &ProcessMetricsSnapshot,
+        attributes: &[KeyValue],
+    ) {
+        if let Some(instrument) = &self.cpu_utilization {
+            observer.observe_f64(
+                instrument,
+                self.cpu_utilization(snapshot.cpu_usage),
+                attributes,
+            );
+        }
+        if let Some(instrument) = &self.memory_usage {
+            if let Some(memory) = self.overflow.to_i64(PROCESS_MEMORY_USAGE, snapshot.memory) {
+                observer.observe_i64(instrument, memory, attributes);
+            }
+        }
+        if let Some(instrument) = &self.memory_virtual {
+            let virtual_memory = self
+                .overflow
+                .to_i64(PROCESS_MEMORY_VIRTUAL, snapshot.virtual_memory);
+            if let Some(virtual_memory) = virtual_memory {
+                observer.observe_i64(instrument, virtual_memory, attributes);
+            }
+        }
+        if let Some(instrument) = &self.disk_io {
+            for (value, bytes) in [
+                ("read", snapshot.disk_read),
+                ("write", snapshot.disk_written),
+            ] {
+                observer.observe_u64(
+                    instrument,
+                    bytes,
+                    &[attributes, &[DIRECTION.string(value)]].concat(),
+                );
+            }
+        }
+    }
+
+    fn record_cpu(&self, cpu_usage: f32, attributes: &[KeyValue]) {
+        if let Some(instrument) = &self.cpu_utilization_histogram {
+            instrument.record(self.cpu_utilization(cpu_usage), attributes);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Delay accounting of the process from the taskstats interface of the
+//! generic netlink, see <https://docs.kernel.org/accounting/taskstats.html>.
+//!
+//! Requests need the `CAP_NET_ADMIN` capability, and the delays stay at zero
+//! unless delay accounting is enabled, with the `kernel.task_delayacct`
+//! sysctl or the `delayacct` boot parameter.
+
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+use crate::DelayTimes;
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const TASKSTATS_GENL_NAME: &[u8] = b"TASKSTATS\0";
+const TASKSTATS_GENL_VERSION: u8 = 1;
+const TASKSTATS_CMD_GET: u8 = 1;
+const TASKSTATS_CMD_ATTR_TGID: u16 = 2;
+const TASKSTATS_TYPE_STATS: u16 = 3;
+const TASKSTATS_TYPE_AGGR_TGID: u16 = 5;
+
+// Offsets in `struct taskstats`, stable across versions.
+const CPU_DELAY_TOTAL: usize = 24;
+const BLKIO_DELAY_TOTAL: usize = 40;
+const SWAPIN_DELAY_TOTAL: usize = 56;
+
+const NLMSG_HEADER_LEN: usize = 16;
+const GENL_HEADER_LEN: usize = 4;
+const NLA_HEADER_LEN: usize = 4;
+
+/// A generic netlink socket bound to the taskstats family.
+pub(crate) struct Taskstats {
+    socket: OwnedFd,
+    family: u16,
+    seq: u32,
+}
+
+impl Taskstats {
+    /// `None` if the socket could not be opened or the kernel has no
+    /// taskstats.
+    pub(crate) fn open() -> Option<Self> {
+        // SAFETY: socket has no preconditions.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_GENERIC,
+            )
+        };
+        if fd < 0 {
+            return None;
+        }
+        // The kernel answers every request, but a sample should never block.
+        let timeout = libc::timeval {
+            tv_sec: 1,
+            tv_usec: 0,
+        };
+        // SAFETY: `timeout` is valid for the duration of the call.
+        unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const _ as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        let mut taskstats = Self {
+            // SAFETY: `fd` was just opened and is owned by nothing else.
+            socket: unsafe { OwnedFd::from_raw_fd(fd) },
+            family: GENL_ID_CTRL,
+            seq: 0,
+        };
+        let reply = taskstats.request(
+            GENL_ID_CTRL,
+            CTRL_CMD_GETFAMILY,
+            CTRL_ATTR_FAMILY_NAME,
+            TASKSTATS_GENL_NAME,
+        )?;
+        let family = attributes(&reply).find(|(kind, _)| *kind == CTRL_ATTR_FAMILY_ID)?;
+        taskstats.family = u16::from_ne_bytes(family.1.get(..2)?.try_into().ok()?);
+        Some(taskstats)
+    }
+
+    /// Delays of all the threads of the process `pid`. `None` if the
+    /// request was denied or the process exited.
+    pub(crate) fn delays(&mut self, pid: u32) -> Option<DelayTimes> {
+        let reply = self.request(
+            self.family,
+            TASKSTATS_CMD_GET,
+            TASKSTATS_CMD_ATTR_TGID,
+            &pid.to_ne_bytes(),
+        )?;
+        let (_, aggregate) =
+            attributes(&reply).find(|(kind, _)| *kind == TASKSTATS_TYPE_AGGR_TGID)?;
+        let (_, stats) = attributes(aggregate).find(|(kind, _)| *kind == TASKSTATS_TYPE_STATS)?;
+        let nanos = |offset: usize| {
+            let bytes = stats.get(offset..offset + 8)?;
+            Some(Duration::from_nanos(u64::from_ne_bytes(
+                bytes.try_into().ok()?,
+            )))
+        };
+        Some(DelayTimes {
+            cpu: nanos(CPU_DELAY_TOTAL)?,
+            block_io: nanos(BLKIO_DELAY_TOTAL)?,
+            swap_in: nanos(SWAPIN_DELAY_TOTAL)?,
+        })
+    }
+
+    /// Send a command with a single attribute, and return the attributes of
+    /// the reply.
+    fn request(&mut self, family: u16, command: u8, kind: u16, value: &[u8]) -> Option<Vec<u8>> {
+        self.seq = self.seq.wrapping_add(1);
+        let attribute_len = NLA_HEADER_LEN + value.len();
+        let len = NLMSG_HEADER_LEN + GENL_HEADER_LEN + align(attribute_len);
+        let mut message = Vec::with_capacity(len);
+        message.extend_from_slice(&(len as u32).to_ne_bytes());
+        message.extend_from_slice(&family.to_ne_bytes());
+        message.extend_from_slice(&(libc::NLM_F_REQUEST as u16).to_ne_bytes());
+        message.extend_from_slice(&self.seq.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&[command, TASKSTATS_GENL_VERSION, 0, 0]);
+        message.extend_from_slice(&(attribute_len as u16).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(value);
+        message.resize(len, 0);
+
+        // SAFETY: `sockaddr_nl` is valid when zeroed, sending to the kernel.
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        // SAFETY: `message` and `address` are valid for the duration of the
+        // call.
+        let sent = unsafe {
+            libc::sendto(
+                self.socket.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+                &address as *const _ as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return None;
+        }
+
+        let mut reply = vec![0u8; 4096];
+        loop {
+            // SAFETY: `reply` is valid for `reply.len()` bytes.
+            let received = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    reply.as_mut_ptr() as *mut libc::c_void,
+                    reply.len(),
+                    0,
+                )
+            };
+            if received < NLMSG_HEADER_LEN as isize {
+                return None;
+            }
+            let reply = &reply[..received as usize];
+            let kind = u16::from_ne_bytes([reply[4], reply[5]]);
+            let seq = u32::from_ne_bytes(reply[8..12].try_into().ok()?);
+            // Replies to a previous request which timed out.
+            if seq != self.seq {
+                continue;
+            }
+            // Errors, such as a missing capability or an unknown process,
+            // are `NLMSG_ERROR` messages.
+            if kind != family {
+                return None;
+            }
+            let len = (u32::from_ne_bytes(reply[..4].try_into().ok()?) as usize).min(reply.len());
+            return reply
+                .get(NLMSG_HEADER_LEN + GENL_HEADER_LEN..len)
+                .map(<[u8]>::to_vec);
+        }
+    }
+}
+
+/// Netlink attributes are aligned on 4 bytes.
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// The type and value of the netlink attributes of `buffer`.
+fn attributes(mut buffer: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        let len = u16::from_ne_bytes(buffer.get(..2)?.try_into().ok()?) as usize;
+        let kind = u16::from_ne_bytes(buffer.get(2..4)?.try_into().ok()?);
+        let value = buffer.get(NLA_HEADER_LEN..len)?;
+        buffer = buffer.get(align(len)..).unwrap_or_default();
+        // Without the nested and byte order flags.
+        Some((kind & 0x3fff, value))
+    })
+}
@@ -0,0 +1,251 @@
+//! Helpers to test code registering process observers without depending on
+//! the processes of the host.
+//!
+//! [`MockSampler`] provides deterministic samples. With the `testing`
+//! feature, `MetricsHarness` collects the metrics recorded by observers in
+//! memory, to assert their names, units and attributes without any
+//! exporter.
+
+#[cfg(feature = "testing")]
+use std::sync::{Arc, Weak};
+
+#[cfg(feature = "testing")]
+use opentelemetry::metrics::{Meter, MeterProvider as _};
+#[cfg(feature = "testing")]
+use opentelemetry_sdk::metrics::data::{Metric, ResourceMetrics, Temporality};
+#[cfg(feature = "testing")]
+use opentelemetry_sdk::metrics::reader::{AggregationSelector, MetricReader, TemporalitySelector};
+#[cfg(feature = "testing")]
+use opentelemetry_sdk::metrics::{
+    Aggregation, InstrumentKind, ManualReader, Pipeline, SdkMeterProvider,
+};
+#[cfg(feature = "testing")]
+use opentelemetry_sdk::Resource;
+
+use crate::{CpuTimes, MemoryDetails, ProcessInfo, ProcessMetricsSnapshot, ProcessSampler};
+
+/// A [`ProcessSampler`] returning the same deterministic values on every
+/// sample.
+///
+/// Every value is set by default, so every instrument has a data point.
+/// # Example
+///
+/// ```
+/// use opentelemetry::global;
+/// use opentelemetry_system_metrics::testing::MockSampler;
+/// use opentelemetry_system_metrics::ProcessObserverBuilder;
+///
+/// let meter = global::meter("process-meter");
+/// ProcessObserverBuilder::new(meter)
+///     .with_sampler(MockSampler::new(42).exit_after(3))
+///     .init()
+///     .unwrap();
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct MockSampler {
+    pid: u32,
+    info: ProcessInfo,
+    snapshot: ProcessMetricsSnapshot,
+    // `None` never exits.
+    remaining_samples: Option<usize>,
+}
+
+impl MockSampler {
+    /// Values of the process are:
+    /// - CPU usage: 50% of a CPU, 1.5 seconds in user mode and 0.5 in
+    ///   system mode
+    /// - Memory: 64 MiB resident, 256 MiB virtual and 1 MiB swapped out
+    /// - Disk: 4 KiB read and 8 KiB written
+    /// - No GPU
+    pub fn new(pid: u32) -> Self {
+        Self {
+            pid,
+            info: ProcessInfo {
+                name: "mock".to_string(),
+                executable_path: "/usr/bin/mock".to_string(),
+                command: vec!["mock".to_string(), "--serve".to_string()],
+                ..Default::default()
+            },
+            snapshot: ProcessMetricsSnapshot {
+                cpu_usage: 50.0,
+                cpu_times: Some(CpuTimes {
+                    user: 1.5,
+                    system: 0.5,
+                }),
+                allowed_cpus: None,
+                nice: None,
+                priority_class: None,
+                memory: 64 << 20,
+                virtual_memory: 256 << 20,
+                swap: Some(1 << 20),
+                memory_details: MemoryDetails::default(),
+                handles: None,
+                gdi_objects: None,
+                user_objects: None,
+                disk_read: 4 << 10,
+                disk_written: 8 << 10,
+                gpus: Vec::new(),
+                threads: Vec::new(),
+                delays: None,
+                pressure: Vec::new(),
+                connections: Vec::new(),
+                memory_growth_rate: None,
+                disk_read_rate: None,
+                disk_write_rate: None,
+                zombie: false,
+            },
+            remaining_samples: None,
+        }
+    }
+
+    /// Replace the identity of the process.
+    pub fn with_info(mut self, info: ProcessInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Replace the values returned by every sample.
+    pub fn with_snapshot(mut self, snapshot: ProcessMetricsSnapshot) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
+
+    /// Report the exit of the process after `samples` samples.
+    pub fn exit_after(mut self, samples: usize) -> Self {
+        self.remaining_samples = Some(samples);
+        self
+    }
+}
+
+impl ProcessSampler for MockSampler {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn info(&mut self) -> Option<ProcessInfo> {
+        Some(self.info.clone())
+    }
+
+    fn sample(&mut self) -> Option<ProcessMetricsSnapshot> {
+        if let Some(remaining_samples) = &mut self.remaining_samples {
+            *remaining_samples = remaining_samples.checked_sub(1)?;
+        }
+        Some(self.snapshot.clone())
+    }
+
+    fn sample_cpu(&mut self) -> Option<f32> {
+        // Only whole samples count toward `exit_after`.
+        match self.remaining_samples {
+            Some(0) => None,
+            _ => Some(self.snapshot.cpu_usage),
+        }
+    }
+}
+
+/// A meter provider whose metrics are collected on demand, in memory.
+///
+/// Requires the `testing` feature.
+/// # Example
+///
+/// ```
+/// use opentelemetry_system_metrics::testing::{MetricsHarness, MockSampler};
+/// use opentelemetry_system_metrics::ProcessObserverBuilder;
+///
+/// let harness = MetricsHarness::new();
+/// ProcessObserverBuilder::new(harness.meter())
+///     .with_sampler(MockSampler::new(42))
+///     .init()
+///     .unwrap();
+///
+/// let metrics = harness.collect().unwrap();
+/// let memory = MetricsHarness::find(&metrics, "process.memory.usage").unwrap();
+/// assert_eq!(memory.unit.as_str(), "byte");
+/// ```
+///
+#[cfg(feature = "testing")]
+pub struct MetricsHarness {
+    provider: SdkMeterProvider,
+    reader: SharedReader,
+}
+
+#[cfg(feature = "testing")]
+impl MetricsHarness {
+    /// A provider with a single reader and cumulative temporality.
+    pub fn new() -> Self {
+        let reader = SharedReader(Arc::new(ManualReader::builder().build()));
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader.clone())
+            .build();
+        Self { provider, reader }
+    }
+
+    /// A meter of the provider, to give to the observers under test.
+    pub fn meter(&self) -> Meter {
+        self.provider.meter(crate::METER_NAME)
+    }
+
+    /// Run the callbacks of the observers and return what they recorded.
+    pub fn collect(&self) -> opentelemetry::metrics::Result<ResourceMetrics> {
+        let mut metrics = ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: Vec::new(),
+        };
+        self.reader.collect(&mut metrics)?;
+        Ok(metrics)
+    }
+
+    /// The collected metric named `name`, in any scope.
+    pub fn find<'a>(metrics: &'a ResourceMetrics, name: &str) -> Option<&'a Metric> {
+        metrics
+            .scope_metrics
+            .iter()
+            .flat_map(|scope| &scope.metrics)
+            .find(|metric| metric.name == name)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl Default for MetricsHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The reader is owned by the provider, shared to collect from the harness.
+#[cfg(feature = "testing")]
+#[derive(Debug, Clone)]
+struct SharedReader(Arc<ManualReader>);
+
+#[cfg(feature = "testing")]
+impl TemporalitySelector for SharedReader {
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        self.0.temporality(kind)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl AggregationSelector for SharedReader {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.0.aggregation(kind)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl MetricReader for SharedReader {
+    fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+        self.0.register_pipeline(pipeline)
+    }
+
+    fn collect(&self, metrics: &mut ResourceMetrics) -> opentelemetry::metrics::Result<()> {
+        self.0.collect(metrics)
+    }
+
+    fn force_flush(&self) -> opentelemetry::metrics::Result<()> {
+        self.0.force_flush()
+    }
+
+    fn shutdown(&self) -> opentelemetry::metrics::Result<()> {
+        self.0.shutdown()
+    }
+}
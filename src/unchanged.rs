@@ -0,0 +1,127 @@
+//! Suppression of the data points which did not change since they were last
+//! recorded, see [`crate::ProcessMetricsConfig::suppress_unchanged`].
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use opentelemetry::metrics::{AsyncInstrument, Observer};
+use opentelemetry::KeyValue;
+
+/// The value last recorded for every set of attributes of an instrument, and
+/// whether the attributes were seen in the current collection.
+type Points = Vec<(Vec<KeyValue>, f64, bool)>;
+
+/// The values last recorded by every instrument.
+pub(crate) struct LastValues {
+    epsilon: f64,
+    // By address of the instrument, which lives as long as the recorder.
+    values: Mutex<HashMap<usize, Points>>,
+}
+
+impl LastValues {
+    pub(crate) fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon,
+            values: Mutex::default(),
+        }
+    }
+
+    /// An observer recording on `observer` the data points which changed, for
+    /// a single collection: the values of the attributes it does not see are
+    /// dropped once it is.
+    pub(crate) fn observer<'a>(&'a self, observer: &'a dyn Observer) -> Unchanged<'a> {
+        Unchanged {
+            observer,
+            last: self,
+        }
+    }
+
+    /// Whether `value` changed by more than the epsilon, in which case it is
+    /// the new last value.
+    fn changed<T: ?Sized>(&self, instrument: &T, value: f64, attributes: &[KeyValue]) -> bool {
+        let key = instrument as *const T as *const () as usize;
+        let mut values = self.values.lock().unwrap();
+        let points = values.entry(key).or_default();
+        match points.iter_mut().find(|(last, ..)| last == attributes) {
+            Some((_, last, seen)) => {
+                *seen = true;
+                if (value - *last).abs() <= self.epsilon {
+                    return false;
+                }
+                *last = value;
+                true
+            }
+            None => {
+                points.push((attributes.to_vec(), value, true));
+                true
+            }
+        }
+    }
+
+    /// Drops the values not seen since the last call, such as those of the
+    /// exited threads or closed connections.
+    fn prune(&self) {
+        let mut values = self.values.lock().unwrap();
+        values.retain(|_, points| {
+            points.retain_mut(|(_, _, seen)| std::mem::take(seen));
+            !points.is_empty()
+        });
+    }
+}
+
+pub(crate) struct Unchanged<'a> {
+    observer: &'a dyn Observer,
+    last: &'a LastValues,
+}
+
+impl Drop for Unchanged<'_> {
+    fn drop(&mut self) {
+        self.last.prune();
+    }
+}
+
+impl Observer for Unchanged<'_> {
+    fn observe_f64(&self, inst: &dyn AsyncInstrument<f64>, measurement: f64, attrs: &[KeyValue]) {
+        if self.last.changed(inst, measurement, attrs) {
+            self.observer.observe_f64(inst, measurement, attrs);
+        }
+    }
+
+    fn observe_u64(&self, inst: &dyn AsyncInstrument<u64>, measurement: u64, attrs: &[KeyValue]) {
+        if self.last.changed(inst, measurement as f64, attrs) {
+            self.observer.observe_u64(inst, measurement, attrs);
+        }
+    }
+
+    fn observe_i64(&self, inst: &dyn AsyncInstrument<i64>, measurement: i64, attrs: &[KeyValue]) {
+        if self.last.changed(inst, measurement as f64, attrs) {
+            self.observer.observe_i64(inst, measurement, attrs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unseen_attributes_are_dropped() {
+        let last = LastValues::new(0.0);
+        let instrument = ();
+        let thread = |tid: i64| [KeyValue::new("thread.id", tid)];
+        assert!(last.changed(&instrument, 1.0, &thread(1)));
+        assert!(last.changed(&instrument, 1.0, &thread(2)));
+        last.prune();
+        // The thread 2 exited.
+        assert!(!last.changed(&instrument, 1.0, &thread(1)));
+        last.prune();
+        assert_eq!(
+            last.values.lock().unwrap()[&(&instrument as *const () as usize)].len(),
+            1
+        );
+        last.prune();
+        assert!(last.values.lock().unwrap().is_empty());
+        // Recorded again once it is seen again.
+        assert!(last.changed(&instrument, 1.0, &thread(2)));
+    }
+}
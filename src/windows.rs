@@ -0,0 +1,32 @@
+//! Windows specific readers for information sysinfo does not expose.
+
+use windows_sys::Win32::Foundation::CloseHandle;
+use windows_sys::Win32::System::Threading::{
+    GetGuiResources, GetProcessHandleCount, OpenProcess, GR_GDIOBJECTS, GR_USEROBJECTS,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+/// Kernel handles, GDI objects and USER objects open by the process, the
+/// Windows equivalents of file descriptors. `None` if the process is gone.
+pub(crate) fn handle_counts(pid: u32) -> Option<(u64, u64, u64)> {
+    // SAFETY: the handle is checked and closed below.
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle == 0 {
+        return None;
+    }
+    let mut handles = 0;
+    // SAFETY: `handle` is a valid process handle and `handles` a valid
+    // pointer.
+    let ok = unsafe { GetProcessHandleCount(handle, &mut handles) };
+    // SAFETY: `handle` is a valid process handle. Processes without a GUI
+    // have no objects, which is also the value on errors.
+    let (gdi_objects, user_objects) = unsafe {
+        (
+            GetGuiResources(handle, GR_GDIOBJECTS),
+            GetGuiResources(handle, GR_USEROBJECTS),
+        )
+    };
+    // SAFETY: `handle` was opened above and is not used afterwards.
+    unsafe { CloseHandle(handle) };
+    (ok != 0).then_some((handles.into(), gdi_objects.into(), user_objects.into()))
+}
@@ -0,0 +1,114 @@
+//! The metrics recorded by `ProcessObserverBuilder`, collected in memory.
+
+use opentelemetry::{Key, KeyValue, Value};
+use opentelemetry_sdk::metrics::data::{DataPoint, Gauge, ResourceMetrics, Sum};
+use opentelemetry_system_metrics::testing::{MetricsHarness, MockSampler};
+use opentelemetry_system_metrics::{
+    CommandAttribute, CpuNormalization, MetricGroups, ProcessMetricsSnapshot,
+    ProcessObserverBuilder,
+};
+
+fn points<'a, T: 'static>(metrics: &'a ResourceMetrics, name: &str) -> &'a [DataPoint<T>] {
+    let metric = MetricsHarness::find(metrics, name).unwrap_or_else(|| panic!("no {name}"));
+    let data = metric.data.as_any();
+    if let Some(gauge) = data.downcast_ref::<Gauge<T>>() {
+        &gauge.data_points
+    } else if let Some(sum) = data.downcast_ref::<Sum<T>>() {
+        &sum.data_points
+    } else {
+        panic!("{name} is neither a gauge nor a sum of the expected type")
+    }
+}
+
+fn attribute<'a, T>(point: &'a DataPoint<T>, key: &'static str) -> Option<&'a Value> {
+    let key = Key::from_static_str(key);
+    point
+        .attributes
+        .iter()
+        .find_map(|(k, value)| (*k == key).then_some(value))
+}
+
+#[test]
+fn records_the_samples() {
+    let harness = MetricsHarness::new();
+    ProcessObserverBuilder::new(harness.meter())
+        .with_sampler(MockSampler::new(42))
+        .with_cpu_normalization(CpuNormalization::None)
+        .with_attributes([KeyValue::new("service.name", "api")])
+        .init()
+        .unwrap();
+
+    let metrics = harness.collect().unwrap();
+    let memory = points::<i64>(&metrics, "process.memory.usage");
+    assert_eq!(memory.len(), 1);
+    assert_eq!(memory[0].value, 64 << 20);
+    assert_eq!(attribute(&memory[0], "process.pid"), Some(&Value::I64(42)));
+    assert_eq!(
+        attribute(&memory[0], "service.name"),
+        Some(&Value::from("api"))
+    );
+    assert_eq!(
+        attribute(&memory[0], "process.command"),
+        Some(&Value::from("mock --serve"))
+    );
+
+    let cpu = points::<f64>(&metrics, "process.cpu.usage");
+    assert_eq!(cpu[0].value, 50.0);
+
+    let mut disk: Vec<_> = points::<u64>(&metrics, "process.disk.io")
+        .iter()
+        .map(|point| (attribute(point, "direction").cloned(), point.value))
+        .collect();
+    disk.sort_by_key(|(_, bytes)| *bytes);
+    assert_eq!(
+        disk,
+        [
+            (Some(Value::from("read")), 4 << 10),
+            (Some(Value::from("write")), 8 << 10)
+        ]
+    );
+}
+
+#[test]
+fn applies_the_configuration() {
+    let harness = MetricsHarness::new();
+    ProcessObserverBuilder::new(harness.meter())
+        .with_sampler(MockSampler::new(42))
+        .with_metric_prefix("app.")
+        .with_metric_groups(MetricGroups {
+            disk: false,
+            ..Default::default()
+        })
+        .with_command_attribute(CommandAttribute::Omit)
+        .init()
+        .unwrap();
+
+    let metrics = harness.collect().unwrap();
+    assert!(MetricsHarness::find(&metrics, "process.memory.usage").is_none());
+    assert!(MetricsHarness::find(&metrics, "app.process.disk.io").is_none());
+    let memory = points::<i64>(&metrics, "app.process.memory.usage");
+    assert_eq!(memory[0].value, 64 << 20);
+    assert_eq!(attribute(&memory[0], "process.command"), None);
+}
+
+#[test]
+fn records_the_state_of_zombies() {
+    let harness = MetricsHarness::new();
+    ProcessObserverBuilder::new(harness.meter())
+        .with_sampler(MockSampler::new(42).with_snapshot(ProcessMetricsSnapshot {
+            zombie: true,
+            ..Default::default()
+        }))
+        .init()
+        .unwrap();
+
+    let metrics = harness.collect().unwrap();
+    let alive = points::<u64>(&metrics, "process.alive");
+    assert_eq!(alive.len(), 1);
+    assert_eq!(alive[0].value, 0);
+    assert_eq!(
+        attribute(&alive[0], "process.state"),
+        Some(&Value::from("defunct"))
+    );
+    assert!(MetricsHarness::find(&metrics, "process.memory.usage").is_none());
+}